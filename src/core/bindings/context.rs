@@ -245,9 +245,23 @@ pub trait Evaluate {
 }
 
 impl Evaluate for ContextRule {
-    fn evaluate(&self, _: &Core) -> bool {
-        // TODO: implement this
-        false
+    fn evaluate(&self, core: &Core) -> bool {
+        let has_panel = core.panel.is_some();
+        match *self {
+            // panel takes the input focus while it's visible
+            ContextRule::PanelVisibleEqual(value) |
+            ContextRule::PanelHasFocusEqual(value) => has_panel == value,
+            ContextRule::PanelEqual(ref operator, _) => match core.panel {
+                Some(ref panel) => operator.evaluate(panel),
+                None => false
+            },
+            // input of the panel is the only widget
+            ContextRule::Setting(ref name, ref operator) if name == "is_widget" => {
+                operator.evaluate(&Settings::Boolean(has_panel))
+            },
+            // TODO: implement other rules
+            _ => false
+        }
     }
 }
 
@@ -94,6 +94,29 @@ pub enum ParseKeymapError {
     ContextError(ParseContextError),
 }
 
+impl Hotkey {
+    pub fn new(key: Key, modifiers: Modifiers) -> Hotkey {
+        Hotkey {
+            key: key,
+            modifiers: modifiers
+        }
+    }
+
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+}
+
+impl From<Key> for Hotkey {
+    fn from(key: Key) -> Hotkey {
+        Hotkey::new(key, Modifiers::empty())
+    }
+}
+
 impl IntoIterator for HotkeySequence {
     type Item = Hotkey;
     type IntoIter = ::std::vec::IntoIter<Hotkey>;
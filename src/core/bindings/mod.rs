@@ -1,6 +1,11 @@
 mod context;
 mod keymap;
+mod mousemap;
 mod performer;
 
-pub use self::keymap::{Keymap, Key, ParseKeymapError};
+pub use self::keymap::{
+    Keymap, Key, Hotkey, Modifiers, ParseKeymapError,
+    MODIFIER_CTRL, MODIFIER_ALT, MODIFIER_SHIFT
+};
+pub use self::mousemap::{Mousemap, MouseBinding, MouseButton, ParseMousemapError};
 pub use self::performer::HotkeyPerformer;
@@ -0,0 +1,150 @@
+use std::str::FromStr;
+
+use core::command::{Command, ParseCommandError};
+use core::settings::{Settings, SettingsObject, ParseSettings};
+
+use super::keymap::Modifiers;
+
+use self::ParseMousemapError::*;
+
+pub type Mousemap = Vec<MouseBinding>;
+
+#[derive(Debug)]
+pub struct MouseBinding {
+    pub button: MouseButton,
+    pub count: u8,
+    pub modifiers: Modifiers,
+    /// Command performed when the button is pressed
+    pub press_command: Option<Command>,
+    /// Command performed when the button is released
+    pub command: Option<Command>
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MouseButton {
+    /// Button1 is the left button, button2 is the right one and button3 is the middle one
+    Button(u8),
+    ScrollUp,
+    ScrollDown
+}
+
+#[derive(Debug)]
+pub enum ParseMousemapError {
+    MousemapIsNotArray,
+    BindingIsNotObject,
+    ButtonIsNotString,
+    IncorrectButton(String),
+    CountIsNotNumber,
+    ModifiersIsNotArray,
+    ModifierIsNotString,
+    IncorrectModifier(String),
+    CommandError(ParseCommandError)
+}
+
+impl MouseBinding {
+    pub fn matches(&self, button: MouseButton, count: u8, modifiers: Modifiers) -> bool {
+        self.button == button && self.count == count && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for MouseButton {
+    type Err = ParseMousemapError;
+
+    fn from_str(s: &str) -> Result<MouseButton, Self::Err> {
+        match s {
+            "scroll_up" => Ok(MouseButton::ScrollUp),
+            "scroll_down" => Ok(MouseButton::ScrollDown),
+            _ if s.starts_with("button") => match u8::from_str(&s[6..]) {
+                Ok(i) if 1 <= i && i <= 16 => Ok(MouseButton::Button(i)),
+                _ => Err(IncorrectButton(s.to_owned()))
+            },
+            _ => Err(IncorrectButton(s.to_owned()))
+        }
+    }
+}
+
+/// Parses command stored under `command_key` and `args_key` keys of the binding.
+fn parse_command(obj: &mut SettingsObject, command_key: &str, args_key: &str)
+        -> Result<Option<Command>, ParseMousemapError> {
+    let name = match obj.remove(command_key) {
+        Some(name) => name,
+        None => return Ok(None)
+    };
+    let mut command = SettingsObject::new();
+    command.insert("command".to_owned(), name);
+    if let Some(args) = obj.remove(args_key) {
+        command.insert("args".to_owned(), args);
+    }
+    match Command::parse_settings(Settings::Object(command)) {
+        Ok(command) => Ok(Some(command)),
+        Err(err) => Err(CommandError(err))
+    }
+}
+
+impl ParseSettings for MouseBinding {
+    type Error = ParseMousemapError;
+
+    fn parse_settings(settings: Settings) -> Result<MouseBinding, Self::Error> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(BindingIsNotObject)
+        };
+
+        let button = match obj.remove("button") {
+            Some(Settings::String(s)) => try!(MouseButton::from_str(&s)),
+            _ => return Err(ButtonIsNotString)
+        };
+
+        let count = match obj.remove("count") {
+            Some(count) => match count.as_u64() {
+                Some(count) => count as u8,
+                None => return Err(CountIsNotNumber)
+            },
+            None => 1
+        };
+
+        let mut modifiers = Modifiers::empty();
+        match obj.remove("modifiers") {
+            Some(Settings::Array(arr)) => {
+                for modifier in arr {
+                    match modifier {
+                        Settings::String(s) => match Modifiers::from_str(&s) {
+                            Ok(modifier) => modifiers.insert(modifier),
+                            Err(_) => return Err(IncorrectModifier(s))
+                        },
+                        _ => return Err(ModifierIsNotString)
+                    }
+                }
+            },
+            None => {},
+            _ => return Err(ModifiersIsNotArray)
+        }
+
+        let press_command = try!(parse_command(&mut obj, "press_command", "press_args"));
+        let command = try!(parse_command(&mut obj, "command", "args"));
+
+        Ok(MouseBinding {
+            button: button,
+            count: count,
+            modifiers: modifiers,
+            press_command: press_command,
+            command: command
+        })
+    }
+}
+
+impl ParseSettings for Mousemap {
+    type Error = ParseMousemapError;
+
+    fn parse_settings(settings: Settings) -> Result<Mousemap, ParseMousemapError> {
+        let arr = match settings {
+            Settings::Array(arr) => arr,
+            _ => return Err(MousemapIsNotArray)
+        };
+        let mut mousemap = Mousemap::new();
+        for settings in arr {
+            mousemap.push(try!(MouseBinding::parse_settings(settings)))
+        }
+        Ok(mousemap)
+    }
+}
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 
 use core::Core;
@@ -16,7 +17,7 @@ struct PerformerNode {
 
 #[derive(Default, Debug)]
 pub struct HotkeyPerformer {
-    node_id: PerformerNodeId,
+    node_id: Cell<PerformerNodeId>,
     nodes: Vec<PerformerNode>,
     hotkeys: HashMap<Command, HotkeySequence>
 }
@@ -24,7 +25,7 @@ pub struct HotkeyPerformer {
 impl HotkeyPerformer {
     pub fn new() -> HotkeyPerformer {
         HotkeyPerformer {
-            node_id: 0,
+            node_id: Cell::new(0),
             nodes: vec![PerformerNode::default()],
             hotkeys: HashMap::new()
         }
@@ -50,20 +51,21 @@ impl HotkeyPerformer {
         self.hotkeys.get(command)
     }
 
-    pub fn perform_hotkey(&mut self, core: &Core, hotkey: &Hotkey) -> Option<Command> {
-        if let Some(&node_id) = self.nodes[self.node_id].children.get(hotkey) {
+    pub fn perform_hotkey(&self, core: &Core, hotkey: &Hotkey) -> Option<Command> {
+        if let Some(&node_id) = self.nodes[self.node_id.get()].children.get(hotkey) {
             // Check whether there are commands that binded to the current state
-            // If yes, return it
-            for &(ref command, ref context) in &self.nodes[node_id].commands {
+            // If yes, return it. Later bindings override earlier ones
+            for &(ref command, ref context) in self.nodes[node_id].commands.iter().rev() {
                 if context.evaluate(core) {
+                    self.node_id.set(0);
                     return Some((*command).clone());
                 }
             }
             // If no, change current state
-            self.node_id = node_id;
+            self.node_id.set(node_id);
         } else {
             // There's no node for this hotkey, so we reset current status
-            self.node_id = 0;
+            self.node_id.set(0);
         }
         None
     }
@@ -0,0 +1,494 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{BufReader, BufRead, Error as IoError};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use core::diff::LineDiff;
+use core::region::{Point, Region};
+use core::syntax::{Parser, ParserState, Highlighter, HighlightIterator};
+use core::symbols::{Symbol, SymbolList};
+
+pub type BufferId = usize;
+
+static NEXT_BUFFER_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+#[derive(Debug)]
+pub struct Buffer {
+    id: BufferId,
+    path: Option<PathBuf>,
+    /// Name of the buffer without a file, like `Find Results`
+    name: Option<String>,
+    pub lines: Vec<Line>,
+    parser: Option<Parser>,
+    is_dirty: bool,
+    /// Scratch buffer is never dirty, its text is produced by commands
+    is_scratch: bool,
+    /// Changed lines of the file compared to git HEAD
+    diff: Option<LineDiff>,
+    /// Changes of the current undo step, it's finished by `commit`
+    changes: Vec<Change>,
+    undo_stack: Vec<Vec<Change>>,
+    redo_stack: Vec<Vec<Change>>
+}
+
+#[derive(Debug)]
+pub struct Line {
+    pub text: String,
+    parser_state: ParserState,
+}
+
+/// Describes replacement of the text between `begin` and `old_end` by the text,
+/// that ends at `new_end` now.
+#[derive(Debug, Clone, Copy)]
+pub struct Edit {
+    pub begin: Point,
+    pub old_end: Point,
+    pub new_end: Point
+}
+
+/// Edit with the replaced and inserted text, so it can be undone and redone.
+#[derive(Debug)]
+struct Change {
+    edit: Edit,
+    old_text: String,
+    new_text: String
+}
+
+impl Line {
+    fn new(text: String) -> Line {
+        Line {
+            text: text,
+            parser_state: ParserState::new()
+        }
+    }
+
+    pub fn parse(&mut self, parser: &mut Parser, state: &mut ParserState) {
+        self.parser_state = state.clone();
+        parser.parse(&self.text, state);
+        self.parser_state.swap_changes(state);
+    }
+
+    pub fn highlight<'a>(&'a self, highlighter: &'a Highlighter) -> HighlightIterator<'a> {
+        HighlightIterator::new(
+            self.parser_state.scope_path.clone(),
+            &self.parser_state.changes,
+            &self.text,
+            highlighter
+        )
+    }
+
+    fn symbols(&self, line: usize, list: &SymbolList, symbols: &mut Vec<Symbol>) {
+        list.line_symbols(line, &self.text, &self.parser_state.scope_path,
+                          &self.parser_state.changes, symbols)
+    }
+}
+
+impl Edit {
+    /// Returns new position of the point after this edit.
+    pub fn transform(&self, point: Point) -> Point {
+        if point < self.old_end {
+            if point < self.begin {
+                point
+            } else {
+                // point was inside replaced text
+                self.new_end
+            }
+        } else if point.line == self.old_end.line {
+            Point::new(self.new_end.line, self.new_end.column + point.column - self.old_end.column)
+        } else {
+            Point::new(point.line + self.new_end.line - self.old_end.line, point.column)
+        }
+    }
+}
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer {
+            id: NEXT_BUFFER_ID.fetch_add(1, Ordering::SeqCst),
+            path: None,
+            name: None,
+            lines: vec![Line::new(String::new())],
+            parser: None,
+            is_dirty: false,
+            is_scratch: false,
+            diff: None,
+            changes: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new()
+        }
+    }
+
+    pub fn open(path: PathBuf) -> Result<Buffer, IoError> {
+        let mut lines = Vec::new();
+        for text in BufReader::new(try!(File::open(&path))).lines() {
+            lines.push(Line::new(try!(text)));
+        }
+        if lines.is_empty() {
+            lines.push(Line::new(String::new()));
+        }
+        let mut buffer = Buffer::new();
+        buffer.path = Some(path);
+        buffer.lines = lines;
+        Ok(buffer)
+    }
+
+    /// Creates the buffer of the file with the text that was read and decoded beforehand.
+    /// Lines are split the same way as in `open`.
+    pub fn from_text(path: PathBuf, text: &str) -> Buffer {
+        let mut lines: Vec<Line> = text.lines().map(|line| Line::new(line.to_owned())).collect();
+        if lines.is_empty() {
+            lines.push(Line::new(String::new()));
+        }
+        let mut buffer = Buffer::new();
+        buffer.path = Some(path);
+        buffer.lines = lines;
+        buffer
+    }
+
+    /// Replaces the text by the contents of the file, so the reload can be undone.
+    /// Returns None if the file has the same text.
+    pub fn reload(&mut self) -> Result<Option<Edit>, IoError> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Ok(None)
+        };
+        let mut lines = Vec::new();
+        for text in BufReader::new(try!(File::open(&path))).lines() {
+            lines.push(try!(text));
+        }
+        let text = lines.join("\n");
+        let (begin, end) = (Point::new(0, 0), self.last_point());
+        if self.substr(begin, end) == text {
+            return Ok(None)
+        }
+        self.commit();
+        let edit = self.replace(begin, end, &text);
+        self.commit();
+        self.is_dirty = false;
+        Ok(Some(edit))
+    }
+
+    /// Creates named buffer without a file, that is never dirty.
+    pub fn scratch(name: &str) -> Buffer {
+        let mut buffer = Buffer::new();
+        buffer.name = Some(name.to_owned());
+        buffer.is_scratch = true;
+        buffer
+    }
+
+    pub fn id(&self) -> BufferId {
+        self.id
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(|p| p.as_path())
+    }
+
+    /// Changes the path after the file was renamed, the text stays unchanged.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = Some(path);
+    }
+
+    pub fn name(&self) -> Cow<str> {
+        match self.path {
+            Some(ref path) => match path.file_name() {
+                Some(s) => s.to_string_lossy(),
+                None => path.to_string_lossy()
+            },
+            None => match self.name {
+                Some(ref name) => Cow::Borrowed(name),
+                None => Cow::Borrowed("untitled")
+            }
+        }
+    }
+
+    /// Returns true for the named buffer without a file, like results of find in files.
+    pub fn is_scratch(&self) -> bool {
+        self.is_scratch
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty && !self.is_scratch
+    }
+
+    /// Sets the text the changed lines are compared to, or removes it.
+    pub fn set_diff_base(&mut self, base: Option<Vec<String>>) {
+        self.diff = base.map(|mut base| {
+            // empty file has one empty line, like the opened one
+            if base.is_empty() {
+                base.push(String::new());
+            }
+            LineDiff::new(base, &self.lines)
+        });
+    }
+
+    pub fn diff(&self) -> Option<&LineDiff> {
+        self.diff.as_ref()
+    }
+
+    /// Sets parser for the buffer and parses all lines.
+    pub fn set_parser(&mut self, parser: Parser) {
+        self.parser = Some(parser);
+        self.parse_lines(0, self.lines.len());
+    }
+
+    /// Removes the parser from the buffer, so it can be reused for another one.
+    pub fn take_parser(&mut self) -> Option<Parser> {
+        self.parser.take()
+    }
+
+    /// Parses lines starting from `from`. Lines after `until` are parsed only while parser state
+    /// at the beginning of the line differs from the previous one.
+    fn parse_lines(&mut self, from: usize, until: usize) {
+        let parser = match self.parser {
+            Some(ref mut parser) => parser,
+            None => return
+        };
+        let mut state = self.lines[from].parser_state.clone();
+        state.changes.clear();
+        for index in from..self.lines.len() {
+            if index > until && self.lines[index].parser_state.same_context(&state) {
+                break
+            }
+            self.lines[index].parse(parser, &mut state);
+        }
+    }
+
+    pub fn line_len(&self, line: usize) -> usize {
+        self.lines[line].text.len()
+    }
+
+    pub fn last_point(&self) -> Point {
+        let line = self.lines.len() - 1;
+        Point::new(line, self.line_len(line))
+    }
+
+    /// Returns the point of the previous character, possibly on the previous line.
+    pub fn prev_point(&self, point: Point) -> Point {
+        if point.column > 0 {
+            let text = &self.lines[point.line].text;
+            let mut column = point.column - 1;
+            while !text.is_char_boundary(column) {
+                column -= 1;
+            }
+            Point::new(point.line, column)
+        } else if point.line > 0 {
+            Point::new(point.line - 1, self.line_len(point.line - 1))
+        } else {
+            point
+        }
+    }
+
+    /// Returns the point of the next character, possibly on the next line.
+    pub fn next_point(&self, point: Point) -> Point {
+        let text = &self.lines[point.line].text;
+        if point.column < text.len() {
+            let mut column = point.column + 1;
+            while !text.is_char_boundary(column) {
+                column += 1;
+            }
+            Point::new(point.line, column)
+        } else if point.line + 1 < self.lines.len() {
+            Point::new(point.line + 1, 0)
+        } else {
+            point
+        }
+    }
+
+    /// Returns the nearest valid point on the given line.
+    pub fn clip_point(&self, line: usize, column: usize) -> Point {
+        let line = ::std::cmp::min(line, self.lines.len() - 1);
+        let text = &self.lines[line].text;
+        let mut column = ::std::cmp::min(column, text.len());
+        while !text.is_char_boundary(column) {
+            column -= 1;
+        }
+        Point::new(line, column)
+    }
+
+    /// Returns region of the word around the point. If there is no word,
+    /// the region of the character after the point is returned.
+    pub fn word_region(&self, point: Point) -> Region {
+        let text = &self.lines[point.line].text;
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        let after = text[point.column..].chars().next();
+        let before = text[..point.column].chars().next_back();
+        if !after.map_or(false, &is_word_char) && !before.map_or(false, &is_word_char) {
+            let next = if after.is_some() { self.next_point(point) } else { point };
+            return Region::new(point, next);
+        }
+        let begin = text[..point.column].char_indices().rev()
+            .take_while(|&(_, c)| is_word_char(c))
+            .last()
+            .map_or(point.column, |(i, _)| i);
+        let end = text[point.column..].char_indices()
+            .find(|&(_, c)| !is_word_char(c))
+            .map_or(text.len(), |(i, _)| point.column + i);
+        Region::new(Point::new(point.line, begin), Point::new(point.line, end))
+    }
+
+    /// Returns region of the whole line including the line break.
+    pub fn line_region(&self, line: usize) -> Region {
+        let end = if line + 1 < self.lines.len() {
+            Point::new(line + 1, 0)
+        } else {
+            Point::new(line, self.line_len(line))
+        };
+        Region::new(Point::new(line, 0), end)
+    }
+
+    /// Returns region of the first occurrence of the text within a line. Case of ASCII
+    /// characters is ignored unless `case_sensitive` is set.
+    pub fn find_text(&self, text: &str, case_sensitive: bool, whole_word: bool) -> Option<Region> {
+        if text.is_empty() {
+            return None
+        }
+        let text = if case_sensitive { text.to_owned() } else { text.to_ascii_lowercase() };
+        let is_word_char = |c: Option<char>| c.map_or(false, |c| c.is_alphanumeric() || c == '_');
+        for (index, line) in self.lines.iter().enumerate() {
+            let haystack = if case_sensitive {
+                Cow::Borrowed(&line.text[..])
+            } else {
+                Cow::Owned(line.text.to_ascii_lowercase())
+            };
+            for (begin, _) in haystack.match_indices(&text[..]) {
+                let end = begin + text.len();
+                if whole_word && (is_word_char(haystack[..begin].chars().next_back()) ||
+                                  is_word_char(haystack[end..].chars().next())) {
+                    continue
+                }
+                return Some(Region::new(Point::new(index, begin), Point::new(index, end)))
+            }
+        }
+        None
+    }
+
+    /// Returns symbols defined in the buffer.
+    pub fn symbols(&self, list: &SymbolList) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            line.symbols(index, list, &mut symbols);
+        }
+        symbols
+    }
+
+    pub fn substr(&self, begin: Point, end: Point) -> String {
+        if begin.line == end.line {
+            return self.lines[begin.line].text[begin.column..end.column].to_owned()
+        }
+        let mut s = self.lines[begin.line].text[begin.column..].to_owned();
+        for line in &self.lines[begin.line + 1..end.line] {
+            s.push('\n');
+            s.push_str(&line.text);
+        }
+        s.push('\n');
+        s.push_str(&self.lines[end.line].text[..end.column]);
+        s
+    }
+
+    /// Replaces the text between `begin` and `end` by the given text.
+    /// The change is added to the current undo step.
+    pub fn replace(&mut self, begin: Point, end: Point, text: &str) -> Edit {
+        let old_text = self.substr(begin, end);
+        let edit = self.apply(begin, end, text);
+        self.changes.push(Change {
+            edit: edit,
+            old_text: old_text,
+            new_text: text.to_owned()
+        });
+        edit
+    }
+
+    fn apply(&mut self, begin: Point, end: Point, text: &str) -> Edit {
+        let head = self.lines[begin.line].text[..begin.column].to_owned();
+        let tail = self.lines[end.line].text[end.column..].to_owned();
+        let mut new_lines: Vec<String> = text.split('\n').map(|s| s.to_owned()).collect();
+        let last = new_lines.len() - 1;
+        let new_end = if last == 0 {
+            Point::new(begin.line, begin.column + new_lines[0].len())
+        } else {
+            Point::new(begin.line + last, new_lines[last].len())
+        };
+        new_lines[0] = head + &new_lines[0];
+        new_lines[last].push_str(&tail);
+        let rest = self.lines.split_off(end.line + 1);
+        // keep parser state of the first line to continue parsing from it
+        let state = self.lines[begin.line].parser_state.clone();
+        self.lines.truncate(begin.line);
+        self.lines.extend(new_lines.into_iter().map(Line::new));
+        self.lines.extend(rest);
+        self.lines[begin.line].parser_state = state;
+        self.is_dirty = true;
+        self.parse_lines(begin.line, new_end.line);
+        let edit = Edit {
+            begin: begin,
+            old_end: end,
+            new_end: new_end
+        };
+        if let Some(ref mut diff) = self.diff {
+            diff.update(&edit, &self.lines);
+        }
+        edit
+    }
+
+    pub fn insert(&mut self, point: Point, text: &str) -> Edit {
+        self.replace(point, point, text)
+    }
+
+    pub fn erase(&mut self, begin: Point, end: Point) -> Edit {
+        self.replace(begin, end, "")
+    }
+
+    /// Appends the text to the end of the buffer, it can't be undone.
+    pub fn append(&mut self, text: &str) -> Edit {
+        let end = self.last_point();
+        self.apply(end, end, text)
+    }
+
+    /// Removes all the text and the undo history.
+    pub fn clear(&mut self) {
+        self.lines = vec![Line::new(String::new())];
+        self.diff = None;
+        self.changes.clear();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Finishes the current undo step. Changes made after undoing can't be redone anymore.
+    pub fn commit(&mut self) {
+        if self.changes.is_empty() {
+            return
+        }
+        let changes = ::std::mem::replace(&mut self.changes, Vec::new());
+        self.undo_stack.push(changes);
+        self.redo_stack.clear();
+    }
+
+    /// Reverts the last undo step. Returns edits that reverted it.
+    pub fn undo(&mut self) -> Vec<Edit> {
+        self.commit();
+        let changes = match self.undo_stack.pop() {
+            Some(changes) => changes,
+            None => return Vec::new()
+        };
+        let edits = changes.iter().rev().map(|change| {
+            self.apply(change.edit.begin, change.edit.new_end, &change.old_text)
+        }).collect();
+        self.redo_stack.push(changes);
+        edits
+    }
+
+    /// Performs the last undone step again. Returns edits that performed it.
+    pub fn redo(&mut self) -> Vec<Edit> {
+        let changes = match self.redo_stack.pop() {
+            Some(changes) => changes,
+            None => return Vec::new()
+        };
+        let edits = changes.iter().map(|change| {
+            self.apply(change.edit.begin, change.edit.old_end, &change.new_text)
+        }).collect();
+        self.undo_stack.push(changes);
+        edits
+    }
+}
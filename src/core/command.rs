@@ -9,6 +9,15 @@ pub struct Command {
     pub args: SettingsObject
 }
 
+impl Command {
+    pub fn new(name: &str, args: SettingsObject) -> Command {
+        Command {
+            name: name.to_owned(),
+            args: args
+        }
+    }
+}
+
 impl Eq for Command {}
 
 impl Hash for Command {
@@ -0,0 +1,60 @@
+use core::settings::{Settings, ParseSettings};
+use core::command::{Command, ParseCommandError};
+
+use self::ParseCommandListError::*;
+
+/// Commands available in the command palette, read from `.sublime-commands` files.
+pub type CommandList = Vec<CommandListItem>;
+
+#[derive(Debug)]
+pub struct CommandListItem {
+    pub caption: String,
+    pub command: Command
+}
+
+#[derive(Debug)]
+pub enum ParseCommandListError {
+    CommandListIsNotArray,
+    ItemIsNotObject,
+    CaptionIsNotString,
+    CommandError(ParseCommandError)
+}
+
+impl ParseSettings for CommandListItem {
+    type Error = ParseCommandListError;
+
+    fn parse_settings(settings: Settings) -> Result<CommandListItem, Self::Error> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(ItemIsNotObject)
+        };
+        let caption = match obj.remove("caption") {
+            Some(Settings::String(caption)) => caption,
+            _ => return Err(CaptionIsNotString)
+        };
+        let command = match Command::parse_settings(Settings::Object(obj)) {
+            Ok(command) => command,
+            Err(err) => return Err(CommandError(err))
+        };
+        Ok(CommandListItem {
+            caption: caption,
+            command: command
+        })
+    }
+}
+
+impl ParseSettings for CommandList {
+    type Error = ParseCommandListError;
+
+    fn parse_settings(settings: Settings) -> Result<CommandList, ParseCommandListError> {
+        let arr = match settings {
+            Settings::Array(arr) => arr,
+            _ => return Err(CommandListIsNotArray)
+        };
+        let mut list = CommandList::new();
+        for settings in arr {
+            list.push(try!(CommandListItem::parse_settings(settings)))
+        }
+        Ok(list)
+    }
+}
@@ -0,0 +1,284 @@
+use std::cmp::{min, max};
+
+use core::buffer::{Edit, Line};
+use core::region::Point;
+
+/// Largest number of the inserted and deleted lines the lines are compared for. Time and
+/// memory of the comparison grow with it, so more different lines are marked as a whole.
+const MAX_EDIT_STEPS: isize = 1000;
+
+/// Lines of the base text starting from `old_start` that were replaced by the lines
+/// of the buffer starting from `new_start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize
+}
+
+/// Marker of the changed line shown in the gutter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineMarker {
+    Added,
+    Modified,
+    /// Lines were deleted after this line, or before the first line
+    Deleted
+}
+
+/// Difference between the lines of the buffer and the base text, like the version
+/// of the file in git HEAD.
+#[derive(Debug)]
+pub struct LineDiff {
+    base: Vec<String>,
+    hunks: Vec<Hunk>
+}
+
+impl Hunk {
+    /// Returns the first and the last marked line.
+    fn marked_lines(&self) -> (usize, usize) {
+        if self.new_len == 0 {
+            let line = self.new_start.saturating_sub(1);
+            (line, line)
+        } else {
+            (self.new_start, self.new_start + self.new_len - 1)
+        }
+    }
+
+    /// Returns the first marked line.
+    pub fn line(&self) -> usize {
+        self.marked_lines().0
+    }
+
+    /// Returns true if some marked line is between the lines inclusive.
+    pub fn touches(&self, first: usize, last: usize) -> bool {
+        let (begin, end) = self.marked_lines();
+        begin <= last && end >= first
+    }
+}
+
+impl LineDiff {
+    pub fn new(base: Vec<String>, lines: &[Line]) -> LineDiff {
+        let hunks = {
+            let old: Vec<&str> = base.iter().map(|line| &line[..]).collect();
+            let new: Vec<&str> = lines.iter().map(|line| &line.text[..]).collect();
+            diff(&old, &new, 0, 0)
+        };
+        LineDiff {
+            base: base,
+            hunks: hunks
+        }
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /// Updates the hunks after the edit. Only the edited lines and the hunks that touch them
+    /// are compared again, following hunks are shifted.
+    pub fn update(&mut self, edit: &Edit, lines: &[Line]) {
+        // edited lines of the buffer before the edit
+        let (mut start, mut end) = (edit.begin.line, edit.old_end.line + 1);
+        let first = self.hunks.iter()
+            .position(|hunk| hunk.new_start + hunk.new_len >= start)
+            .unwrap_or(self.hunks.len());
+        let mut last = first;
+        while last < self.hunks.len() && self.hunks[last].new_start <= end {
+            let hunk = self.hunks[last];
+            start = min(start, hunk.new_start);
+            end = max(end, hunk.new_start + hunk.new_len);
+            last += 1;
+        }
+        // lines outside of the hunks are equal, so they're shifted by the preceding hunks
+        let shift = |hunks: &[Hunk]| hunks.iter().fold(0, |shift, hunk| {
+            shift + hunk.old_len as isize - hunk.new_len as isize
+        });
+        let old_start = (start as isize + shift(&self.hunks[..first])) as usize;
+        let old_end = (end as isize + shift(&self.hunks[..last])) as usize;
+        let delta = edit.new_end.line as isize - edit.old_end.line as isize;
+        let new_end = (end as isize + delta) as usize;
+        let hunks = {
+            let old: Vec<&str> = self.base[old_start..old_end].iter()
+                .map(|line| &line[..])
+                .collect();
+            let new: Vec<&str> = lines[start..new_end].iter()
+                .map(|line| &line.text[..])
+                .collect();
+            diff(&old, &new, old_start, start)
+        };
+        let rest = self.hunks.split_off(last);
+        self.hunks.truncate(first);
+        self.hunks.extend(hunks);
+        self.hunks.extend(rest.into_iter().map(|hunk| Hunk {
+            new_start: (hunk.new_start as isize + delta) as usize,
+            ..hunk
+        }));
+    }
+
+    /// Returns the marker of the line, if it's changed.
+    pub fn marker(&self, line: usize) -> Option<LineMarker> {
+        for hunk in &self.hunks {
+            if hunk.line() > line {
+                break
+            }
+            if hunk.touches(line, line) {
+                return Some(match (hunk.old_len, hunk.new_len) {
+                    (_, 0) => LineMarker::Deleted,
+                    (0, _) => LineMarker::Added,
+                    _ => LineMarker::Modified
+                })
+            }
+        }
+        None
+    }
+
+    /// Returns the replacement of the changed lines by the lines of the base text.
+    pub fn revert(&self, hunk: &Hunk, lines: &[Line]) -> (Point, Point, String) {
+        let base = &self.base[hunk.old_start..hunk.old_start + hunk.old_len];
+        let text = base.join("\n");
+        let (start, end) = (hunk.new_start, hunk.new_start + hunk.new_len);
+        let line_end = |line: usize| Point::new(line, lines[line].text.len());
+        if start < end && !base.is_empty() {
+            (Point::new(start, 0), line_end(end - 1), text)
+        } else if end < lines.len() {
+            // lines are followed by another one, so it keeps its line break
+            let text = if base.is_empty() { text } else { text + "\n" };
+            (Point::new(start, 0), Point::new(end, 0), text)
+        } else if start > 0 {
+            // lines at the end of the buffer are preceded by the line break
+            let text = if base.is_empty() { text } else { format!("\n{}", text) };
+            (line_end(start - 1), line_end(lines.len() - 1), text)
+        } else {
+            (Point::new(0, 0), line_end(lines.len() - 1), text)
+        }
+    }
+}
+
+/// Returns hunks of the difference between the lines. Offsets are added to the line indices.
+fn diff(old: &[&str], new: &[&str], old_offset: usize, new_offset: usize) -> Vec<Hunk> {
+    // common lines around the changes are skipped
+    let prefix = old.iter().zip(new).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let mut pairs = common_lines(old, new).unwrap_or_default();
+    pairs.push((old.len(), new.len()));
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (next_x, next_y) in pairs {
+        if next_x > x || next_y > y {
+            hunks.push(Hunk {
+                old_start: old_offset + prefix + x,
+                old_len: next_x - x,
+                new_start: new_offset + prefix + y,
+                new_len: next_y - y
+            });
+        }
+        x = next_x + 1;
+        y = next_y + 1;
+    }
+    hunks
+}
+
+/// Returns indices of the equal lines of the longest common subsequence, found by
+/// the Myers algorithm. Returns None if the lines differ by more than `MAX_EDIT_STEPS`.
+fn common_lines(old: &[&str], new: &[&str]) -> Option<Vec<(usize, usize)>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    // furthest x of each diagonal k = x - y
+    let mut v = vec![0; 2 * offset as usize + 1];
+    // diagonals -d..d reached by each step d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut is_found = false;
+    'search: for d in 0..min(n + m, MAX_EDIT_STEPS) + 1 {
+        let mut k = -d;
+        while k <= d {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                is_found = true;
+                trace.push(v[(offset - d) as usize..(offset + d + 1) as usize].to_vec());
+                break 'search
+            }
+            k += 2;
+        }
+        trace.push(v[(offset - d) as usize..(offset + d + 1) as usize].to_vec());
+    }
+    if !is_found {
+        return None
+    }
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..trace.len() as isize).rev() {
+        let k = x - y;
+        // start of the diagonal that was followed after the insertion or the deletion
+        let (prev_x, prev_y, start_x, start_y) = if d == 0 {
+            (0, 0, 0, 0)
+        } else {
+            let prev = &trace[d as usize - 1];
+            let get = |k: isize| prev[(k + d - 1) as usize];
+            if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+                let prev_x = get(k + 1);
+                (prev_x, prev_x - k - 1, prev_x, prev_x - k)
+            } else {
+                let prev_x = get(k - 1);
+                (prev_x, prev_x - k + 1, prev_x + 1, prev_x - k + 1)
+            }
+        };
+        while x > start_x && y > start_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    pairs.reverse();
+    Some(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hunk, diff};
+
+    fn hunk(old_start: usize, old_len: usize, new_start: usize, new_len: usize) -> Hunk {
+        Hunk {
+            old_start: old_start,
+            old_len: old_len,
+            new_start: new_start,
+            new_len: new_len
+        }
+    }
+
+    #[test]
+    fn changed_lines() {
+        let old = ["a", "b", "c", "d", "e"];
+        let new = ["a", "x", "c", "e", "f"];
+        assert_eq!(diff(&old, &new, 0, 0),
+                   vec![hunk(1, 1, 1, 1), hunk(3, 1, 3, 0), hunk(5, 0, 4, 1)]);
+        assert_eq!(diff(&old, &old, 0, 0), vec![]);
+    }
+
+    #[test]
+    fn rewritten_lines() {
+        let old: Vec<String> = (0..20000).map(|i| format!("old {}", i)).collect();
+        let new: Vec<String> = (0..20000).map(|i| format!("new {}", i)).collect();
+        let old: Vec<&str> = old.iter().map(|line| &line[..]).collect();
+        let mut new: Vec<&str> = new.iter().map(|line| &line[..]).collect();
+        new[0] = old[0];
+        // too different lines are a single hunk
+        assert_eq!(diff(&old, &new, 0, 0), vec![hunk(1, 19999, 1, 19999)]);
+    }
+}
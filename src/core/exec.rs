@@ -0,0 +1,360 @@
+use std::env;
+use std::io::{BufRead, BufReader, Read, Error as IoError};
+#[cfg(unix)]
+use std::os::raw::c_int;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command as Process, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+use core::buffer::{Buffer, BufferId};
+use core::find_in_files::{self, ResultMatch};
+use core::regex::{Regex, RegexError, Captures};
+use core::region::{Point, Region};
+use core::workspace::{BuildSystem, expand_variables};
+
+/// Name of the buffer the output of the build is written to.
+pub const OUTPUT_NAME: &'static str = "Build Results";
+
+/// Shell that runs `shell_cmd`.
+#[cfg(target_os = "windows")]
+const SHELL: [&'static str; 2] = ["cmd", "/C"];
+#[cfg(not(target_os = "windows"))]
+const SHELL: [&'static str; 2] = ["/bin/sh", "-c"];
+
+#[cfg(unix)]
+extern "C" {
+    fn kill(pid: c_int, signal: c_int) -> c_int;
+    fn setpgid(pid: c_int, pgid: c_int) -> c_int;
+}
+
+#[cfg(unix)]
+const SIGKILL: c_int = 9;
+
+#[derive(Debug)]
+enum Output {
+    Line(String),
+    /// Process has exited, the code is missing when it's killed by a signal
+    Finished(Option<i32>)
+}
+
+#[derive(Debug)]
+pub enum ExecError {
+    Io(IoError),
+    Regex(RegexError),
+    /// Build system has neither `cmd` nor `shell_cmd`
+    NoCommand
+}
+
+impl From<IoError> for ExecError {
+    fn from(error: IoError) -> ExecError {
+        ExecError::Io(error)
+    }
+}
+
+impl From<RegexError> for ExecError {
+    fn from(error: RegexError) -> ExecError {
+        ExecError::Regex(error)
+    }
+}
+
+/// Build process running in the background. Its output is appended to the output buffer
+/// as it comes, the lines matched by `file_regex` are the errors to go to.
+#[derive(Debug)]
+pub struct Exec {
+    buffer_id: BufferId,
+    /// Id of the process, it's also the id of its process group on Unix
+    pid: u32,
+    output: Receiver<Output>,
+    started: Instant,
+    is_finished: bool,
+    is_killed: bool,
+    file_regex: Option<Regex>,
+    line_regex: Option<Regex>,
+    /// Folder the file names of the errors are relative to
+    working_dir: PathBuf,
+    /// File of the last error, for the errors that give only the line
+    last_file: Option<PathBuf>,
+    errors: Vec<ResultMatch>,
+    /// Index of the last error that was jumped to
+    current: Option<usize>
+}
+
+fn compile(pattern: &Option<String>) -> Result<Option<Regex>, RegexError> {
+    match *pattern {
+        Some(ref pattern) => Regex::new(pattern).map(Some),
+        None => Ok(None)
+    }
+}
+
+/// Sends the lines of the output to the window, until the stream is closed.
+fn read_lines<R: Read>(stream: R, sender: Sender<Output>) {
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let text = String::from_utf8_lossy(&line).trim_right_matches(&['\r', '\n'][..])
+                                                 .to_owned();
+        if sender.send(Output::Line(text)).is_err() {
+            return
+        }
+    }
+}
+
+/// Kills the process together with the processes it started, like the commands
+/// of `shell_cmd`, so they don't keep running and holding the output open.
+#[cfg(unix)]
+fn kill_process(pid: u32) -> Result<(), IoError> {
+    if unsafe { kill(-(pid as c_int), SIGKILL) } < 0 {
+        return Err(IoError::last_os_error())
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn kill_process(pid: u32) -> Result<(), IoError> {
+    try!(Process::new("taskkill").args(&["/T", "/F", "/PID", &pid.to_string()])
+                                  .stdout(Stdio::null())
+                                  .stderr(Stdio::null())
+                                  .status());
+    Ok(())
+}
+
+/// Parses the number captured by the group, numbers are counted from 1.
+fn captured_number(captures: &Captures, index: usize) -> Option<usize> {
+    captures.at(index).and_then(|s| s.parse::<usize>().ok())
+}
+
+impl Exec {
+    /// Starts the process of the build system. Variables of the environment are expanded
+    /// in `env` and `path`.
+    pub fn start(build_system: &BuildSystem, buffer: &Buffer) -> Result<Exec, ExecError> {
+        let mut process = match (build_system.shell_cmd.as_ref(), build_system.cmd.split_first()) {
+            (Some(shell_cmd), _) => {
+                let mut process = Process::new(SHELL[0]);
+                process.arg(SHELL[1]).arg(shell_cmd);
+                process
+            },
+            (None, Some((program, args))) => {
+                let mut process = Process::new(program);
+                process.args(args);
+                process
+            },
+            (None, None) => return Err(ExecError::NoCommand)
+        };
+        let working_dir = match build_system.working_dir {
+            Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => try!(env::current_dir())
+        };
+        let lookup = |name: &str| env::var(name).ok();
+        for (name, value) in &build_system.env {
+            process.env(name, expand_variables(value, &lookup));
+        }
+        if let Some(ref path) = build_system.path {
+            process.env("PATH", expand_variables(path, &lookup));
+        }
+        // process gets its own group, so it's killed with the processes it started
+        #[cfg(unix)]
+        process.before_exec(|| {
+            if unsafe { setpgid(0, 0) } < 0 {
+                return Err(IoError::last_os_error())
+            }
+            Ok(())
+        });
+        let file_regex = try!(compile(&build_system.file_regex));
+        let line_regex = try!(compile(&build_system.line_regex));
+        let mut child = try!(process.current_dir(&working_dir)
+                                    .stdin(Stdio::null())
+                                    .stdout(Stdio::piped())
+                                    .stderr(Stdio::piped())
+                                    .spawn());
+        let (sender, output) = channel();
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            let sender = sender.clone();
+            readers.push(thread::spawn(move || read_lines(stdout, sender)));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            let sender = sender.clone();
+            readers.push(thread::spawn(move || read_lines(stderr, sender)));
+        }
+        let pid = child.id();
+        thread::spawn(move || {
+            // process isn't reaped until its output is closed, so its id stays taken
+            // while the build can be killed
+            for reader in readers {
+                let _ = reader.join();
+            }
+            let status = child.wait();
+            let _ = sender.send(Output::Finished(status.ok().and_then(|status| status.code())));
+        });
+        Ok(Exec {
+            buffer_id: buffer.id(),
+            pid: pid,
+            output: output,
+            started: Instant::now(),
+            is_finished: false,
+            is_killed: false,
+            file_regex: file_regex,
+            line_regex: line_regex,
+            working_dir: working_dir,
+            last_file: None,
+            errors: Vec::new(),
+            current: None
+        })
+    }
+
+    pub fn buffer_id(&self) -> BufferId {
+        self.buffer_id
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    /// Stops the build, the output says it's cancelled.
+    pub fn kill(&mut self) {
+        if self.is_finished || self.is_killed {
+            return
+        }
+        self.is_killed = true;
+        if let Err(err) = kill_process(self.pid) {
+            warn!("Can't kill build: {:?}", err);
+        }
+    }
+
+    /// Appends the new output of the process to the buffer, and the summary when
+    /// the process is finished. Returns false if there was nothing to append.
+    pub fn update(&mut self, buffer: &mut Buffer) -> bool {
+        if self.is_finished {
+            return false
+        }
+        let mut text = String::new();
+        // the last line of the buffer is empty, the text starts there
+        let mut result_line = buffer.lines.len() - 1;
+        while let Ok(output) = self.output.try_recv() {
+            match output {
+                Output::Line(line) => {
+                    self.parse_error(&line, result_line);
+                    text.push_str(&line);
+                    text.push('\n');
+                    result_line += 1;
+                },
+                Output::Finished(code) => {
+                    self.is_finished = true;
+                    let elapsed = self.started.elapsed();
+                    let seconds = elapsed.as_secs() as f64 +
+                                  elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+                    text.push_str(&match code {
+                        _ if self.is_killed => "[Cancelled]\n".to_owned(),
+                        Some(0) => format!("[Finished in {:.1}s]\n", seconds),
+                        Some(code) => {
+                            format!("[Finished in {:.1}s with exit code {}]\n", seconds, code)
+                        },
+                        None => format!("[Finished in {:.1}s, killed by a signal]\n", seconds)
+                    });
+                    break
+                }
+            }
+        }
+        if text.is_empty() {
+            return false
+        }
+        buffer.append(&text);
+        true
+    }
+
+    /// Remembers the error given by the output line. `file_regex` captures the file,
+    /// the line and the column, `line_regex` captures the line and the column of an error
+    /// in the file of the previous one.
+    fn parse_error(&mut self, text: &str, result_line: usize) {
+        let (file, line, column) = match self.file_regex.as_ref().and_then(|r| r.captures(text)) {
+            Some(captures) => {
+                let file = captures.at(1).map(|file| self.working_dir.join(file));
+                (file, captured_number(&captures, 2), captured_number(&captures, 3))
+            },
+            None => match self.line_regex.as_ref().and_then(|r| r.captures(text)) {
+                Some(captures) => {
+                    (None, captured_number(&captures, 1), captured_number(&captures, 2))
+                },
+                None => return
+            }
+        };
+        if file.is_some() {
+            self.last_file = file;
+        }
+        if let (Some(path), Some(line)) = (self.last_file.clone(), line) {
+            let point = Point::new(line.saturating_sub(1), column.unwrap_or(1).saturating_sub(1));
+            self.errors.push(ResultMatch {
+                path: path,
+                region: Region::caret(point),
+                result_line: result_line
+            });
+        }
+    }
+
+    /// Returns the error that follows the last one jumped to, or precedes it.
+    /// Errors are cycled.
+    pub fn next_error(&mut self, forward: bool) -> Option<&ResultMatch> {
+        self.current = find_in_files::next_index(self.current, self.errors.len(), forward);
+        self.current.and_then(move |index| self.errors.get(index))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use core::buffer::Buffer;
+    use core::settings::{ParseSettings, read_json};
+    use core::workspace::BuildSystem;
+
+    use super::Exec;
+
+    fn start(shell_cmd: &str) -> (Exec, Buffer) {
+        let json = format!(r#"{{"shell_cmd": {:?}}}"#, shell_cmd);
+        let build_system = BuildSystem::parse_settings(read_json(json.as_bytes()).unwrap());
+        let buffer = Buffer::new();
+        (Exec::start(&build_system.unwrap(), &buffer).unwrap(), buffer)
+    }
+
+    /// Appends the output until the condition is met or the time is out.
+    fn wait_until<F: Fn(&Exec, &Buffer) -> bool>(exec: &mut Exec, buffer: &mut Buffer, done: F) {
+        let started = Instant::now();
+        while !done(exec, buffer) && started.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(20));
+            exec.update(buffer);
+        }
+    }
+
+    #[test]
+    fn finished() {
+        let (mut exec, mut buffer) = start("echo one; echo two >&2; exit 3");
+        wait_until(&mut exec, &mut buffer, |exec, _| exec.is_finished());
+        assert!(exec.is_finished());
+        let lines: Vec<&str> = buffer.lines.iter().map(|line| &line.text[..]).collect();
+        assert!(lines.contains(&"one") && lines.contains(&"two"));
+        assert!(lines.iter().any(|line| line.ends_with("with exit code 3]")));
+    }
+
+    #[test]
+    fn kill_started_processes() {
+        // background command keeps the output open after the shell is killed
+        let (mut exec, mut buffer) = start("sleep 30 & echo started; wait");
+        wait_until(&mut exec, &mut buffer, |_, buffer| buffer.lines[0].text == "started");
+        let killed = Instant::now();
+        exec.kill();
+        wait_until(&mut exec, &mut buffer, |exec, _| exec.is_finished());
+        assert!(exec.is_finished());
+        assert!(killed.elapsed() < Duration::from_secs(5));
+        assert_eq!(buffer.lines[1].text, "[Cancelled]");
+    }
+}
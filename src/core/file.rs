@@ -0,0 +1,69 @@
+use std::env::current_dir;
+use std::fs::{File, OpenOptions, metadata, set_permissions, rename, remove_file};
+use std::io::{Write, Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of the temporary files created by the process, it makes their names unique.
+static TEMP_FILES: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Returns nanoseconds of the current time, they tell apart the temporary files
+/// of several processes.
+fn stamp() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0)
+}
+
+/// Creates a new temporary file next to the file, an existing file is never overwritten.
+fn create_temp(path: &Path) -> Result<(PathBuf, File), IoError> {
+    let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let dir = path.parent().unwrap_or(Path::new(""));
+    loop {
+        let index = TEMP_FILES.fetch_add(1, Ordering::SeqCst);
+        let temp = dir.join(format!(".{}.{}-{}.sublimate-tmp", name, stamp(), index));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            // left by another process
+            Err(ref err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err)
+        }
+    }
+}
+
+/// Writes the file as a whole: the bytes are written to a temporary file which then
+/// replaces the file, so a crash never leaves the file written partially. Permissions
+/// of the existing file are kept.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), IoError> {
+    let (temp, mut file) = try!(create_temp(path));
+    let written = file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .and_then(|_| match metadata(path) {
+            Ok(metadata) => set_permissions(&temp, metadata.permissions()),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err)
+        })
+        .and_then(|_| rename(&temp, path));
+    if let Err(err) = written {
+        let _ = remove_file(&temp);
+        return Err(err)
+    }
+    Ok(())
+}
+
+/// Checks that both paths lead to the same existing file, for example when they differ
+/// only by the case on a case-insensitive file system.
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false
+    }
+}
+
+/// Returns the canonical path of the file, so it doesn't depend on the current folder.
+/// Path of a missing file is only made absolute.
+pub fn absolute_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| match current_dir() {
+        Ok(dir) => dir.join(path),
+        Err(_) => path.to_path_buf()
+    })
+}
@@ -0,0 +1,571 @@
+use std::cmp::{min, max};
+use std::collections::BTreeMap;
+use std::fs::{File, read_dir, metadata};
+use std::io::{Read, Error as IoError};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use glob::{Pattern, PatternError};
+
+use core::buffer::{Buffer, BufferId};
+use core::file::write_atomically;
+use core::region::{Point, Region};
+use core::search::{Search, SearchOptions};
+use core::workspace::Project;
+
+/// Name of the buffer the results are written to.
+pub const RESULTS_NAME: &'static str = "Find Results";
+
+const OPEN_FOLDERS: &'static str = "<open folders>";
+const OPEN_FILES: &'static str = "<open files>";
+
+/// Number of lines shown before and after each matched line.
+const CONTEXT_LINES: usize = 2;
+
+/// Number of threads that search the files.
+const NUM_WORKERS: usize = 4;
+
+/// Files to search, parsed from the comma separated `Where:` field of the panel:
+/// `<open folders>`, `<open files>`, paths, include patterns and exclude patterns
+/// prefixed by `-`. Project folders are searched when no files are given.
+#[derive(Debug, Default)]
+pub struct Where {
+    pub open_folders: bool,
+    pub open_files: bool,
+    pub paths: Vec<PathBuf>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>
+}
+
+/// Match listed in the results buffer.
+#[derive(Debug, Clone)]
+pub struct ResultMatch {
+    pub path: PathBuf,
+    pub region: Region,
+    /// Line of the results buffer that shows the match
+    pub result_line: usize
+}
+
+/// Line of the searched file, matches are empty for the context lines.
+#[derive(Debug)]
+struct ResultLine {
+    number: usize,
+    text: String,
+    matches: Vec<(usize, usize)>
+}
+
+/// Replacement of a match. The replaced text is checked before the file is changed.
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pub region: Region,
+    pub old_text: String,
+    pub new_text: String
+}
+
+/// Replacements of the matches in a file, with the changed lines for the preview.
+#[derive(Debug)]
+pub struct FileReplacements {
+    pub path: PathBuf,
+    pub replacements: Vec<Replacement>,
+    encoding: Encoding,
+    /// Number of the line, its text before and after the replacement
+    lines: Vec<(usize, String, String)>
+}
+
+/// Encoding the file is read and written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// Files that aren't valid UTF-8 are read as Latin-1, so every byte is kept
+    Latin1
+}
+
+#[derive(Debug)]
+pub enum ReplaceError {
+    Io(IoError),
+    /// New text has characters the encoding of the file doesn't have
+    Encoding,
+    /// Replaced text was changed after the replacements were prepared
+    TextChanged
+}
+
+/// Search results of a file, they're sent even if nothing is found to count the searched files.
+#[derive(Debug)]
+struct FileResult {
+    path: PathBuf,
+    lines: Vec<ResultLine>
+}
+
+/// Find in files, that is in progress or finished. Files are searched by the background
+/// threads and their results are appended to the results buffer as they come.
+#[derive(Debug)]
+pub struct FindResults {
+    buffer_id: BufferId,
+    results: Receiver<FileResult>,
+    num_files: usize,
+    num_searched: usize,
+    num_matched_files: usize,
+    matches: Vec<ResultMatch>,
+    /// Index of the last match that was jumped to
+    current: Option<usize>
+}
+
+impl From<IoError> for ReplaceError {
+    fn from(error: IoError) -> ReplaceError {
+        ReplaceError::Io(error)
+    }
+}
+
+impl Where {
+    pub fn parse(text: &str) -> Result<Where, PatternError> {
+        let mut location = Where::default();
+        for item in text.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            match item {
+                OPEN_FOLDERS => location.open_folders = true,
+                OPEN_FILES => location.open_files = true,
+                _ if item.starts_with('-') => {
+                    location.exclude.push(try!(Pattern::new(&item[1..])));
+                },
+                _ if item.contains('*') || item.contains('?') => {
+                    location.include.push(try!(Pattern::new(item)));
+                },
+                _ => location.paths.push(PathBuf::from(item))
+            }
+        }
+        if !location.open_files && location.paths.is_empty() {
+            location.open_folders = true;
+        }
+        Ok(location)
+    }
+
+    /// Checks the file against the include and exclude patterns. Patterns are matched
+    /// with the file name and with the whole path.
+    pub fn matches(&self, path: &Path) -> bool {
+        let matches = |pattern: &Pattern| {
+            pattern.matches_path(path) ||
+                path.file_name().map_or(false, |name| pattern.matches(&name.to_string_lossy()))
+        };
+        (self.include.is_empty() || self.include.iter().any(&matches)) &&
+            !self.exclude.iter().any(&matches)
+    }
+}
+
+/// Adds the file, or all files of the folder.
+fn walk(path: &Path, files: &mut Vec<PathBuf>) {
+    match metadata(path) {
+        Ok(ref metadata) if metadata.is_dir() => match read_dir(path) {
+            Ok(entries) => {
+                for entry in entries.filter_map(Result::ok) {
+                    walk(&entry.path(), files);
+                }
+            },
+            Err(err) => warn!("Can't read {:?}: {:?}", path, err)
+        },
+        Ok(_) => files.push(path.to_owned()),
+        Err(err) => warn!("Can't read {:?}: {:?}", path, err)
+    }
+}
+
+/// Returns files to search. Files of the project folders are listed by the project, so its
+/// exclude patterns are honored, other paths are walked.
+pub fn collect_files(location: &Where, project: &Project, open_files: &[PathBuf])
+        -> Vec<PathBuf> {
+    let project_files: Vec<PathBuf> = project.text_files().into_iter()
+        .map(|file| file.path)
+        .collect();
+    let mut files = Vec::new();
+    if location.open_folders {
+        files.extend(project_files.iter().cloned());
+    }
+    if location.open_files {
+        files.extend(open_files.iter().cloned());
+    }
+    for path in &location.paths {
+        if project.folders.iter().any(|folder| path.starts_with(&folder.path)) {
+            files.extend(project_files.iter().filter(|file| file.starts_with(path)).cloned());
+        } else {
+            walk(path, &mut files);
+        }
+    }
+    files.sort();
+    files.dedup();
+    files.retain(|file| location.matches(file));
+    files
+}
+
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+fn encode_latin1(text: &str) -> Result<Vec<u8>, ReplaceError> {
+    text.chars().map(|c| if (c as u32) < 256 { Ok(c as u8) } else { Err(ReplaceError::Encoding) })
+        .collect()
+}
+
+/// Reads the file to search or replace in. Returns None if it can't be read or it's binary,
+/// that is it has a zero byte.
+pub fn open_file(path: &Path) -> Option<(Buffer, Encoding)> {
+    let mut bytes = Vec::new();
+    if File::open(path).and_then(|mut file| file.read_to_end(&mut bytes)).is_err() ||
+       bytes.contains(&0) {
+        return None
+    }
+    let (text, encoding) = match String::from_utf8(bytes) {
+        Ok(text) => (text, Encoding::Utf8),
+        Err(err) => (decode_latin1(err.as_bytes()), Encoding::Latin1)
+    };
+    Some((Buffer::from_text(path.to_path_buf(), &text), encoding))
+}
+
+/// Returns matched lines of the file with the context lines around them.
+/// Files that can't be read as text have no results.
+fn search_file(search: &Search, path: &Path) -> Vec<ResultLine> {
+    let buffer = match open_file(path) {
+        Some((buffer, _)) => buffer,
+        None => return Vec::new()
+    };
+    let mut matched = BTreeMap::new();
+    for region in search.find_all(&buffer) {
+        let columns = (region.a.column, region.b.column);
+        matched.entry(region.a.line).or_insert_with(Vec::new).push(columns);
+    }
+    let mut lines = Vec::new();
+    // the first line that isn't added yet
+    let mut next = 0;
+    for &line in matched.keys() {
+        let from = max(line.saturating_sub(CONTEXT_LINES), next);
+        let to = min(line + CONTEXT_LINES + 1, buffer.lines.len());
+        for number in from..to {
+            lines.push(ResultLine {
+                number: number,
+                text: buffer.lines[number].text.clone(),
+                matches: matched.get(&number).cloned().unwrap_or_default()
+            });
+        }
+        next = max(to, next);
+    }
+    lines
+}
+
+impl FindResults {
+    /// Starts searching the files in the background. The pattern must be already checked.
+    pub fn start(pattern: &str, options: SearchOptions, mut files: Vec<PathBuf>,
+                 buffer: &mut Buffer) -> FindResults {
+        let num_files = files.len();
+        buffer.append(&format!("Searching {} files for {:?}\n", num_files, pattern));
+        if num_files == 0 {
+            buffer.append("\n0 matches across 0 files\n");
+        }
+        // files are taken from the end of the queue
+        files.reverse();
+        let queue = Arc::new(Mutex::new(files));
+        let (sender, results) = channel();
+        for _ in 0..min(NUM_WORKERS, num_files) {
+            let (queue, sender, pattern) = (queue.clone(), sender.clone(), pattern.to_owned());
+            thread::spawn(move || {
+                // regex can't be sent to the thread, so each thread compiles it
+                let search = match Search::new(&pattern, options, &[]) {
+                    Ok(search) => search,
+                    Err(_) => return
+                };
+                loop {
+                    let path = match queue.lock().unwrap().pop() {
+                        Some(path) => path,
+                        None => return
+                    };
+                    let lines = search_file(&search, &path);
+                    // search is stopped when the results are dropped
+                    if sender.send(FileResult { path: path, lines: lines }).is_err() {
+                        return
+                    }
+                }
+            });
+        }
+        FindResults {
+            buffer_id: buffer.id(),
+            results: results,
+            num_files: num_files,
+            num_searched: 0,
+            num_matched_files: 0,
+            matches: Vec::new(),
+            current: None
+        }
+    }
+
+    pub fn buffer_id(&self) -> BufferId {
+        self.buffer_id
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.num_searched == self.num_files
+    }
+
+    /// Appends results of the searched files to the buffer, and the summary when all files
+    /// are searched. Returns false if there was nothing to append.
+    pub fn update(&mut self, buffer: &mut Buffer) -> bool {
+        if self.is_finished() {
+            return false
+        }
+        let mut changed = false;
+        while let Ok(result) = self.results.try_recv() {
+            self.num_searched += 1;
+            if !result.lines.is_empty() {
+                self.append_file(result, buffer);
+                changed = true;
+            }
+            if self.is_finished() {
+                let summary = format!("\n{} matches across {} files\n",
+                                      self.matches.len(), self.num_matched_files);
+                buffer.append(&summary);
+                return true
+            }
+        }
+        changed
+    }
+
+    fn append_file(&mut self, result: FileResult, buffer: &mut Buffer) {
+        self.num_matched_files += 1;
+        let mut text = format!("\n{}:\n", result.path.display());
+        // the last line of the buffer is empty, the text starts there
+        let mut result_line = buffer.lines.len() + 1;
+        let mut prev: Option<usize> = None;
+        for line in result.lines {
+            if prev.map_or(false, |prev| prev + 1 < line.number) {
+                text.push_str("  ..\n");
+                result_line += 1;
+            }
+            prev = Some(line.number);
+            let mark = if line.matches.is_empty() { ' ' } else { ':' };
+            text.push_str(&format!("{:>5}{} {}\n", line.number + 1, mark, line.text));
+            for (begin, end) in line.matches {
+                self.matches.push(ResultMatch {
+                    path: result.path.clone(),
+                    region: Region::new(Point::new(line.number, begin),
+                                        Point::new(line.number, end)),
+                    result_line: result_line
+                });
+            }
+            result_line += 1;
+        }
+        buffer.append(&text);
+    }
+
+    /// Returns the match that follows the last one jumped to, or precedes it.
+    /// Matches are cycled.
+    pub fn next_match(&mut self, forward: bool) -> Option<&ResultMatch> {
+        self.current = next_index(self.current, self.matches.len(), forward);
+        self.current.and_then(move |index| self.matches.get(index))
+    }
+}
+
+/// Returns the index of the next item of the list, or the previous one, the list is cycled.
+/// The first or the last item is taken when there is no current one.
+pub fn next_index(current: Option<usize>, len: usize, forward: bool) -> Option<usize> {
+    if len == 0 {
+        return None
+    }
+    Some(match current {
+        Some(current) if forward => (current + 1) % len,
+        Some(current) => (current + len - 1) % len,
+        None if forward => 0,
+        None => len - 1
+    })
+}
+
+/// Replaces the regions of the line by the new text, the regions must be ordered
+/// and contain the old text. Line is given by bytes, so the rest of it is kept as is
+/// even if it isn't valid UTF-8.
+fn replace_line(text: &[u8], replacements: &[&Replacement]) -> Result<Vec<u8>, ReplaceError> {
+    let mut result = Vec::with_capacity(text.len());
+    let mut last = 0;
+    for replacement in replacements {
+        let (begin, end) = (replacement.region.begin().column, replacement.region.end().column);
+        let old_text = replacement.old_text.as_bytes();
+        if begin < last || end > text.len() || text[begin..end] != *old_text {
+            return Err(ReplaceError::TextChanged)
+        }
+        result.extend_from_slice(&text[last..begin]);
+        result.extend_from_slice(replacement.new_text.as_bytes());
+        last = end;
+    }
+    result.extend_from_slice(&text[last..]);
+    Ok(result)
+}
+
+/// Returns replacements of the line, matches never span several lines.
+fn line_replacements(replacements: &[Replacement], line: usize) -> Vec<&Replacement> {
+    replacements.iter().filter(|replacement| replacement.region.a.line == line).collect()
+}
+
+impl FileReplacements {
+    /// Prepares replacements of all matches in the buffer of the file, the file is written
+    /// in the encoding it was read in.
+    pub fn new(path: PathBuf, search: &Search, format: &str, buffer: &Buffer,
+               encoding: Encoding) -> FileReplacements {
+        let replacements: Vec<Replacement> = search.find_all(buffer).into_iter().map(|region| {
+            Replacement {
+                region: region,
+                old_text: buffer.substr(region.begin(), region.end()),
+                new_text: search.replacement(buffer, region, format)
+            }
+        }).collect();
+        let mut numbers: Vec<usize> = replacements.iter().map(|r| r.region.a.line).collect();
+        numbers.dedup();
+        let lines = numbers.into_iter().filter_map(|number| {
+            let text = &buffer.lines[number].text;
+            replace_line(text.as_bytes(), &line_replacements(&replacements, number)).ok()
+                .and_then(|new_text| String::from_utf8(new_text).ok())
+                .map(|new_text| (number, text.clone(), new_text))
+        }).collect();
+        FileReplacements {
+            path: path,
+            replacements: replacements,
+            encoding: encoding,
+            lines: lines
+        }
+    }
+
+    /// Checks that the buffer still contains the replaced text.
+    pub fn check(&self, buffer: &Buffer) -> Result<(), ReplaceError> {
+        for replacement in &self.replacements {
+            let (begin, end) = (replacement.region.begin(), replacement.region.end());
+            let is_valid = buffer.clip_point(begin.line, begin.column) == begin &&
+                buffer.clip_point(end.line, end.column) == end &&
+                buffer.substr(begin, end) == replacement.old_text;
+            if !is_valid {
+                return Err(ReplaceError::TextChanged)
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the replacements to the file, the replaced text must be unchanged.
+    /// Line endings and the byte order mark are kept as is, the file is written
+    /// in its encoding.
+    pub fn write(&self) -> Result<(), ReplaceError> {
+        let mut bytes = Vec::new();
+        try!(try!(File::open(&self.path)).read_to_end(&mut bytes));
+        if self.encoding == Encoding::Latin1 {
+            // columns of the replacements are in the decoded text
+            bytes = decode_latin1(&bytes).into_bytes();
+        }
+        let mut result = Vec::with_capacity(bytes.len());
+        for (number, line) in bytes.split(|&byte| byte == b'\n').enumerate() {
+            if number > 0 {
+                result.push(b'\n');
+            }
+            let (line, ending) = if line.ends_with(b"\r") {
+                (&line[..line.len() - 1], &b"\r"[..])
+            } else {
+                (line, &b""[..])
+            };
+            let replacements = line_replacements(&self.replacements, number);
+            result.extend_from_slice(&try!(replace_line(line, &replacements)));
+            result.extend_from_slice(ending);
+        }
+        if self.encoding == Encoding::Latin1 {
+            result = try!(encode_latin1(&String::from_utf8_lossy(&result)));
+        }
+        try!(write_atomically(&self.path, &result));
+        Ok(())
+    }
+}
+
+/// Returns the preview of the replacements: each changed line before and after.
+pub fn preview(pattern: &str, format: &str, files: &[FileReplacements]) -> String {
+    let count: usize = files.iter().map(|file| file.replacements.len()).sum();
+    let mut text = format!("Replacing {} matches of {:?} by {:?} in {} files\n",
+                           count, pattern, format, files.len());
+    for file in files {
+        text.push_str(&format!("\n{}:\n", file.path.display()));
+        for &(number, ref old_text, ref new_text) in &file.lines {
+            text.push_str(&format!("{:>5}- {}\n", number + 1, old_text));
+            text.push_str(&format!("{:>5}+ {}\n", number + 1, new_text));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::path::Path;
+
+    use core::search::{Search, SearchOptions};
+    use core::temp_dir::TempDir;
+
+    use super::{FileReplacements, ReplaceError, Encoding, open_file, search_file};
+
+    /// Finds the pattern in the file and prepares its replacements, like replace in files
+    /// does for the files that aren't open.
+    fn replacements(path: &Path, pattern: &str, format: &str) -> FileReplacements {
+        let search = Search::new(pattern, SearchOptions::default(), &[]).unwrap();
+        let (buffer, encoding) = open_file(path).unwrap();
+        FileReplacements::new(path.to_path_buf(), &search, format, &buffer, encoding)
+    }
+
+    fn read(path: &Path) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        File::open(path).unwrap().read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn replace_in_latin1() {
+        let dir = TempDir::new("replace-latin1");
+        // Latin-1 text with Windows line endings
+        let path = dir.write("latin1.txt", b"caf\xe9 bar\r\nfoo bar\r\n");
+        let search = Search::new("bar", SearchOptions::default(), &[]).unwrap();
+        let lines = search_file(&search, &path);
+        let matched: Vec<usize> = lines.iter().filter(|line| !line.matches.is_empty())
+                                       .map(|line| line.number)
+                                       .collect();
+        assert_eq!(matched, vec![0, 1]);
+        assert_eq!(lines[0].text, "caf\u{e9} bar");
+
+        let file = replacements(&path, "bar", "b\u{e4}z");
+        assert_eq!(file.encoding, Encoding::Latin1);
+        file.write().unwrap();
+        assert_eq!(read(&path), b"caf\xe9 b\xe4z\r\nfoo b\xe4z\r\n".to_vec());
+        // only the file itself is left in the folder
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        match replacements(&path, "foo", "\u{2014}").write() {
+            Err(ReplaceError::Encoding) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+    }
+
+    #[test]
+    fn replace_in_utf8() {
+        let dir = TempDir::new("replace-utf8");
+        let path = dir.write("utf8.txt", "\u{feff}caf\u{e9} bar\nbar\n");
+        let file = replacements(&path, "bar", "\u{2014}");
+        assert_eq!(file.encoding, Encoding::Utf8);
+        file.write().unwrap();
+        assert_eq!(read(&path), "\u{feff}caf\u{e9} \u{2014}\n\u{2014}\n".as_bytes().to_vec());
+    }
+
+    #[test]
+    fn binary_files() {
+        let dir = TempDir::new("replace-binary");
+        let path = dir.write("binary", b"bar\x00bar");
+        assert!(open_file(&path).is_none());
+    }
+
+    #[test]
+    fn write_changed_text() {
+        let dir = TempDir::new("replace-changed");
+        let path = dir.write("file.txt", "foo bar\n");
+        let file = replacements(&path, "bar", "baz");
+        dir.write("file.txt", "foo qux\n");
+        match file.write() {
+            Err(ReplaceError::TextChanged) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+        assert_eq!(read(&path), b"foo qux\n".to_vec());
+    }
+}
@@ -0,0 +1,250 @@
+use std::cmp::max;
+
+/// Score of each matched character.
+const SCORE_MATCH: i64 = 16;
+/// Bonus for the character that follows the previous matched one.
+const BONUS_CONSECUTIVE: i64 = 12;
+/// Bonus for the first character of the text.
+const BONUS_FIRST: i64 = 10;
+/// Bonus for the character after a path separator.
+const BONUS_SEPARATOR: i64 = 10;
+/// Bonus for the character after a space, underscore, dash, dot or colon.
+const BONUS_WORD_START: i64 = 8;
+/// Bonus for the uppercase character after a lowercase one, or a digit after a letter.
+const BONUS_CAMEL_HUMP: i64 = 7;
+/// Bonus for the character typed in the same case.
+const BONUS_SAME_CASE: i64 = 1;
+/// Penalty for the first skipped character between matched ones.
+const PENALTY_GAP_START: i64 = 3;
+/// Penalty for each following skipped character.
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+/// Result of successful matching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Byte offsets of the matched characters in the text
+    pub positions: Vec<usize>
+}
+
+/// Pattern prepared for matching against many candidates.
+#[derive(Debug, Clone)]
+pub struct FuzzyMatcher {
+    pattern: Vec<char>,
+    lowercase: Vec<char>
+}
+
+fn to_lower(c: char) -> char {
+    if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Returns bonus for matching the character that follows `prev`.
+fn position_bonus(prev: Option<char>, c: char) -> i64 {
+    match prev {
+        None => BONUS_FIRST,
+        Some('/') | Some('\\') => BONUS_SEPARATOR,
+        Some(' ') | Some('_') | Some('-') | Some('.') | Some(':') => BONUS_WORD_START,
+        Some(p) if p.is_lowercase() && c.is_uppercase() => BONUS_CAMEL_HUMP,
+        Some(p) if p.is_alphabetic() && c.is_numeric() => BONUS_CAMEL_HUMP,
+        _ => 0
+    }
+}
+
+impl FuzzyMatcher {
+    /// Creates matcher for the pattern. Whitespace in the pattern is ignored.
+    pub fn new(pattern: &str) -> FuzzyMatcher {
+        let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+        FuzzyMatcher {
+            lowercase: pattern.iter().map(|&c| to_lower(c)).collect(),
+            pattern: pattern
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// Checks that all characters of the pattern are presented in the text in the same order.
+    /// It's much cheaper than scoring, so candidates are rejected by it first.
+    fn is_subsequence(&self, text: &str) -> bool {
+        let mut pattern = self.lowercase.iter().peekable();
+        for c in text.chars() {
+            match pattern.peek() {
+                Some(&&p) if p == to_lower(c) => { pattern.next(); },
+                Some(_) => {},
+                None => return true
+            }
+        }
+        pattern.peek().is_none()
+    }
+
+    /// Returns the best scored match of the pattern in the text.
+    pub fn matches(&self, text: &str) -> Option<FuzzyMatch> {
+        if self.pattern.is_empty() {
+            return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+        }
+        if !self.is_subsequence(text) {
+            return None;
+        }
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let (n, m) = (chars.len(), self.pattern.len());
+        let bonuses: Vec<i64> = (0..n).map(|j| {
+            let prev = if j > 0 { Some(chars[j - 1].1) } else { None };
+            position_bonus(prev, chars[j].1)
+        }).collect();
+
+        // scores[i * n + j] is the best score of the pattern prefix ending with
+        // the i-th character matched at the j-th position of the text
+        let none = i64::min_value();
+        let mut scores = vec![none; m * n];
+        // column of the previous matched character for the traceback
+        let mut from = vec![0; m * n];
+        // bonus of the beginning of the consecutive chunk that ends at the character
+        let mut chunks = vec![0; m * n];
+
+        for i in 0..m {
+            // best score of the previous row with a gap penalty applied, and its column
+            let mut best_gap = (none, 0);
+            for j in i..n {
+                if i > 0 && j >= 2 {
+                    let prev = scores[(i - 1) * n + j - 2];
+                    let extended = if best_gap.0 == none {
+                        none
+                    } else {
+                        best_gap.0 - PENALTY_GAP_EXTENSION
+                    };
+                    best_gap = if prev != none && prev - PENALTY_GAP_START >= extended {
+                        (prev - PENALTY_GAP_START, j - 2)
+                    } else {
+                        (extended, best_gap.1)
+                    };
+                }
+                let (_, c) = chars[j];
+                if to_lower(c) != self.lowercase[i] {
+                    continue
+                }
+                let mut score = SCORE_MATCH;
+                if c == self.pattern[i] {
+                    score += BONUS_SAME_CASE;
+                }
+                if i == 0 {
+                    scores[j] = score + bonuses[j];
+                    chunks[j] = bonuses[j];
+                    continue
+                }
+                // consecutive characters keep the bonus of the beginning of the chunk
+                let chunk = max(chunks[(i - 1) * n + j - 1], bonuses[j]);
+                let consecutive = scores[(i - 1) * n + j - 1];
+                let consecutive = if consecutive == none {
+                    none
+                } else {
+                    consecutive + BONUS_CONSECUTIVE + chunk
+                };
+                let gap = if best_gap.0 == none { none } else { best_gap.0 + bonuses[j] };
+                if consecutive == none && gap == none {
+                    continue
+                }
+                if consecutive >= gap {
+                    scores[i * n + j] = score + consecutive;
+                    from[i * n + j] = j - 1;
+                    chunks[i * n + j] = chunk;
+                } else {
+                    scores[i * n + j] = score + gap;
+                    from[i * n + j] = best_gap.1;
+                    chunks[i * n + j] = bonuses[j];
+                }
+            }
+        }
+
+        let last = (m - 1) * n;
+        let (end, score) = match (m - 1..n).map(|j| (j, scores[last + j])).max_by_key(|&(_, s)| s) {
+            Some((j, score)) if score != none => (j, score),
+            _ => return None
+        };
+        let mut positions = vec![0; m];
+        let mut j = end;
+        for i in (0..m).rev() {
+            positions[i] = chars[j].0;
+            j = from[i * n + j];
+        }
+        Some(FuzzyMatch { score: score, positions: positions })
+    }
+}
+
+/// Matches the pattern against all candidates. Returns indices of the matched candidates
+/// with their matches, the best ones first. Equally scored candidates are sorted by length.
+pub fn filter<'a, I>(pattern: &str, candidates: I) -> Vec<(usize, FuzzyMatch)>
+        where I: IntoIterator<Item=&'a str> {
+    let matcher = FuzzyMatcher::new(pattern);
+    let mut matches: Vec<(usize, usize, FuzzyMatch)> = candidates.into_iter()
+        .enumerate()
+        .filter_map(|(index, text)| matcher.matches(text).map(|m| (index, text.len(), m)))
+        .collect();
+    if !matcher.is_empty() {
+        matches.sort_by(|&(i1, len1, ref m1), &(i2, len2, ref m2)| {
+            m2.score.cmp(&m1.score).then(len1.cmp(&len2)).then(i1.cmp(&i2))
+        });
+    }
+    matches.into_iter().map(|(index, _, m)| (index, m)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FuzzyMatcher, BONUS_WORD_START, filter};
+
+    fn ranked<'a>(pattern: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+        filter(pattern, candidates.iter().cloned())
+            .into_iter()
+            .map(|(index, _)| candidates[index])
+            .collect()
+    }
+
+    fn positions(pattern: &str, text: &str) -> Vec<usize> {
+        FuzzyMatcher::new(pattern).matches(text).unwrap().positions
+    }
+
+    #[test]
+    fn word_start() {
+        assert_eq!(ranked("fb", &["fabric", "foo_bar"]), vec!["foo_bar", "fabric"]);
+        assert_eq!(ranked("ab", &["xaxb", "x.a-b"]), vec!["x.a-b", "xaxb"]);
+    }
+
+    #[test]
+    fn camel_case() {
+        assert_eq!(ranked("fb", &["foobar", "fooBar"]), vec!["fooBar", "foobar"]);
+        assert_eq!(ranked("v2", &["view_2x", "view2"]), vec!["view2", "view_2x"]);
+    }
+
+    #[test]
+    fn separator() {
+        assert_eq!(ranked("m", &["domain.rs", "src/main.rs"]), vec!["src/main.rs", "domain.rs"]);
+        assert_eq!(ranked("sm", &["system.rs", "src/main.rs"]), vec!["src/main.rs", "system.rs"]);
+    }
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        FuzzyMatcher::new(pattern).matches(text).unwrap().score
+    }
+
+    #[test]
+    fn consecutive() {
+        assert_eq!(ranked("ab", &["axxb", "xxab"]), vec!["xxab", "axxb"]);
+        assert_eq!(ranked("ain", &["a_i_n", "main"]), vec!["main", "a_i_n"]);
+        // each character of the chunk keeps the bonus of its beginning
+        assert_eq!(score("ain", "x_ain") - score("ain", "xxain"), 3 * BONUS_WORD_START);
+    }
+
+    #[test]
+    fn matched_positions() {
+        assert_eq!(positions("fb", "foo_bar"), vec![0, 4]);
+        assert_eq!(positions("ma", "emma/main"), vec![5, 6]);
+        assert_eq!(positions("mr", "src/main.rs"), vec![4, 9]);
+        // positions are byte offsets
+        assert_eq!(positions("жк", "ёж/ёжик"), vec![7, 11]);
+        assert!(FuzzyMatcher::new("ab").matches("ba").is_none());
+        assert!(FuzzyMatcher::new("").matches("text").unwrap().positions.is_empty());
+    }
+}
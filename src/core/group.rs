@@ -0,0 +1,78 @@
+use core::view::View;
+
+#[derive(Debug, Default)]
+pub struct Group {
+    pub views: Vec<View>,
+    active: usize
+}
+
+impl Group {
+    pub fn new() -> Group {
+        Group::default()
+    }
+
+    pub fn active_index(&self) -> Option<usize> {
+        if self.views.is_empty() {
+            None
+        } else {
+            Some(self.active)
+        }
+    }
+
+    pub fn active_view(&self) -> Option<&View> {
+        self.views.get(self.active)
+    }
+
+    pub fn active_view_mut(&mut self) -> Option<&mut View> {
+        self.views.get_mut(self.active)
+    }
+
+    /// Adds view right after the active one and makes it active.
+    pub fn add_view(&mut self, view: View) {
+        let index = if self.views.is_empty() { 0 } else { self.active + 1 };
+        self.views.insert(index, view);
+        self.active = index;
+    }
+
+    /// Removes the active view from the group. The previous view becomes active.
+    pub fn take_active_view(&mut self) -> Option<View> {
+        let active = self.active;
+        self.remove_view(active)
+    }
+
+    /// Removes the view from the group. When the active view is removed, the previous one
+    /// becomes active.
+    pub fn remove_view(&mut self, index: usize) -> Option<View> {
+        if index >= self.views.len() {
+            return None
+        }
+        let view = self.views.remove(index);
+        if self.active > 0 && index <= self.active {
+            self.active -= 1;
+        }
+        Some(view)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.views.len() {
+            self.active = index;
+        }
+    }
+
+    pub fn next_view(&mut self) {
+        if !self.views.is_empty() {
+            self.active = (self.active + 1) % self.views.len();
+        }
+    }
+
+    pub fn prev_view(&mut self) {
+        if !self.views.is_empty() {
+            self.active = (self.active + self.views.len() - 1) % self.views.len();
+        }
+    }
+
+    /// Moves all views from other group to the end of this group.
+    pub fn merge(&mut self, other: Group) {
+        self.views.extend(other.views);
+    }
+}
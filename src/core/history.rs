@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, Error as IoError, ErrorKind};
+use std::fs::{self, File};
+
+use core::file::write_atomically;
+use core::settings::{Settings, SettingsObject, SettingsError, read_json};
+
+/// Number of the items kept in each list
+const MAX_ITEMS: usize = 20;
+
+/// Recently opened files, folders and projects, the recent one is the first.
+/// It's kept in the user data folder, so it's shared by all the projects.
+#[derive(Debug, Default)]
+pub struct History {
+    /// File of the history, the history isn't saved without it
+    path: Option<PathBuf>,
+    files: Vec<PathBuf>,
+    folders: Vec<PathBuf>,
+    projects: Vec<PathBuf>
+}
+
+#[derive(Debug)]
+pub enum HistoryError {
+    Io(IoError),
+    Settings(SettingsError),
+    HistoryIsNotObject,
+    ListIsNotArray,
+    ListItemIsNotString
+}
+
+impl From<IoError> for HistoryError {
+    fn from(error: IoError) -> HistoryError {
+        HistoryError::Io(error)
+    }
+}
+
+impl From<SettingsError> for HistoryError {
+    fn from(error: SettingsError) -> HistoryError {
+        HistoryError::Settings(error)
+    }
+}
+
+fn parse_paths(settings: Option<Settings>) -> Result<Vec<PathBuf>, HistoryError> {
+    let arr = match settings {
+        Some(Settings::Array(arr)) => arr,
+        None => return Ok(Vec::new()),
+        Some(_) => return Err(HistoryError::ListIsNotArray)
+    };
+    let mut paths = Vec::new();
+    for item in arr {
+        match item {
+            Settings::String(s) => paths.push(PathBuf::from(s)),
+            _ => return Err(HistoryError::ListItemIsNotString)
+        }
+    }
+    Ok(paths)
+}
+
+fn paths_setting(paths: &[PathBuf]) -> Settings {
+    Settings::Array(paths.iter().map(|path| {
+        Settings::String(path.to_string_lossy().into_owned())
+    }).collect())
+}
+
+/// Moves the path to the beginning of the list, paths are kept absolute.
+fn add_path(list: &mut Vec<PathBuf>, path: &Path) {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    list.retain(|item| *item != path);
+    list.insert(0, path);
+    list.truncate(MAX_ITEMS);
+}
+
+impl History {
+    /// Creates empty history that isn't saved.
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Reads the history file. The history is empty until the file is written.
+    pub fn open(path: PathBuf) -> Result<History, HistoryError> {
+        let mut history = History { path: Some(path.clone()), ..History::default() };
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(history),
+            Err(err) => return Err(HistoryError::Io(err))
+        };
+        let mut obj = match try!(read_json(BufReader::new(file))) {
+            Settings::Object(obj) => obj,
+            _ => return Err(HistoryError::HistoryIsNotObject)
+        };
+        history.files = try!(parse_paths(obj.remove("files")));
+        history.folders = try!(parse_paths(obj.remove("folders")));
+        history.projects = try!(parse_paths(obj.remove("projects")));
+        Ok(history)
+    }
+
+    /// Writes the history file, its folder is created when it's missing. The file is replaced
+    /// as a whole, so the previous history is kept if writing fails.
+    pub fn save(&self) -> Result<(), HistoryError> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(())
+        };
+        if let Some(parent) = path.parent() {
+            try!(fs::create_dir_all(parent));
+        }
+        let mut obj = SettingsObject::new();
+        obj.insert("files".to_owned(), paths_setting(&self.files));
+        obj.insert("folders".to_owned(), paths_setting(&self.folders));
+        obj.insert("projects".to_owned(), paths_setting(&self.projects));
+        let text = format!("{}\n", Settings::Object(obj).pretty());
+        try!(write_atomically(path, text.as_bytes()));
+        Ok(())
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn folders(&self) -> &[PathBuf] {
+        &self.folders
+    }
+
+    pub fn projects(&self) -> &[PathBuf] {
+        &self.projects
+    }
+
+    pub fn add_file(&mut self, path: &Path) {
+        add_path(&mut self.files, path);
+    }
+
+    pub fn add_folder(&mut self, path: &Path) {
+        add_path(&mut self.folders, path);
+    }
+
+    pub fn add_project(&mut self, path: &Path) {
+        add_path(&mut self.projects, path);
+    }
+
+    /// Forgets the recent files and folders.
+    pub fn clear_files(&mut self) {
+        self.files.clear();
+        self.folders.clear();
+    }
+
+    pub fn clear_projects(&mut self) {
+        self.projects.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use core::temp_dir::TempDir;
+
+    use super::History;
+
+    #[test]
+    fn save_and_open() {
+        let dir = TempDir::new("history-save");
+        let path = dir.join("sublimate/history.json");
+        let file = dir.write("file.rs", "");
+        let mut history = History::open(path.clone()).unwrap();
+        history.add_file(&file);
+        history.add_folder(dir.path());
+        history.save().unwrap();
+        // the temporary file is renamed to the history file
+        assert_eq!(fs::read_dir(dir.join("sublimate")).unwrap().count(), 1);
+
+        let history = History::open(path).unwrap();
+        assert_eq!(history.files(), &[file]);
+        assert_eq!(history.folders(), &[dir.path().to_path_buf()]);
+        assert!(history.projects().is_empty());
+    }
+}
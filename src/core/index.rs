@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::mem;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+
+use core::Core;
+use core::buffer::Buffer;
+use core::packages::PackageRepository;
+use core::symbols::{Symbol, SymbolList};
+use core::syntax::Parser;
+use core::workspace::ProjectFile;
+
+/// Symbol defined in a project file.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub file: ProjectFile,
+    pub symbol: Symbol
+}
+
+/// Symbols of all project files. Files are parsed in the background thread,
+/// which parses them again when they're reported as changed.
+#[derive(Debug)]
+pub struct SymbolIndex {
+    symbols: BTreeMap<PathBuf, (ProjectFile, Vec<Symbol>)>,
+    requests: Sender<IndexRequest>,
+    /// Symbols of the parsed files, or None if the file was removed from the index
+    results: Receiver<(ProjectFile, Option<Vec<Symbol>>)>
+}
+
+#[derive(Debug)]
+enum IndexRequest {
+    /// Replaces the indexed files, the new ones are parsed
+    SetFiles(Vec<ProjectFile>),
+    /// Parses the indexed files again, usually after they were changed on disk
+    Update(Vec<PathBuf>)
+}
+
+struct Indexer {
+    repository: PackageRepository,
+    symbol_list: SymbolList,
+    /// Parsers by the file extension, None if there is no syntax for it
+    parsers: HashMap<String, Option<Parser>>,
+    files: BTreeMap<PathBuf, ProjectFile>,
+    /// Files to parse once the pending requests are taken
+    changed: BTreeSet<PathBuf>,
+    results: Sender<(ProjectFile, Option<Vec<Symbol>>)>
+}
+
+impl Indexer {
+    /// Takes the requests until the index is dropped. Requests that come in a row
+    /// are taken together, so a file is parsed once.
+    fn run(&mut self, requests: Receiver<IndexRequest>) {
+        while let Ok(request) = requests.recv() {
+            self.take(request);
+            while let Ok(request) = requests.try_recv() {
+                self.take(request);
+            }
+            if !self.update() {
+                return
+            }
+        }
+    }
+
+    fn take(&mut self, request: IndexRequest) {
+        match request {
+            IndexRequest::SetFiles(files) => self.set_files(files),
+            IndexRequest::Update(paths) => {
+                let indexed: Vec<PathBuf> = paths.into_iter()
+                    .filter(|path| self.files.contains_key(path))
+                    .collect();
+                self.changed.extend(indexed);
+            }
+        }
+    }
+
+    fn set_files(&mut self, files: Vec<ProjectFile>) {
+        let mut old_files = mem::replace(&mut self.files, BTreeMap::new());
+        for file in files {
+            if old_files.remove(&file.path).is_none() {
+                self.changed.insert(file.path.clone());
+            }
+            self.files.insert(file.path.clone(), file);
+        }
+        for (path, file) in old_files {
+            self.changed.remove(&path);
+            let _ = self.results.send((file, None));
+        }
+    }
+
+    /// Parses the changed files. Returns false if the index is dropped.
+    fn update(&mut self) -> bool {
+        for path in mem::replace(&mut self.changed, BTreeSet::new()) {
+            let file = match self.files.get(&path) {
+                Some(file) => file.clone(),
+                None => continue
+            };
+            let symbols = self.parse(path);
+            if self.results.send((file, symbols)).is_err() {
+                return false
+            }
+        }
+        true
+    }
+
+    /// Returns symbols of the file, or None if it can't be read, like a binary file.
+    fn parse(&mut self, path: PathBuf) -> Option<Vec<Symbol>> {
+        let extension = match path.extension() {
+            Some(extension) => extension.to_string_lossy().into_owned(),
+            None => return Some(Vec::new())
+        };
+        let mut buffer = match Buffer::open(path) {
+            Ok(buffer) => buffer,
+            Err(_) => return None
+        };
+        let repository = &self.repository;
+        let parser = self.parsers.entry(extension.clone())
+            .or_insert_with(|| Core::create_parser(repository, buffer.path()))
+            .take();
+        let parser = match parser {
+            Some(parser) => parser,
+            None => return Some(Vec::new())
+        };
+        buffer.set_parser(parser);
+        let symbols = buffer.symbols(&self.symbol_list);
+        // parser is returned to the cache for the next file
+        self.parsers.insert(extension, buffer.take_parser());
+        Some(symbols)
+    }
+}
+
+impl SymbolIndex {
+    /// Starts indexing thread. Syntaxes and symbol settings are loaded from the packages.
+    pub fn new(repository: PackageRepository) -> SymbolIndex {
+        let (requests, requests_receiver) = channel();
+        let (results_sender, results) = channel();
+        thread::spawn(move || {
+            let mut indexer = Indexer {
+                symbol_list: Core::load_symbol_list(&repository),
+                repository: repository,
+                parsers: HashMap::new(),
+                files: BTreeMap::new(),
+                changed: BTreeSet::new(),
+                results: results_sender
+            };
+            indexer.run(requests_receiver);
+        });
+        SymbolIndex {
+            symbols: BTreeMap::new(),
+            requests: requests,
+            results: results
+        }
+    }
+
+    /// Replaces the indexed files.
+    pub fn set_files(&self, files: Vec<ProjectFile>) {
+        self.send(IndexRequest::SetFiles(files));
+    }
+
+    /// Parses the changed files again, paths that aren't indexed are ignored.
+    pub fn update_files(&self, paths: Vec<PathBuf>) {
+        self.send(IndexRequest::Update(paths));
+    }
+
+    fn send(&self, request: IndexRequest) {
+        if self.requests.send(request).is_err() {
+            error!("Symbol index thread is stopped");
+        }
+    }
+
+    /// Takes the results of the indexing thread.
+    fn update(&mut self) {
+        while let Ok((file, symbols)) = self.results.try_recv() {
+            match symbols {
+                Some(symbols) => {
+                    self.symbols.insert(file.path.clone(), (file, symbols));
+                },
+                None => {
+                    self.symbols.remove(&file.path);
+                }
+            }
+        }
+    }
+
+    /// Returns all definitions, ordered by file.
+    pub fn definitions(&mut self) -> Vec<Definition> {
+        self.update();
+        self.symbols.values().flat_map(|&(ref file, ref symbols)| {
+            symbols.iter().map(move |symbol| Definition {
+                file: file.clone(),
+                symbol: symbol.clone()
+            })
+        }).collect()
+    }
+
+    /// Returns definitions of the symbol with the given name.
+    pub fn find(&mut self, name: &str) -> Vec<Definition> {
+        self.definitions().into_iter().filter(|definition| definition.symbol.name == name).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use core::packages::PackageRepository;
+    use core::temp_dir::TempDir;
+    use core::workspace::ProjectFile;
+
+    use super::SymbolIndex;
+
+    fn wait_for_names(index: &mut SymbolIndex, expected: &[&str]) -> Vec<String> {
+        let started = Instant::now();
+        loop {
+            let names: Vec<String> = index.definitions().into_iter()
+                .map(|definition| definition.symbol.name)
+                .collect();
+            if names == expected || started.elapsed() > Duration::from_secs(10) {
+                return names
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn update_changed_files() {
+        let packages = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/packages"));
+        let mut index = SymbolIndex::new(PackageRepository::open(vec![packages]));
+        let dir = TempDir::new("index-update");
+        let path = dir.write("main.rs", "fn first() {}\n");
+        index.set_files(vec![ProjectFile { name: "main.rs".to_owned(), path: path.clone() }]);
+        assert_eq!(wait_for_names(&mut index, &["first"]), vec!["first"]);
+
+        // files aren't read again until they're reported as changed
+        dir.write("main.rs", "fn second() {}\n");
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(index.definitions()[0].symbol.name, "first");
+        index.update_files(vec![dir.join("other.rs"), path]);
+        assert_eq!(wait_for_names(&mut index, &["second"]), vec!["second"]);
+
+        index.set_files(Vec::new());
+        assert!(wait_for_names(&mut index, &[]).is_empty());
+    }
+}
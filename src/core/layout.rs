@@ -0,0 +1,164 @@
+use core::settings::{Settings, SettingsArray, SettingsObject, ParseSettings};
+
+use self::ParseLayoutError::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    /// Relative positions of vertical split lines, from `0.0` to `1.0`.
+    pub cols: Vec<f64>,
+    /// Relative positions of horizontal split lines, from `0.0` to `1.0`.
+    pub rows: Vec<f64>,
+    /// Cells, one for each group. Each cell is described by indexes into `cols` and `rows`.
+    pub cells: Vec<LayoutCell>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutCell {
+    pub x1: usize,
+    pub y1: usize,
+    pub x2: usize,
+    pub y2: usize
+}
+
+#[derive(Debug)]
+pub enum ParseLayoutError {
+    LayoutIsNotObject,
+    SplitsIsNotArray,
+    SplitIsNotNumber,
+    IncorrectSplits,
+    CellsIsNotArray,
+    CellIsNotArray,
+    CellIndexIsNotNumber,
+    IncorrectCell,
+}
+
+fn parse_splits(settings: Option<Settings>) -> Result<Vec<f64>, ParseLayoutError> {
+    let arr = match settings {
+        Some(Settings::Array(arr)) => arr,
+        _ => return Err(SplitsIsNotArray)
+    };
+    let mut splits = Vec::new();
+    for settings in arr {
+        match settings.as_f64() {
+            Some(value) => splits.push(value),
+            None => return Err(SplitIsNotNumber)
+        }
+    }
+    // splits should cover the whole area in ascending order
+    if splits.len() < 2 || splits[0] != 0.0 || splits[splits.len() - 1] != 1.0 {
+        return Err(IncorrectSplits)
+    }
+    if splits.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(IncorrectSplits)
+    }
+    Ok(splits)
+}
+
+fn parse_cell(settings: Settings) -> Result<LayoutCell, ParseLayoutError> {
+    let arr: SettingsArray = match settings {
+        Settings::Array(arr) => arr,
+        _ => return Err(CellIsNotArray)
+    };
+    if arr.len() != 4 {
+        return Err(IncorrectCell)
+    }
+    let mut indexes = [0; 4];
+    for (i, settings) in arr.into_iter().enumerate() {
+        indexes[i] = match settings.as_u64() {
+            Some(index) => index as usize,
+            None => return Err(CellIndexIsNotNumber)
+        }
+    }
+    Ok(LayoutCell {
+        x1: indexes[0],
+        y1: indexes[1],
+        x2: indexes[2],
+        y2: indexes[3]
+    })
+}
+
+impl ParseSettings for Layout {
+    type Error = ParseLayoutError;
+
+    fn parse_settings(settings: Settings) -> Result<Layout, ParseLayoutError> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(LayoutIsNotObject)
+        };
+
+        let cols = try!(parse_splits(obj.remove("cols")));
+        let rows = try!(parse_splits(obj.remove("rows")));
+
+        let cells_arr = match obj.remove("cells") {
+            Some(Settings::Array(arr)) => arr,
+            _ => return Err(CellsIsNotArray)
+        };
+
+        let mut cells = Vec::new();
+        for settings in cells_arr {
+            let cell = try!(parse_cell(settings));
+            if cell.x1 >= cell.x2 || cell.x2 >= cols.len() ||
+               cell.y1 >= cell.y2 || cell.y2 >= rows.len() {
+                return Err(IncorrectCell)
+            }
+            cells.push(cell);
+        }
+        if cells.is_empty() {
+            return Err(IncorrectCell)
+        }
+
+        Ok(Layout {
+            cols: cols,
+            rows: rows,
+            cells: cells
+        })
+    }
+}
+
+impl Layout {
+    /// Layout with the single group that takes all available space.
+    pub fn single() -> Layout {
+        Layout {
+            cols: vec![0.0, 1.0],
+            rows: vec![0.0, 1.0],
+            cells: vec![LayoutCell { x1: 0, y1: 0, x2: 1, y2: 1 }]
+        }
+    }
+
+    /// Returns the layout in the form accepted by `set_layout`.
+    pub fn to_settings(&self) -> Settings {
+        let splits = |splits: &[f64]| Settings::Array(splits.iter().map(|&value| {
+            Settings::F64(value)
+        }).collect());
+        let cells = self.cells.iter().map(|cell| {
+            Settings::Array([cell.x1, cell.y1, cell.x2, cell.y2].iter().map(|&index| {
+                Settings::U64(index as u64)
+            }).collect())
+        }).collect();
+        let mut obj = SettingsObject::new();
+        obj.insert("cols".to_owned(), splits(&self.cols));
+        obj.insert("rows".to_owned(), splits(&self.rows));
+        obj.insert("cells".to_owned(), Settings::Array(cells));
+        Settings::Object(obj)
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns `(x1, y1, x2, y2)` bounds of the cell inside area with given size.
+    pub fn cell_rect(&self, index: usize, width: usize, height: usize) -> (usize, usize, usize, usize) {
+        let cell = &self.cells[index];
+        let scale = |value: f64, size: usize| (value * size as f64).round() as usize;
+        (scale(self.cols[cell.x1], width),
+         scale(self.rows[cell.y1], height),
+         scale(self.cols[cell.x2], width),
+         scale(self.rows[cell.y2], height))
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout::single()
+    }
+}
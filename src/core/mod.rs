@@ -3,53 +3,546 @@ pub mod menu;
 pub mod workspace;
 mod packages;
 pub mod command;
+pub mod command_list;
 pub mod syntax;
 pub mod regex;
 pub mod bindings;
 pub mod view;
+pub mod layout;
+pub mod group;
+pub mod buffer;
+pub mod region;
+pub mod preferences;
+pub mod wrap;
+pub mod fuzzy;
+pub mod symbols;
+pub mod index;
+pub mod search;
+pub mod find_in_files;
+pub mod watcher;
+pub mod vcs;
+pub mod diff;
+pub mod history;
+pub mod exec;
+pub mod file;
+#[cfg(test)]
+mod temp_dir;
 
-use std::path::PathBuf;
+use std::cell::{RefCell, RefMut};
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
 
-use core::workspace::Project;
+use core::workspace::{Project, Session, SessionGroup, SessionView, BuildSystem};
 use core::packages::{PackageRepository, PackageError};
-use core::bindings::HotkeyPerformer;
-use core::view::View;
-use core::menu::Menu;
-use core::syntax::{Parser, Highlighter};
+use core::bindings::{HotkeyPerformer, Mousemap, MouseBinding, MouseButton, Modifiers};
+use core::command::Command;
+use core::buffer::{Buffer, BufferId, Edit};
+use core::view::{View, ViewId};
+use core::menu::{Menu, MenuItem};
+use core::command_list::CommandList;
+use core::layout::Layout;
+use core::group::Group;
+use core::syntax::{Syntax, Parser, Highlighter};
+use core::settings::{Settings, FromSettings};
+use core::preferences::Preferences;
+use core::symbols::SymbolList;
+use core::index::SymbolIndex;
+use core::search::{Search, SearchOptions};
+use core::find_in_files::{FindResults, FileReplacements, ReplaceError, Where, Encoding};
+use core::file::{absolute_path, is_same_file};
+use core::regex::RegexError;
+use core::region::{Point, Region};
+use core::watcher::{Watcher, Change};
+use core::vcs::{VcsStatus, VcsReader, HeadReader};
+use core::history::History;
+use core::exec::Exec;
 
 #[derive(Debug)]
 pub struct Core {
     pub package_repository: PackageRepository,
     pub project: Project,
     pub hotkeys: HotkeyPerformer,
-    pub view: View
+    pub mousemap: Mousemap,
+    pub preferences: Preferences,
+    pub symbol_list: SymbolList,
+    pub symbol_index: SymbolIndex,
+    pub layout: Layout,
+    pub groups: Vec<Group>,
+    /// Name of the panel shown at the bottom of the window, like `find`
+    pub panel: Option<String>,
+    /// Results of the last find in files
+    pub find_results: Option<FindResults>,
+    /// Replacements in files shown in the preview, they're performed when confirmed
+    pub pending_replacements: Option<Vec<FileReplacements>>,
+    /// Buffer of the view that shows results of find in files
+    results_buffer: Option<BufferId>,
+    /// Build systems of the packages, they follow the build systems of the project
+    package_build_systems: Vec<BuildSystem>,
+    /// Index of the build system chosen in the menu, it's chosen by the file otherwise
+    build_system: Option<usize>,
+    /// Build that is running or finished last
+    build: Option<Exec>,
+    /// Buffer of the view that shows the output of the build
+    output_buffer: Option<BufferId>,
+    /// Next result is taken from the build errors instead of the results of find in files
+    is_build_result: bool,
+    active_group: usize,
+    /// View opened to preview a file, it's closed as soon as another file is previewed
+    transient: Option<ViewId>,
+    /// Git status of the project folders
+    pub vcs_status: VcsStatus,
+    vcs_reader: VcsReader,
+    head_reader: HeadReader,
+    /// Watches the project folders and folders of the open files
+    watcher: Watcher,
+    /// Modified files that were changed on disk, the user is asked whether to reload them
+    dirty_changes: Vec<PathBuf>,
+    /// Session read on start, until it's taken by the window
+    session: Option<Session>,
+    /// Recently opened files, folders and projects
+    history: History,
+    /// History was changed since the menu was created
+    history_changed: bool,
+    /// Files of the closed views, the last closed one is the last
+    closed_files: Vec<PathBuf>,
+    commands: RefCell<Vec<Command>>
 }
 
+#[cfg(target_os = "macos")]
+const DEFAULT_KEYMAP: &'static str = "default/Default (OSX).sublime-keymap";
+#[cfg(target_os = "windows")]
+const DEFAULT_KEYMAP: &'static str = "default/Default (Windows).sublime-keymap";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const DEFAULT_KEYMAP: &'static str = "default/Default (Linux).sublime-keymap";
+
+#[cfg(target_os = "macos")]
+const DEFAULT_MOUSEMAP: &'static str = "default/Default (OSX).sublime-mousemap";
+#[cfg(target_os = "windows")]
+const DEFAULT_MOUSEMAP: &'static str = "default/Default (Windows).sublime-mousemap";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const DEFAULT_MOUSEMAP: &'static str = "default/Default (Linux).sublime-mousemap";
+
+const PREFERENCES: &'static str = "default/Preferences.sublime-settings";
+
 impl Core {
 
-    pub fn load(packages_path_str: &str, file_path_str: &str, project_path_str: &str) -> Core {
-        let packages_path = PathBuf::from(packages_path_str);
-        let view_path = PathBuf::from(file_path_str);
-        let project_path = PathBuf::from(project_path_str);
-        let repository = PackageRepository::open(packages_path);
-        let mut view = View::open(view_path).unwrap();
-        let syntax = repository.get_syntax("Rust/Rust.tmLanguage").unwrap();
-        let mut parser = Parser::from_syntax(syntax);
-        view.parse(&mut parser);
+    /// Loads the packages and opens the project. Views of the last session are restored
+    /// unless the new window is requested, files are opened afterwards.
+    pub fn load(packages_paths: Vec<PathBuf>, project: Project, history: History,
+                restore_session: bool) -> Core {
+        let repository = PackageRepository::open(packages_paths);
+        let symbol_index = SymbolIndex::new(repository.clone());
         let mut hotkeys = HotkeyPerformer::new();
         // TODO: fix unwrap
-        hotkeys.add_keymap(repository.get_keymap("default/Default (OSX).sublime-keymap").unwrap());
-        Core {
-            project: Project::open(project_path).unwrap(),
+        hotkeys.add_keymap(repository.get_keymap(DEFAULT_KEYMAP).unwrap());
+        let mousemap = repository.get_mousemap(DEFAULT_MOUSEMAP).unwrap();
+        let preferences = Core::load_preferences(&repository, &project);
+        let symbol_list = Core::load_symbol_list(&repository);
+        let build_systems = Core::load_build_systems(&repository);
+        symbol_index.set_files(project.index_files());
+        let mut core = Core {
+            project: project,
             package_repository: repository,
             hotkeys: hotkeys,
-            view: view
+            mousemap: mousemap,
+            preferences: preferences,
+            symbol_list: symbol_list,
+            symbol_index: symbol_index,
+            layout: Layout::single(),
+            groups: vec![Group::new()],
+            panel: None,
+            find_results: None,
+            pending_replacements: None,
+            results_buffer: None,
+            package_build_systems: build_systems,
+            build_system: None,
+            build: None,
+            output_buffer: None,
+            is_build_result: false,
+            active_group: 0,
+            transient: None,
+            vcs_status: VcsStatus::default(),
+            vcs_reader: VcsReader::new(),
+            head_reader: HeadReader::new(),
+            watcher: Watcher::new(),
+            dirty_changes: Vec::new(),
+            session: None,
+            history: history,
+            history_changed: false,
+            closed_files: Vec::new(),
+            commands: RefCell::new(Vec::new())
+        };
+        core.add_project_history();
+        let session = core.read_session();
+        if let (Some(ref session), true) = (session.as_ref(), restore_session) {
+            core.restore_session(session);
+        }
+        core.session = session;
+        core.update_vcs_status();
+        core.update_watches();
+        core
+    }
+
+    /// Opens the views of the session saved on the last exit and restores the layout.
+    fn restore_session(&mut self, session: &Session) {
+        let mut groups: Vec<Group> = Vec::new();
+        for saved in &session.groups {
+            let mut group = Group::new();
+            for saved_view in &saved.views {
+                let view = self.restore_view(&groups, &group, saved_view);
+                group.views.extend(view);
+            }
+            group.select(saved.active);
+            groups.push(group);
+        }
+        if groups.is_empty() {
+            groups.push(Group::new());
+        }
+        self.groups = groups;
+        self.active_group = min(session.active_group, self.groups.len() - 1);
+        // groups are merged when the layout doesn't fit them
+        self.set_layout(session.layout.clone().unwrap_or_else(Layout::single));
+    }
+
+    /// Creates the view of the saved file, the buffer is shared with the restored view
+    /// of the same file. Unsaved contents replace the text of the file.
+    fn restore_view(&self, groups: &[Group], group: &Group, saved: &SessionView)
+                    -> Option<View> {
+        let shared = saved.file.as_ref().and_then(|file| {
+            groups.iter().chain(Some(group))
+                .flat_map(|group| group.views.iter())
+                .find(|view| view.buffer().path() == Some(file.as_path()))
+        });
+        let mut view = match shared {
+            Some(view) => view.clone_view(),
+            None => {
+                let mut buffer = match saved.file {
+                    Some(ref file) => match Buffer::open(file.clone()) {
+                        Ok(buffer) => buffer,
+                        Err(_) if saved.contents.is_some() => {
+                            let mut buffer = Buffer::new();
+                            buffer.set_path(file.clone());
+                            buffer
+                        },
+                        Err(err) => {
+                            warn!("Can't restore file {:?}: {:?}", file, err);
+                            return None
+                        }
+                    },
+                    None => Buffer::new()
+                };
+                if let Some(parser) = Core::create_parser(&self.package_repository,
+                                                          buffer.path()) {
+                    buffer.set_parser(parser);
+                }
+                self.read_diff_base(&buffer);
+                if let Some(ref contents) = saved.contents {
+                    let end = buffer.last_point();
+                    buffer.replace(Point::new(0, 0), end, contents);
+                    buffer.commit();
+                }
+                let mut view = View::new(buffer);
+                view.preferences = self.preferences.clone();
+                view
+            }
+        };
+        let selection: Vec<Region> = saved.selection.iter().map(|region| {
+            let buffer = view.buffer();
+            Region::new(buffer.clip_point(region.a.line, region.a.column),
+                        buffer.clip_point(region.b.line, region.b.column))
+        }).collect();
+        if !selection.is_empty() {
+            view.selection = selection;
+        }
+        view.set_viewport_position(saved.viewport.0, saved.viewport.1);
+        Some(view)
+    }
+
+    fn read_session(&self) -> Option<Session> {
+        match self.project.read_session() {
+            Ok(session) => session,
+            Err(err) => {
+                warn!("Can't read session: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Returns the session saved on the last exit, the window restores its part of it.
+    pub fn take_session(&mut self) -> Option<Session> {
+        self.session.take()
+    }
+
+    /// Returns the open views and the layout to restore them on the next start.
+    /// Modified buffers keep their text when `hot_exit` is enabled.
+    pub fn session(&self) -> Session {
+        let mut groups = Vec::new();
+        for group in &self.groups {
+            let mut saved = SessionGroup::default();
+            for (index, view) in group.views.iter().enumerate() {
+                let buffer = view.buffer();
+                let contents = if buffer.is_dirty() && self.preferences.hot_exit {
+                    Some(buffer.substr(Point::new(0, 0), buffer.last_point()))
+                } else {
+                    None
+                };
+                // results of find in files and untitled buffers without text aren't kept
+                if buffer.is_scratch() || (buffer.path().is_none() && contents.is_none()) {
+                    continue
+                }
+                if group.active_index() == Some(index) {
+                    saved.active = saved.views.len();
+                }
+                saved.views.push(SessionView {
+                    // session is restored regardless of the current folder
+                    file: buffer.path().map(absolute_path),
+                    contents: contents,
+                    selection: view.selection.clone(),
+                    viewport: view.viewport_position()
+                });
+            }
+            groups.push(saved);
+        }
+        Session {
+            layout: Some(self.layout.clone()),
+            active_group: self.active_group,
+            groups: groups,
+            ..Session::default()
+        }
+    }
+
+    /// Writes the session to the workspace file of the project.
+    pub fn save_session(&self, session: &Session) {
+        if let Err(err) = self.project.write_session(session) {
+            error!("Can't save session: {:?}", err);
+        }
+    }
+
+    /// Reads default preferences and overrides them by the project settings.
+    fn load_preferences(repository: &PackageRepository, project: &Project) -> Preferences {
+        let mut preferences = match repository.read_json(Path::new(PREFERENCES)) {
+            Ok(settings) => Preferences::from_settings(settings),
+            Err(err) => {
+                error!("Can't read preferences: {:?}", err);
+                Preferences::default()
+            }
+        };
+        if let Some(ref settings) = project.settings {
+            preferences.update(settings);
+        }
+        preferences
+    }
+
+    /// Reads symbol list settings from all `.tmPreferences` files of the packages.
+    fn load_symbol_list(repository: &PackageRepository) -> SymbolList {
+        let mut list = SymbolList::new();
+        let files = match repository.find_files("tmPreferences") {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Can't find preferences files: {:?}", err);
+                return list
+            }
+        };
+        for path in files {
+            match repository.get_symbol_preferences(&path) {
+                Ok(preferences) => list.add(preferences),
+                Err(err) => error!("Can't read {:?}: {:?}", path, err)
+            }
+        }
+        list
+    }
+
+    /// Reads build systems from all `.sublime-build` files of the packages.
+    fn load_build_systems(repository: &PackageRepository) -> Vec<BuildSystem> {
+        let files = match repository.find_files("sublime-build") {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Can't find build files: {:?}", err);
+                return Vec::new()
+            }
+        };
+        let mut build_systems = Vec::new();
+        for path in files {
+            match repository.get_build_system(&path) {
+                Ok(build_system) => build_systems.push(build_system),
+                Err(err) => error!("Can't read {:?}: {:?}", path, err)
+            }
+        }
+        build_systems
+    }
+
+    /// Creates parser for the syntax that handles the file extension.
+    fn create_parser(repository: &PackageRepository, path: Option<&Path>) -> Option<Parser> {
+        Core::load_syntax(repository, path).map(Parser::from_syntax)
+    }
+
+    /// Reads the syntax that handles the file extension.
+    fn load_syntax(repository: &PackageRepository, path: Option<&Path>) -> Option<Syntax> {
+        let extension = match path.and_then(Path::extension) {
+            Some(extension) => extension.to_string_lossy(),
+            None => return None
+        };
+        let syntax = repository.find_syntax(&extension)
+            .and_then(|path| match path {
+                Some(path) => repository.get_syntax(path).map(Some),
+                None => Ok(None)
+            });
+        match syntax {
+            Ok(syntax) => syntax,
+            Err(err) => {
+                error!("Can't load syntax for {:?}: {:?}", extension, err);
+                None
+            }
         }
     }
 
+    /// Returns the main menu. Items of the recent files, folders and projects get the paths
+    /// as captions, items of the build systems get their names. Items beyond the history
+    /// or the build systems are removed.
     pub fn create_menu(&self) -> Menu {
         // todo: fix unwrap
-        self.package_repository.get_menu("default/Main.sublime-menu").unwrap()
+        let menu = self.package_repository.get_menu("default/Main.sublime-menu").unwrap();
+        self.expand_dynamic_items(menu)
+    }
+
+    fn expand_dynamic_items(&self, menu: Menu) -> Menu {
+        let mut expanded = Menu::new();
+        for item in menu {
+            let item = match item {
+                MenuItem::Group(caption, menu) => {
+                    MenuItem::Group(caption, self.expand_dynamic_items(menu))
+                },
+                MenuItem::Button(None, command, is_checkbox) => {
+                    let caption = match &command.name[..] {
+                        "open_recent_file" | "open_recent_folder" | "open_recent_project" => {
+                            self.recent_path(&command).map(|path| {
+                                path.to_string_lossy().into_owned()
+                            })
+                        },
+                        "set_build_system" => self.indexed_build_system(&command)
+                            .map(|build_system| build_system.name.clone().unwrap_or_default()),
+                        "build" => match command.args.get("variant").and_then(Settings::as_string) {
+                            Some(variant) => Some(format!("Build: {}", variant)),
+                            None => Some("Build".to_owned())
+                        },
+                        // placeholder of the build systems that don't fit the menu
+                        "$build_systems" => None,
+                        _ => {
+                            expanded.push(MenuItem::Button(None, command, is_checkbox));
+                            continue
+                        }
+                    };
+                    match caption {
+                        Some(caption) => MenuItem::Button(Some(caption), command, is_checkbox),
+                        None => continue
+                    }
+                },
+                // dividers around the removed items aren't doubled
+                MenuItem::Divider => match expanded.last() {
+                    Some(&MenuItem::Divider) | None => continue,
+                    _ => MenuItem::Divider
+                },
+                item => item
+            };
+            expanded.push(item);
+        }
+        expanded
+    }
+
+    /// Returns the path opened by the `open_recent_file`, `open_recent_folder`
+    /// or `open_recent_project` command.
+    pub fn recent_path(&self, command: &Command) -> Option<&Path> {
+        let paths = match &command.name[..] {
+            "open_recent_file" => self.history.files(),
+            "open_recent_folder" => self.history.folders(),
+            "open_recent_project" => self.history.projects(),
+            _ => return None
+        };
+        let index = command.args.get("index").and_then(Settings::as_u64).unwrap_or(0);
+        paths.get(index as usize).map(PathBuf::as_path)
+    }
+
+    /// Returns the build systems of the project followed by the build systems of the packages.
+    pub fn build_systems(&self) -> Vec<&BuildSystem> {
+        self.project.build_systems.iter().chain(&self.package_build_systems).collect()
+    }
+
+    /// Returns the build system chosen by the `index` argument of the `set_build_system`
+    /// command.
+    pub fn indexed_build_system(&self, command: &Command) -> Option<&BuildSystem> {
+        let index = command.args.get("index").and_then(Settings::as_u64);
+        index.and_then(|index| self.build_systems().get(index as usize).cloned())
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns true once after the history was changed, so the menu is created again.
+    pub fn take_history_change(&mut self) -> bool {
+        ::std::mem::replace(&mut self.history_changed, false)
+    }
+
+    fn save_history(&mut self) {
+        self.history_changed = true;
+        if let Err(err) = self.history.save() {
+            error!("Can't save history: {:?}", err);
+        }
+    }
+
+    /// Remembers the project file, or the folders of the project without a file.
+    fn add_project_history(&mut self) {
+        match self.project.path {
+            Some(ref path) => self.history.add_project(path),
+            None => for pf in &self.project.folders {
+                self.history.add_folder(&pf.path);
+            }
+        }
+        self.save_history();
+    }
+
+    pub fn clear_recent_files(&mut self) {
+        self.history.clear_files();
+        self.save_history();
+    }
+
+    pub fn clear_recent_projects(&mut self) {
+        self.history.clear_projects();
+        self.save_history();
+    }
+
+    /// Returns the context menu of the sidebar entries.
+    pub fn create_sidebar_menu(&self) -> Menu {
+        match self.package_repository.get_menu("default/Side Bar.sublime-menu") {
+            Ok(menu) => menu,
+            Err(err) => {
+                error!("Can't read sidebar menu: {:?}", err);
+                Menu::new()
+            }
+        }
+    }
+
+    /// Reads commands from all `.sublime-commands` files of the packages.
+    pub fn create_command_list(&self) -> CommandList {
+        let mut list = CommandList::new();
+        let files = match self.package_repository.find_files("sublime-commands") {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Can't find command files: {:?}", err);
+                return list
+            }
+        };
+        for path in files {
+            match self.package_repository.get_command_list(&path) {
+                Ok(commands) => list.extend(commands),
+                Err(err) => error!("Can't read {:?}: {:?}", path, err)
+            }
+        }
+        list
     }
 
     pub fn create_highlighter(&self) -> Result<Highlighter, PackageError> {
@@ -58,4 +551,921 @@ impl Core {
         Ok(highlighter)
     }
 
+    /// Returns the binding for the mouse button. Later bindings override earlier ones.
+    pub fn mouse_binding(&self, button: MouseButton, count: u8, modifiers: Modifiers)
+            -> Option<&MouseBinding> {
+        self.mousemap.iter().rev().find(|binding| binding.matches(button, count, modifiers))
+    }
+
+    /// Schedules command to be performed by the window after the current event.
+    pub fn run_command(&self, command: Command) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    /// Takes all scheduled commands.
+    pub fn take_commands(&self) -> Vec<Command> {
+        ::std::mem::replace(&mut *self.commands.borrow_mut(), Vec::new())
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn active_group(&self) -> usize {
+        self.active_group
+    }
+
+    pub fn focus_group(&mut self, index: usize) {
+        if index < self.groups.len() {
+            self.active_group = index;
+        }
+    }
+
+    pub fn focus_neighboring_group(&mut self, forward: bool) {
+        let len = self.groups.len();
+        self.active_group = if forward {
+            (self.active_group + 1) % len
+        } else {
+            (self.active_group + len - 1) % len
+        };
+    }
+
+    pub fn views_in_group(&self, index: usize) -> &[View] {
+        match self.groups.get(index) {
+            Some(group) => &group.views,
+            None => &[]
+        }
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.groups[self.active_group]
+    }
+
+    pub fn group_mut(&mut self) -> &mut Group {
+        &mut self.groups[self.active_group]
+    }
+
+    pub fn active_view(&self) -> Option<&View> {
+        self.group().active_view()
+    }
+
+    pub fn active_view_mut(&mut self) -> Option<&mut View> {
+        self.group_mut().active_view_mut()
+    }
+
+    /// Changes layout of the window. When the new layout has less cells than there are groups,
+    /// views of the removed groups are moved to the last remaining group.
+    pub fn set_layout(&mut self, layout: Layout) {
+        let num_cells = layout.num_cells();
+        while self.groups.len() > num_cells {
+            let group = self.groups.pop().unwrap();
+            self.groups.last_mut().unwrap().merge(group);
+        }
+        while self.groups.len() < num_cells {
+            self.groups.push(Group::new());
+        }
+        if self.active_group >= num_cells {
+            self.active_group = num_cells - 1;
+        }
+        self.layout = layout;
+    }
+
+    /// Performs edit in the active view as a single undo step and updates selections
+    /// of other views into the same buffer.
+    pub fn edit<F>(&mut self, f: F) where F: FnOnce(&mut View) -> Vec<Edit> {
+        let (view_id, buffer_id, edits) = match self.active_view_mut() {
+            Some(view) => {
+                let edits = f(view);
+                view.buffer_mut().commit();
+                (view.id(), view.buffer_id(), edits)
+            },
+            None => return
+        };
+        for group in &mut self.groups {
+            for view in &mut group.views {
+                if view.buffer_id() == buffer_id && view.id() != view_id {
+                    view.adjust(&edits);
+                }
+            }
+        }
+    }
+
+    /// Opens new view into the buffer of the active view.
+    pub fn clone_file(&mut self) {
+        let view = match self.active_view() {
+            Some(view) => view.clone_view(),
+            None => return
+        };
+        self.group_mut().add_view(view);
+    }
+
+    /// Moves active view to the given group and focuses it.
+    pub fn move_to_group(&mut self, index: usize) {
+        if index >= self.groups.len() || index == self.active_group {
+            return
+        }
+        if let Some(view) = self.group_mut().take_active_view() {
+            self.groups[index].add_view(view);
+            self.active_group = index;
+        }
+    }
+
+    /// Returns indices of the group and the view of the opened file.
+    fn find_view(&self, path: &Path) -> Option<(usize, usize)> {
+        for (index, group) in self.groups.iter().enumerate() {
+            let position = group.views.iter().position(|view| view.buffer().path() == Some(path));
+            if let Some(position) = position {
+                return Some((index, position))
+            }
+        }
+        None
+    }
+
+    /// Returns indices of the group and the view into the buffer.
+    fn find_buffer_view(&self, buffer_id: BufferId) -> Option<(usize, usize)> {
+        for (index, group) in self.groups.iter().enumerate() {
+            let position = group.views.iter().position(|view| view.buffer_id() == buffer_id);
+            if let Some(position) = position {
+                return Some((index, position))
+            }
+        }
+        None
+    }
+
+    /// Focuses the view of the file if it's already opened, otherwise opens it in the active group.
+    /// Transient view previews the file until another file is previewed or the view is opened
+    /// permanently.
+    pub fn open_file(&mut self, path: PathBuf, transient: bool) {
+        let found = self.find_view(&path);
+        let is_transient = found.map_or(false, |(group, index)| {
+            self.transient == Some(self.groups[group].views[index].id())
+        });
+        if is_transient && !transient {
+            self.transient = None;
+        } else if !is_transient && transient {
+            self.close_transient();
+        }
+        // closing of the transient view may shift the views
+        if let Some((group, index)) = self.find_view(&path) {
+            self.groups[group].select(index);
+            self.active_group = group;
+            if !transient {
+                self.add_file_history(&path);
+            }
+            return
+        }
+        let mut buffer = match Buffer::open(path.clone()) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!("Can't open file: {:?}", err);
+                return
+            }
+        };
+        if let Some(parser) = Core::create_parser(&self.package_repository, buffer.path()) {
+            buffer.set_parser(parser);
+        }
+        self.read_diff_base(&buffer);
+        let mut view = View::new(buffer);
+        view.preferences = self.preferences.clone();
+        if transient {
+            self.transient = Some(view.id());
+        }
+        self.group_mut().add_view(view);
+        self.update_watches();
+        // previewed files aren't remembered
+        if !transient {
+            self.add_file_history(&path);
+        }
+    }
+
+    fn add_file_history(&mut self, path: &Path) {
+        self.history.add_file(path);
+        self.save_history();
+    }
+
+    /// Opens an untitled buffer with the text in the active group, the text is unsaved.
+    pub fn new_file(&mut self, text: &str) {
+        let mut buffer = Buffer::new();
+        if !text.is_empty() {
+            buffer.append(text);
+        }
+        let mut view = View::new(buffer);
+        view.preferences = self.preferences.clone();
+        self.group_mut().add_view(view);
+    }
+
+    /// Moves the caret of the active view to the line and the column, counted from 1.
+    pub fn goto_position(&mut self, line: usize, column: usize) {
+        if let Some(view) = self.active_view_mut() {
+            let point = view.buffer().clip_point(line.saturating_sub(1), column.saturating_sub(1));
+            view.select_region(Region::caret(point));
+        }
+    }
+
+    /// Closes the view opened to preview a file.
+    pub fn close_transient(&mut self) {
+        let id = match self.transient.take() {
+            Some(id) => id,
+            None => return
+        };
+        for group in &mut self.groups {
+            if let Some(index) = group.views.iter().position(|view| view.id() == id) {
+                group.remove_view(index);
+                return
+            }
+        }
+    }
+
+    /// Closes the active view, its file can be opened again by `reopen_last_file`.
+    pub fn close_view(&mut self) {
+        let view = match self.group_mut().take_active_view() {
+            Some(view) => view,
+            None => return
+        };
+        if self.transient == Some(view.id()) {
+            self.transient = None;
+        } else if let Some(path) = view.buffer().path().map(Path::to_path_buf) {
+            self.closed_files.retain(|closed| *closed != path);
+            self.closed_files.push(path);
+        }
+        self.update_watches();
+    }
+
+    /// Opens the file of the last closed view.
+    pub fn reopen_last_file(&mut self) {
+        if let Some(path) = self.closed_files.pop() {
+            self.open_file(path, false);
+        }
+    }
+
+    /// Walks the changed folder of the project again and updates the symbol index.
+    fn refresh_folder(&mut self, path: &Path) {
+        if let Err(err) = self.project.refresh(path) {
+            error!("Can't walk folder {:?}: {:?}", path, err);
+        }
+        self.symbol_index.set_files(self.project.index_files());
+        self.update_vcs_status();
+        self.update_watches();
+    }
+
+    /// Walks all the project folders again.
+    pub fn refresh_folders(&mut self) {
+        if let Err(err) = self.project.walk() {
+            error!("Can't walk project folders: {:?}", err);
+        }
+        self.symbol_index.set_files(self.project.index_files());
+        self.update_vcs_status();
+        self.update_watches();
+    }
+
+    /// Adds the folder to the project and writes the project file.
+    pub fn add_folder(&mut self, path: &Path) {
+        if let Err(err) = self.project.add_folder(path) {
+            error!("Can't add folder {:?}: {:?}", path, err);
+            return
+        }
+        self.save_project();
+        self.symbol_index.set_files(self.project.index_files());
+        self.update_vcs_status();
+        self.update_watches();
+    }
+
+    /// Removes the folder from the project and writes the project file, the folder
+    /// isn't deleted.
+    pub fn remove_folder(&mut self, path: &Path) {
+        self.project.remove_folder(path);
+        self.save_project();
+        self.symbol_index.set_files(self.project.index_files());
+        self.update_vcs_status();
+        self.update_watches();
+    }
+
+    fn save_project(&self) {
+        if let Err(err) = self.project.save() {
+            error!("Can't save project: {:?}", err);
+        }
+    }
+
+    /// Writes the project to another file, it becomes the project file.
+    pub fn save_project_as(&mut self, path: PathBuf) {
+        match self.project.save_as(path) {
+            Ok(()) => self.add_project_history(),
+            Err(err) => error!("Can't save project: {:?}", err)
+        }
+    }
+
+    /// Replaces the project of the window, the open files stay open. Session of the new
+    /// project is read, so the window can take its part. The default workspace is kept
+    /// for the projects without a file.
+    pub fn open_project(&mut self, mut project: Project) {
+        project.default_workspace = self.project.default_workspace.take();
+        self.project = project;
+        self.preferences = Core::load_preferences(&self.package_repository, &self.project);
+        self.session = self.read_session();
+        self.add_project_history();
+        self.symbol_index.set_files(self.project.index_files());
+        self.update_vcs_status();
+        self.update_watches();
+    }
+
+    /// Asks to read git status of the project folders, it's taken by `update_vcs`.
+    fn update_vcs_status(&self) {
+        self.vcs_reader.read(self.project.folders.iter().map(|pf| pf.path.clone()).collect());
+    }
+
+    /// Takes git status of the project folders when it's read. Git folders of the new
+    /// repositories are watched. Returns false if the status isn't read yet.
+    pub fn update_vcs(&mut self) -> bool {
+        let status = match self.vcs_reader.status() {
+            Some(status) => status,
+            None => return false
+        };
+        let is_repository_changed = status.git_dirs() != self.vcs_status.git_dirs();
+        self.vcs_status = status;
+        if is_repository_changed {
+            self.update_watches();
+        }
+        true
+    }
+
+    /// Asks to read the version of the file in git HEAD to mark the changed lines,
+    /// it's taken by `update_diff_bases`.
+    fn read_diff_base(&self, buffer: &Buffer) {
+        if let Some(path) = buffer.path() {
+            self.head_reader.read(path.to_path_buf());
+        }
+    }
+
+    /// Asks to read git HEAD versions of the open files again, after a commit for example.
+    fn read_diff_bases(&self) {
+        let mut paths = Vec::new();
+        for view in self.groups.iter().flat_map(|group| group.views.iter()) {
+            if let Some(path) = view.buffer().path() {
+                if !paths.iter().any(|read| read == path) {
+                    paths.push(path.to_path_buf());
+                }
+            }
+        }
+        for path in paths {
+            self.head_reader.read(path);
+        }
+    }
+
+    /// Takes git HEAD versions of the files when they are read and marks the changed lines
+    /// of their buffers. Returns false if nothing is read yet.
+    pub fn update_diff_bases(&mut self) -> bool {
+        let bases = self.head_reader.lines();
+        let mut updated = Vec::new();
+        for view in self.groups.iter().flat_map(|group| group.views.iter()) {
+            if updated.contains(&view.buffer_id()) {
+                continue
+            }
+            let base = view.buffer().path()
+                .and_then(|path| bases.iter().rev().find(|&&(ref read, _)| read == path));
+            if let Some(&(_, ref lines)) = base {
+                updated.push(view.buffer_id());
+                view.buffer_mut().set_diff_base(lines.clone());
+            }
+        }
+        !bases.is_empty()
+    }
+
+    /// Watches the project folders, the folders of the open files and the git folders.
+    fn update_watches(&mut self) {
+        let mut folders: BTreeSet<PathBuf> = self.project.folder_paths().into_iter().collect();
+        folders.extend(self.vcs_status.git_dirs().iter().cloned());
+        for view in self.groups.iter().flat_map(|group| group.views.iter()) {
+            if let Some(parent) = view.buffer().path().and_then(Path::parent) {
+                let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                folders.insert(parent.to_path_buf());
+            }
+        }
+        self.watcher.watch(folders);
+    }
+
+    /// Applies the changes made on disk. Folders are read again, and unmodified buffers of the
+    /// changed files are reloaded. Returns false if nothing was changed.
+    pub fn update_watched(&mut self) -> bool {
+        let changes = self.watcher.changes();
+        if changes.is_empty() {
+            return false
+        }
+        let is_git_changed = changes.iter().any(|change| match *change {
+            Change::Entry(ref path) | Change::Contents(ref path) => {
+                self.vcs_status.git_dirs().iter().any(|dir| path.starts_with(dir))
+            },
+            Change::Overflow => true
+        });
+        let mut files = Vec::new();
+        let mut changed_paths = Vec::new();
+        let is_overflow = changes.contains(&Change::Overflow);
+        if is_overflow {
+            if let Err(err) = self.project.walk() {
+                error!("Can't walk project folders: {:?}", err);
+            }
+        }
+        for change in changes {
+            let path = match change {
+                Change::Entry(path) => {
+                    if let Some(parent) = path.parent() {
+                        // the folder itself may be deleted
+                        if parent.is_dir() {
+                            if let Err(err) = self.project.update_folder(parent) {
+                                error!("Can't read folder {:?}: {:?}", parent, err);
+                            }
+                        }
+                    }
+                    path
+                },
+                Change::Contents(path) => path,
+                Change::Overflow => continue
+            };
+            files.extend(path.canonicalize().ok());
+            changed_paths.push(path);
+        }
+        let index_files = self.project.index_files();
+        // any file may be changed when the changes are lost
+        if is_overflow {
+            changed_paths = index_files.iter().map(|file| file.path.clone()).collect();
+        }
+        self.symbol_index.set_files(index_files);
+        self.symbol_index.update_files(changed_paths);
+        self.update_vcs_status();
+        self.update_watches();
+        if is_git_changed {
+            self.read_diff_bases();
+        }
+        let mut changed = Vec::new();
+        for view in self.groups.iter().flat_map(|group| group.views.iter()) {
+            let path = match view.buffer().path() {
+                Some(path) => path.to_path_buf(),
+                None => continue
+            };
+            let is_changed = path.canonicalize().ok().map_or(false, |file| files.contains(&file));
+            if is_changed && !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+        for path in changed {
+            let is_dirty = self.find_view(&path)
+                .map_or(false, |(group, index)| self.groups[group].views[index].is_dirty());
+            if !is_dirty {
+                self.revert(&path);
+            } else if !self.dirty_changes.contains(&path) {
+                self.dirty_changes.push(path);
+            }
+        }
+        true
+    }
+
+    /// Takes the modified file that was changed on disk, to ask whether to reload it.
+    pub fn take_dirty_change(&mut self) -> Option<PathBuf> {
+        if self.dirty_changes.is_empty() {
+            None
+        } else {
+            Some(self.dirty_changes.remove(0))
+        }
+    }
+
+    /// Reloads the buffer of the file from disk, the reload can be undone.
+    pub fn revert(&mut self, path: &Path) {
+        let (buffer_id, edit) = match self.find_view(path) {
+            Some((group, index)) => {
+                let view = &self.groups[group].views[index];
+                let edit = view.buffer_mut().reload();
+                (view.buffer_id(), edit)
+            },
+            None => return
+        };
+        let edit = match edit {
+            Ok(Some(edit)) => edit,
+            Ok(None) => return,
+            Err(err) => {
+                error!("Can't reload file {:?}: {:?}", path, err);
+                return
+            }
+        };
+        for group in &mut self.groups {
+            for view in &mut group.views {
+                if view.buffer_id() == buffer_id {
+                    view.adjust(&[edit]);
+                }
+            }
+        }
+    }
+
+    /// Creates the empty file in the folder and opens it. The name may contain subfolders.
+    pub fn create_file(&mut self, folder: &Path, name: &str) -> Result<(), IoError> {
+        let path = folder.join(name);
+        if let Some(parent) = path.parent() {
+            try!(fs::create_dir_all(parent));
+        }
+        try!(OpenOptions::new().write(true).create_new(true).open(&path));
+        self.refresh_folder(folder);
+        self.open_file(path, false);
+        Ok(())
+    }
+
+    /// Creates the folder together with the missing parent folders.
+    pub fn create_folder(&mut self, folder: &Path, name: &str) -> Result<(), IoError> {
+        try!(fs::create_dir_all(folder.join(name)));
+        self.refresh_folder(folder);
+        Ok(())
+    }
+
+    /// Renames the file or the folder. Views of the renamed files follow the new paths.
+    /// Existing file or folder is never replaced, except the renamed one itself when only
+    /// the case of its name is changed on a case-insensitive file system.
+    pub fn rename_path(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        if fs::symlink_metadata(to).is_ok() && !is_same_file(from, to) {
+            return Err(IoError::new(ErrorKind::AlreadyExists, "target already exists"))
+        }
+        try!(fs::rename(from, to));
+        for view in self.groups.iter_mut().flat_map(|group| group.views.iter_mut()) {
+            let path = match view.buffer().path().and_then(|path| path.strip_prefix(from).ok()) {
+                Some(relative) if relative.as_os_str().is_empty() => to.to_path_buf(),
+                Some(relative) => to.join(relative),
+                None => continue
+            };
+            // extension may be changed
+            if let Some(parser) = Core::create_parser(&self.package_repository, Some(&path)) {
+                view.set_parser(parser);
+            }
+            self.head_reader.read(path.clone());
+            view.buffer_mut().set_path(path);
+        }
+        for folder in from.parent().into_iter().chain(to.parent()) {
+            self.refresh_folder(folder);
+        }
+        Ok(())
+    }
+
+    /// Deletes the file or the folder with its contents. Views of the deleted files are closed,
+    /// except the modified ones, so their text isn't lost and can be saved again.
+    pub fn delete_path(&mut self, path: &Path) -> Result<(), IoError> {
+        if try!(fs::symlink_metadata(path)).is_dir() {
+            try!(fs::remove_dir_all(path));
+        } else {
+            try!(fs::remove_file(path));
+        }
+        for group in &mut self.groups {
+            // going backward, so removing doesn't shift the remaining indices
+            for index in (0..group.views.len()).rev() {
+                let is_deleted = {
+                    let buffer = group.views[index].buffer();
+                    !buffer.is_dirty() && buffer.path().map_or(false, |file| file.starts_with(path))
+                };
+                if is_deleted {
+                    group.remove_view(index);
+                }
+            }
+        }
+        if let Some(folder) = path.parent() {
+            self.refresh_folder(folder);
+        }
+        Ok(())
+    }
+
+    /// Returns files of the location. Open files are included when they're requested.
+    fn location_files(&self, location: &Where) -> Vec<PathBuf> {
+        let open_files: Vec<PathBuf> = self.groups.iter()
+            .flat_map(|group| group.views.iter())
+            .filter_map(|view| view.buffer().path().map(Path::to_path_buf))
+            .collect();
+        find_in_files::collect_files(location, &self.project, &open_files)
+    }
+
+    /// Focuses the view of the scratch buffer, it's opened in the active group unless
+    /// it's already opened. Returns the id of the buffer.
+    fn show_scratch_view(&mut self, buffer_id: Option<BufferId>, name: &str) -> BufferId {
+        match buffer_id.and_then(|id| self.find_buffer_view(id)) {
+            Some((group, index)) => {
+                self.groups[group].select(index);
+                self.active_group = group;
+                self.groups[group].views[index].buffer_id()
+            },
+            None => {
+                let mut view = View::new(Buffer::scratch(name));
+                view.preferences = self.preferences.clone();
+                let id = view.buffer_id();
+                self.group_mut().add_view(view);
+                id
+            }
+        }
+    }
+
+    /// Focuses the results view of find in files. Returns the results buffer.
+    fn show_results_view(&mut self) -> Option<RefMut<Buffer>> {
+        let id = self.show_scratch_view(self.results_buffer, find_in_files::RESULTS_NAME);
+        self.results_buffer = Some(id);
+        self.active_view().map(View::buffer_mut)
+    }
+
+    /// Focuses the view of the build output. Returns the output buffer.
+    pub fn show_output_view(&mut self) -> Option<RefMut<Buffer>> {
+        let id = self.show_scratch_view(self.output_buffer, exec::OUTPUT_NAME);
+        self.output_buffer = Some(id);
+        self.active_view().map(View::buffer_mut)
+    }
+
+    /// Starts searching the files in the background. Results are shown in the results view.
+    pub fn find_in_files(&mut self, pattern: &str, options: SearchOptions, location: &Where)
+            -> Result<(), RegexError> {
+        // pattern is checked before the previous results are cleared
+        try!(Search::new(pattern, options, &[]));
+        let files = self.location_files(location);
+        self.is_build_result = false;
+        let results = match self.show_results_view() {
+            Some(mut buffer) => {
+                buffer.clear();
+                FindResults::start(pattern, options, files, &mut buffer)
+            },
+            None => return Ok(())
+        };
+        self.find_results = Some(results);
+        Ok(())
+    }
+
+    /// Prepares replacement of the matches in the files and shows its preview in the results
+    /// view. Open files are replaced in their buffers. Returns the number of replacements.
+    pub fn prepare_replace_in_files(&mut self, pattern: &str, format: &str,
+                                    options: SearchOptions, location: &Where)
+            -> Result<usize, RegexError> {
+        let search = try!(Search::new(pattern, options, &[]));
+        let mut files = Vec::new();
+        for path in self.location_files(location) {
+            let replacements = match self.find_view(&path) {
+                Some((group, index)) => {
+                    let buffer = self.groups[group].views[index].buffer();
+                    FileReplacements::new(path, &search, format, &buffer, Encoding::Utf8)
+                },
+                None => match find_in_files::open_file(&path) {
+                    Some((buffer, encoding)) => {
+                        FileReplacements::new(path, &search, format, &buffer, encoding)
+                    },
+                    None => continue
+                }
+            };
+            if !replacements.replacements.is_empty() {
+                files.push(replacements);
+            }
+        }
+        let count = files.iter().map(|file| file.replacements.len()).sum();
+        // old results don't match the view anymore
+        self.find_results = None;
+        self.is_build_result = false;
+        if let Some(mut buffer) = self.show_results_view() {
+            buffer.clear();
+            buffer.append(&find_in_files::preview(pattern, format, &files));
+        }
+        self.pending_replacements = if count > 0 { Some(files) } else { None };
+        Ok(count)
+    }
+
+    /// Performs the prepared replacements. Open files are changed as a single undo step,
+    /// other files are written. Results are appended to the results view.
+    pub fn replace_in_files(&mut self) {
+        let files = match self.pending_replacements.take() {
+            Some(files) => files,
+            None => return
+        };
+        let mut report = String::new();
+        let (mut num_replaced, mut num_files) = (0, 0);
+        for file in files {
+            let replaced = match self.find_view(&file.path) {
+                Some((group, index)) => self.replace_in_view(group, index, &file),
+                None => file.write()
+            };
+            match replaced {
+                Ok(()) => {
+                    num_replaced += file.replacements.len();
+                    num_files += 1;
+                },
+                Err(err) => {
+                    report.push_str(&format!("\nCan't replace in {}: {:?}\n",
+                                             file.path.display(), err));
+                }
+            }
+        }
+        report.push_str(&format!("\n{} replacements in {} files\n", num_replaced, num_files));
+        if let Some((group, index)) = self.results_buffer.and_then(|id| self.find_buffer_view(id)) {
+            self.groups[group].views[index].buffer_mut().append(&report);
+        }
+    }
+
+    /// Replaces the matches in the buffer of the view as a single undo step.
+    fn replace_in_view(&mut self, group: usize, index: usize, file: &FileReplacements)
+            -> Result<(), ReplaceError> {
+        let view = &self.groups[group].views[index];
+        let buffer_id = view.buffer_id();
+        let edits: Vec<Edit> = {
+            let mut buffer = view.buffer_mut();
+            try!(file.check(&buffer));
+            // replacing from the end keeps the preceding regions in place
+            let edits = file.replacements.iter().rev().map(|replacement| {
+                let region = replacement.region;
+                buffer.replace(region.begin(), region.end(), &replacement.new_text)
+            }).collect();
+            buffer.commit();
+            edits
+        };
+        for group in &mut self.groups {
+            for view in &mut group.views {
+                if view.buffer_id() == buffer_id {
+                    view.adjust(&edits);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends new results of find in files to the results view. Returns false if there was
+    /// nothing to append. Search is stopped when the results view is closed.
+    pub fn update_find_results(&mut self) -> bool {
+        let found = match self.find_results {
+            Some(ref results) => self.find_buffer_view(results.buffer_id()),
+            None => return false
+        };
+        match (found, self.find_results.as_mut()) {
+            (Some((group, index)), Some(results)) => {
+                results.update(&mut self.groups[group].views[index].buffer_mut())
+            },
+            _ => {
+                self.find_results = None;
+                false
+            }
+        }
+    }
+
+    /// Opens the next match of find in files, or the previous one, and selects its line
+    /// in the results view. Errors of the build are taken instead when it's run later.
+    pub fn next_result(&mut self, forward: bool) {
+        let (found, buffer_id) = if self.is_build_result {
+            self.update_build();
+            match self.build {
+                Some(ref mut build) => (build.next_error(forward).cloned(), build.buffer_id()),
+                None => return
+            }
+        } else {
+            self.update_find_results();
+            match self.find_results {
+                Some(ref mut results) => {
+                    (results.next_match(forward).cloned(), results.buffer_id())
+                },
+                None => return
+            }
+        };
+        let found = match found {
+            Some(found) => found,
+            None => return
+        };
+        if let Some((group, index)) = self.find_buffer_view(buffer_id) {
+            let view = &mut self.groups[group].views[index];
+            let line = found.result_line;
+            let end = view.buffer().clip_point(line, usize::max_value());
+            view.select_region(Region::new(Point::new(line, 0), end));
+        }
+        self.open_file(found.path, false);
+        if let Some(view) = self.active_view_mut() {
+            let (begin, end) = (found.region.begin(), found.region.end());
+            let region = {
+                let buffer = view.buffer();
+                Region::new(buffer.clip_point(begin.line, begin.column),
+                            buffer.clip_point(end.line, end.column))
+            };
+            view.select_region(region);
+        }
+    }
+
+    /// Chooses the build system by the index in the list of the build systems, the build
+    /// system is chosen by the active file when the index is missing.
+    pub fn set_build_system(&mut self, index: Option<usize>) {
+        self.build_system = index;
+    }
+
+    /// Returns the build system chosen in the menu, or the first one with the selector
+    /// that matches the syntax of the active file.
+    fn choose_build_system(&self) -> Option<BuildSystem> {
+        let build_systems = self.build_systems();
+        if let Some(index) = self.build_system {
+            return build_systems.get(index).map(|&build_system| build_system.clone())
+        }
+        let path = self.active_view()
+            .and_then(|view| view.buffer().path().map(Path::to_path_buf));
+        let repository = &self.package_repository;
+        let syntax = match path.and_then(|path| Core::load_syntax(repository, Some(&path))) {
+            Some(syntax) => syntax,
+            None => return None
+        };
+        build_systems.into_iter()
+            .find(|build_system| build_system.matches(&syntax.scope_name))
+            .cloned()
+    }
+
+    /// Returns the variables of the active file and the project, like `$file`
+    /// and `$project_path`, they're expanded in the build system.
+    fn build_variables(&self) -> BTreeMap<&'static str, String> {
+        fn lossy(s: Option<&OsStr>) -> String {
+            s.map_or(String::new(), |s| s.to_string_lossy().into_owned())
+        }
+        let mut variables = BTreeMap::new();
+        let file = self.active_view()
+            .and_then(|view| view.buffer().path().map(Path::to_path_buf));
+        if let Some(file) = file {
+            let file = file.canonicalize().unwrap_or(file);
+            variables.insert("file", lossy(Some(file.as_os_str())));
+            variables.insert("file_path", lossy(file.parent().map(Path::as_os_str)));
+            variables.insert("file_name", lossy(file.file_name()));
+            variables.insert("file_base_name", lossy(file.file_stem()));
+            variables.insert("file_extension", lossy(file.extension()));
+        }
+        if let Some(pf) = self.project.folders.first() {
+            variables.insert("folder", lossy(Some(pf.path.as_os_str())));
+        }
+        if let Some(ref project) = self.project.path {
+            let project = project.canonicalize().unwrap_or_else(|_| project.clone());
+            variables.insert("project", lossy(Some(project.as_os_str())));
+            variables.insert("project_path", lossy(project.parent().map(Path::as_os_str)));
+            variables.insert("project_name", lossy(project.file_name()));
+            variables.insert("project_base_name", lossy(project.file_stem()));
+        }
+        variables
+    }
+
+    /// Runs the build system of the active file, or its variant. The build is run by
+    /// the `exec` command, unless the build system has another target.
+    pub fn build(&mut self, variant: Option<&str>) {
+        let mut build_system = match self.choose_build_system() {
+            Some(build_system) => build_system,
+            None => {
+                warn!("No build system for the active file");
+                return
+            }
+        };
+        if let Some(variant) = variant {
+            build_system = match build_system.variant(variant) {
+                Some(build_system) => build_system,
+                None => {
+                    warn!("No build variant {:?} in {:?}", variant, build_system.name);
+                    return
+                }
+            };
+        }
+        let variables = self.build_variables();
+        // the build is run in the folder of the file by default
+        if build_system.working_dir.is_none() {
+            build_system.working_dir = variables.get("file_path").cloned();
+        }
+        build_system.expand(|name| variables.get(name).cloned());
+        let target = build_system.target.clone().unwrap_or_else(|| "exec".to_owned());
+        self.run_command(Command::new(&target, build_system.exec_args()));
+    }
+
+    /// Starts the build process, its output is shown in the output view. The running build
+    /// is stopped.
+    pub fn exec(&mut self, build_system: &BuildSystem) {
+        self.kill_build();
+        let build = match self.show_output_view() {
+            Some(mut buffer) => {
+                buffer.clear();
+                match Exec::start(build_system, &buffer) {
+                    Ok(build) => Some(build),
+                    Err(err) => {
+                        buffer.append(&format!("Can't run the build: {:?}\n", err));
+                        None
+                    }
+                }
+            },
+            None => return
+        };
+        self.build = build;
+        self.is_build_result = true;
+    }
+
+    pub fn kill_build(&mut self) {
+        if let Some(ref mut build) = self.build {
+            build.kill();
+        }
+    }
+
+    /// Appends the new output of the build to the output view. Returns false if there was
+    /// nothing to append. Build is stopped when the output view is closed.
+    pub fn update_build(&mut self) -> bool {
+        let found = match self.build {
+            Some(ref build) if !build.is_finished() => self.find_buffer_view(build.buffer_id()),
+            _ => return false
+        };
+        match (found, self.build.as_mut()) {
+            (Some((group, index)), Some(build)) => {
+                build.update(&mut self.groups[group].views[index].buffer_mut())
+            },
+            (_, build) => {
+                if let Some(build) = build {
+                    build.kill();
+                }
+                self.build = None;
+                false
+            }
+        }
+    }
 }
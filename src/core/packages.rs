@@ -1,15 +1,24 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::{Error as IoError, BufReader};
-use std::fs::File;
+use std::fs::{File, read_dir};
 use std::path::{Path, PathBuf};
 
 use core::settings::{Settings, SettingsError, read_json, read_plist, ParseSettings};
 use core::menu::{Menu, ParseMenuError};
-use core::bindings::{Keymap, ParseKeymapError};
+use core::command_list::{CommandList, ParseCommandListError};
+use core::bindings::{Keymap, ParseKeymapError, Mousemap, ParseMousemapError};
 use core::syntax::{Syntax, ParseSyntaxError, Theme, ParseThemeError};
+use core::symbols::{SymbolPreferences, ParseSymbolListError};
+use core::workspace::{BuildSystem, ParseBuildSystemError};
 
-#[derive(Debug)]
+/// Packages placed in several folders. Files of the first folders override the same files
+/// of the next ones, so user packages are followed by the bundled defaults.
+#[derive(Debug, Clone)]
 pub struct PackageRepository {
-    path: PathBuf
+    paths: Vec<PathBuf>,
+    /// Paths of the syntax definitions by the file extension, they're read once
+    syntaxes: RefCell<Option<HashMap<String, PathBuf>>>
 }
 
 #[derive(Debug)]
@@ -18,7 +27,11 @@ pub enum PackageError {
     ParseTheme(ParseThemeError),
     ParseSyntax(ParseSyntaxError),
     ParseKeymap(ParseKeymapError),
+    ParseMousemap(ParseMousemapError),
     ParseMenu(ParseMenuError),
+    ParseCommandList(ParseCommandListError),
+    ParseSymbolList(ParseSymbolListError),
+    ParseBuildSystem(ParseBuildSystemError),
     Io(IoError)
 }
 
@@ -46,12 +59,36 @@ impl From<ParseKeymapError> for PackageError {
     }
 }
 
+impl From<ParseMousemapError> for PackageError {
+    fn from(error: ParseMousemapError) -> PackageError {
+        PackageError::ParseMousemap(error)
+    }
+}
+
 impl From<ParseMenuError> for PackageError {
     fn from(error: ParseMenuError) -> PackageError {
         PackageError::ParseMenu(error)
     }
 }
 
+impl From<ParseCommandListError> for PackageError {
+    fn from(error: ParseCommandListError) -> PackageError {
+        PackageError::ParseCommandList(error)
+    }
+}
+
+impl From<ParseSymbolListError> for PackageError {
+    fn from(error: ParseSymbolListError) -> PackageError {
+        PackageError::ParseSymbolList(error)
+    }
+}
+
+impl From<ParseBuildSystemError> for PackageError {
+    fn from(error: ParseBuildSystemError) -> PackageError {
+        PackageError::ParseBuildSystem(error)
+    }
+}
+
 impl From<IoError> for PackageError {
     fn from(error: IoError) -> PackageError {
         PackageError::Io(error)
@@ -59,12 +96,27 @@ impl From<IoError> for PackageError {
 }
 
 impl PackageRepository {
-    pub fn open(path: PathBuf) -> PackageRepository {
-        PackageRepository { path: path }
+    pub fn open(paths: Vec<PathBuf>) -> PackageRepository {
+        PackageRepository {
+            paths: paths,
+            syntaxes: RefCell::new(None)
+        }
+    }
+
+    /// Returns the path of the file in the first folder that has it.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        for folder in &self.paths {
+            let resolved = folder.join(path);
+            if resolved.exists() {
+                return resolved
+            }
+        }
+        // the file doesn't exist, so opening the path reports the error
+        self.paths.last().map_or(path.to_path_buf(), |folder| folder.join(path))
     }
 
     pub fn read_file(&self, path: &Path) -> Result<BufReader<File>, PackageError> {
-        let reader = try!(File::open(self.path.join(path)));
+        let reader = try!(File::open(self.resolve(path)));
         Ok(BufReader::new(reader))
     }
 
@@ -80,10 +132,41 @@ impl PackageRepository {
         Ok(try!(Menu::parse_settings(try!(self.read_json(path.as_ref())))))
     }
 
+    pub fn get_command_list<P: AsRef<Path>>(&self, path: P) -> Result<CommandList, PackageError> {
+        Ok(try!(CommandList::parse_settings(try!(self.read_json(path.as_ref())))))
+    }
+
+    /// Returns paths of files with the given extension placed in the root of packages.
+    /// Missing folders of packages are skipped.
+    pub fn find_files(&self, extension: &str) -> Result<Vec<PathBuf>, PackageError> {
+        let mut files = Vec::new();
+        for folder in self.paths.iter().filter(|folder| folder.is_dir()) {
+            for package in try!(read_dir(folder)) {
+                let package = try!(package);
+                if !try!(package.file_type()).is_dir() {
+                    continue
+                }
+                for file in try!(read_dir(package.path())) {
+                    let path = try!(file).path();
+                    if path.extension().map_or(false, |ext| ext == extension) {
+                        files.push(path.strip_prefix(folder).unwrap().to_owned());
+                    }
+                }
+            }
+        }
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
     pub fn get_keymap<P: AsRef<Path>>(&self, path: P) -> Result<Keymap, PackageError> {
         Ok(try!(Keymap::parse_settings(try!(self.read_json(path.as_ref())))))
     }
 
+    pub fn get_mousemap<P: AsRef<Path>>(&self, path: P) -> Result<Mousemap, PackageError> {
+        Ok(try!(Mousemap::parse_settings(try!(self.read_json(path.as_ref())))))
+    }
+
     pub fn get_theme<P: AsRef<Path>>(&self, path: P) -> Result<Theme, PackageError> {
         Ok(try!(Theme::parse_settings(try!(self.read_plist(path.as_ref())))))
     }
@@ -91,4 +174,56 @@ impl PackageRepository {
     pub fn get_syntax<P: AsRef<Path>>(&self, path: P) -> Result<Syntax, PackageError> {
         Ok(try!(Syntax::parse_settings(try!(self.read_plist(path.as_ref())))))
     }
+
+    /// Returns path of the syntax definition that handles files with the given extension.
+    /// Syntax definitions are read on the first call, the first one that lists
+    /// the extension is taken.
+    pub fn find_syntax(&self, extension: &str) -> Result<Option<PathBuf>, PackageError> {
+        if self.syntaxes.borrow().is_none() {
+            let mut syntaxes = HashMap::new();
+            for path in try!(self.find_files("tmLanguage")) {
+                for file_type in try!(self.get_syntax(&path)).file_types {
+                    syntaxes.entry(file_type).or_insert_with(|| path.clone());
+                }
+            }
+            *self.syntaxes.borrow_mut() = Some(syntaxes);
+        }
+        Ok(self.syntaxes.borrow().as_ref().and_then(|syntaxes| syntaxes.get(extension).cloned()))
+    }
+
+    /// Reads the `.sublime-build` file, the build system is named after the file.
+    pub fn get_build_system<P: AsRef<Path>>(&self, path: P) -> Result<BuildSystem, PackageError> {
+        let path = path.as_ref();
+        let mut build_system = try!(BuildSystem::parse_settings(try!(self.read_json(path))));
+        if build_system.name.is_none() {
+            build_system.name = path.file_stem().map(|name| name.to_string_lossy().into_owned());
+        }
+        Ok(build_system)
+    }
+
+    pub fn get_symbol_preferences<P: AsRef<Path>>(&self, path: P)
+            -> Result<SymbolPreferences, PackageError> {
+        Ok(try!(SymbolPreferences::parse_settings(try!(self.read_plist(path.as_ref())))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use core::temp_dir::TempDir;
+
+    use super::PackageRepository;
+
+    #[test]
+    fn find_syntax() {
+        let bundled = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/packages"));
+        let user = TempDir::new("packages-syntax");
+        let repository = PackageRepository::open(vec![user.path().to_path_buf(), bundled]);
+        let rust = Some(Path::new("Rust/Rust.tmLanguage").to_path_buf());
+        assert_eq!(repository.find_syntax("rs").unwrap(), rust);
+        assert_eq!(repository.find_syntax("unknown").unwrap(), None);
+        assert!(repository.syntaxes.borrow().is_some());
+        assert_eq!(repository.find_syntax("rs").unwrap(), rust);
+    }
 }
@@ -0,0 +1,107 @@
+use core::settings::{Settings, FromSettings};
+
+/// Settings that affect how a view is displayed and edited.
+#[derive(Debug, Clone)]
+pub struct Preferences {
+    /// Show line numbers in the gutter.
+    pub line_numbers: bool,
+    /// Show the gutter at all.
+    pub gutter: bool,
+    /// Wrap long lines at the viewport width.
+    pub word_wrap: bool,
+    /// Wrap lines at this column instead of the viewport width, zero means no limit.
+    pub wrap_width: usize,
+    /// Indent wrapped rows like the first row of the line.
+    pub indent_subsequent_lines: bool,
+    /// Dim files ignored by git in the sidebar.
+    pub exclude_ignored: bool,
+    /// Keep unsaved changes in the session on exit and restore them on start.
+    pub hot_exit: bool
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            line_numbers: true,
+            gutter: true,
+            word_wrap: false,
+            wrap_width: 0,
+            indent_subsequent_lines: true,
+            exclude_ignored: false,
+            hot_exit: true
+        }
+    }
+}
+
+impl Preferences {
+    /// Overrides preferences by the values presented in the settings object.
+    /// Unknown keys and values of a wrong type are ignored.
+    pub fn update(&mut self, settings: &Settings) {
+        let obj = match *settings {
+            Settings::Object(ref obj) => obj,
+            _ => {
+                warn!("Preferences is not an object");
+                return
+            }
+        };
+        for (key, value) in obj {
+            match &key[..] {
+                "line_numbers" => update_bool(&mut self.line_numbers, key, value),
+                "gutter" => update_bool(&mut self.gutter, key, value),
+                "word_wrap" => update_bool(&mut self.word_wrap, key, value),
+                "wrap_width" => match value.as_u64() {
+                    Some(value) => self.wrap_width = value as usize,
+                    None => warn!("Setting {} is not a number", key)
+                },
+                "indent_subsequent_lines" =>
+                    update_bool(&mut self.indent_subsequent_lines, key, value),
+                "exclude_ignored" => update_bool(&mut self.exclude_ignored, key, value),
+                "hot_exit" => update_bool(&mut self.hot_exit, key, value),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns value of the boolean setting, if it exists.
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match name {
+            "line_numbers" => Some(self.line_numbers),
+            "gutter" => Some(self.gutter),
+            "word_wrap" => Some(self.word_wrap),
+            "indent_subsequent_lines" => Some(self.indent_subsequent_lines),
+            "exclude_ignored" => Some(self.exclude_ignored),
+            "hot_exit" => Some(self.hot_exit),
+            _ => None
+        }
+    }
+
+    /// Inverts the boolean setting. Returns false if there is no such setting.
+    pub fn toggle(&mut self, name: &str) -> bool {
+        match name {
+            "line_numbers" => self.line_numbers = !self.line_numbers,
+            "gutter" => self.gutter = !self.gutter,
+            "word_wrap" => self.word_wrap = !self.word_wrap,
+            "indent_subsequent_lines" =>
+                self.indent_subsequent_lines = !self.indent_subsequent_lines,
+            "exclude_ignored" => self.exclude_ignored = !self.exclude_ignored,
+            "hot_exit" => self.hot_exit = !self.hot_exit,
+            _ => return false
+        }
+        true
+    }
+}
+
+fn update_bool(field: &mut bool, key: &str, value: &Settings) {
+    match value.as_boolean() {
+        Some(value) => *field = value,
+        None => warn!("Setting {} is not a boolean", key)
+    }
+}
+
+impl FromSettings for Preferences {
+    fn from_settings(settings: Settings) -> Preferences {
+        let mut preferences = Preferences::default();
+        preferences.update(&settings);
+        preferences
+    }
+}
@@ -1 +1,2 @@
-pub use onig::{Regex, Region, Error as RegexError, REGEX_OPTION_NONE, SEARCH_OPTION_NONE};
+pub use onig::{Regex, Region, Captures, Syntax, Error as RegexError,
+              REGEX_OPTION_NONE, REGEX_OPTION_IGNORECASE, SEARCH_OPTION_NONE};
@@ -0,0 +1,53 @@
+use std::cmp::{min, max};
+
+/// Position in the buffer. Column is a byte offset inside the line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    pub line: usize,
+    pub column: usize
+}
+
+/// Selected part of the buffer. `a` is the anchor, `b` is the caret.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub a: Point,
+    pub b: Point
+}
+
+impl Point {
+    pub fn new(line: usize, column: usize) -> Point {
+        Point {
+            line: line,
+            column: column
+        }
+    }
+}
+
+impl Region {
+    pub fn new(a: Point, b: Point) -> Region {
+        Region {
+            a: a,
+            b: b
+        }
+    }
+
+    pub fn caret(point: Point) -> Region {
+        Region::new(point, point)
+    }
+
+    pub fn begin(&self) -> Point {
+        min(self.a, self.b)
+    }
+
+    pub fn end(&self) -> Point {
+        max(self.a, self.b)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.a == self.b
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.begin() <= point && point <= self.end()
+    }
+}
@@ -0,0 +1,256 @@
+use std::cmp::min;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use core::buffer::Buffer;
+use core::regex::{Regex, RegexError, Captures, Syntax,
+                  REGEX_OPTION_NONE, REGEX_OPTION_IGNORECASE};
+use core::region::{Point, Region};
+
+/// Characters that have special meaning in the regular expression.
+const SPECIAL_CHARS: &'static str = "\\.^$|?*+()[]{}";
+
+/// Options toggled in the find panel.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Only the text selected before the search is searched
+    pub in_selection: bool,
+    /// Replacement takes the case of the replaced text
+    pub preserve_case: bool
+}
+
+/// Compiled search pattern. Matches never span several lines and are never empty.
+#[derive(Debug)]
+pub struct Search {
+    regex: Regex,
+    options: SearchOptions,
+    /// Regions the matches are restricted to, the whole buffer is searched if it's empty
+    scope: Vec<Region>
+}
+
+/// Escapes the text to match it literally.
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL_CHARS.contains(c) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn read_number(chars: &mut Peekable<Chars>) -> usize {
+    let mut number = 0;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        number = number * 10 + digit as usize;
+        chars.next();
+    }
+    number
+}
+
+/// Expands references to the captured groups in the format: `$1`, `\1`, `${1}` and `${name}`.
+/// Escapes `\n` and `\t` are replaced by the characters, other escaped characters are kept.
+pub fn expand(format: &str, regex: &Regex, captures: &Captures) -> String {
+    let mut result = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' | '\\' if chars.peek().map_or(false, |c| c.is_digit(10)) => {
+                let index = read_number(&mut chars);
+                result.push_str(captures.at(index).unwrap_or(""));
+            },
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let index = match name.parse::<usize>() {
+                    Ok(index) => Some(index),
+                    Err(_) => regex.capture_names()
+                        .find(|&(group, _)| group == name)
+                        .map(|(_, indices)| indices[0] as usize)
+                };
+                if let Some(group) = index.and_then(|index| captures.at(index)) {
+                    result.push_str(group);
+                }
+            },
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(c) => result.push(c),
+                None => result.push('\\')
+            },
+            c => result.push(c)
+        }
+    }
+    result
+}
+
+/// Changes case of the replacement to match the replaced text: upper, lower or capitalized.
+/// Otherwise case is copied character by character.
+pub fn preserve_case(original: &str, replacement: &str) -> String {
+    let letters: Vec<char> = original.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.is_empty() {
+        return replacement.to_owned()
+    }
+    if letters.iter().all(|c| c.is_uppercase()) && letters.len() > 1 {
+        return replacement.to_uppercase()
+    }
+    if letters.iter().all(|c| c.is_lowercase()) {
+        return replacement.to_lowercase()
+    }
+    if letters[0].is_uppercase() && letters[1..].iter().all(|c| c.is_lowercase()) {
+        let mut chars = replacement.chars();
+        let first: String = chars.next().map_or(String::new(), |c| c.to_uppercase().collect());
+        return first + &chars.as_str().to_lowercase()
+    }
+    let mut cases = original.chars();
+    replacement.chars().map(|c| match cases.next() {
+        Some(case) if case.is_uppercase() => c.to_uppercase().collect(),
+        Some(case) if case.is_lowercase() => c.to_lowercase().collect(),
+        _ => c.to_string()
+    }).collect()
+}
+
+impl Search {
+    /// Compiles the pattern. The scope is used only when searching in selection.
+    pub fn new(pattern: &str, options: SearchOptions, scope: &[Region])
+            -> Result<Search, RegexError> {
+        let mut pattern = if options.regex { pattern.to_owned() } else { escape(pattern) };
+        if options.whole_word {
+            pattern = format!("\\b(?:{})\\b", pattern);
+        }
+        let flags = if options.case_sensitive {
+            REGEX_OPTION_NONE
+        } else {
+            REGEX_OPTION_IGNORECASE
+        };
+        let scope = if options.in_selection {
+            scope.iter().cloned().filter(|region| !region.is_empty()).collect()
+        } else {
+            Vec::new()
+        };
+        Ok(Search {
+            regex: try!(Regex::with_options(&pattern, flags, Syntax::default())),
+            options: options,
+            scope: scope
+        })
+    }
+
+    fn in_scope(&self, region: &Region) -> bool {
+        self.scope.is_empty() || self.scope.iter().any(|scope| {
+            scope.begin() <= region.begin() && region.end() <= scope.end()
+        })
+    }
+
+    /// Returns matches in the lines from `begin` until `end`.
+    pub fn find_in_lines(&self, buffer: &Buffer, begin: usize, end: usize) -> Vec<Region> {
+        let mut matches = Vec::new();
+        for index in begin..min(end, buffer.lines.len()) {
+            for (from, to) in self.regex.find_iter(&buffer.lines[index].text) {
+                let region = Region::new(Point::new(index, from), Point::new(index, to));
+                if from < to && self.in_scope(&region) {
+                    matches.push(region);
+                }
+            }
+        }
+        matches
+    }
+
+    pub fn find_all(&self, buffer: &Buffer) -> Vec<Region> {
+        self.find_in_lines(buffer, 0, buffer.lines.len())
+    }
+
+    /// Returns the first match after the point, or the last match before it when searching
+    /// backward. Search wraps around the end of the buffer.
+    pub fn find_next(&self, buffer: &Buffer, point: Point, forward: bool) -> Option<Region> {
+        let matches = self.find_all(buffer);
+        let found = if forward {
+            matches.iter().find(|region| region.begin() >= point).or(matches.first())
+        } else {
+            matches.iter().rev().find(|region| region.end() <= point).or(matches.last())
+        };
+        found.cloned()
+    }
+
+    /// Checks whether the region is exactly a match.
+    pub fn is_match(&self, buffer: &Buffer, region: Region) -> bool {
+        let (begin, end) = (region.begin(), region.end());
+        begin.line == end.line && self.find_in_lines(buffer, begin.line, begin.line + 1)
+            .iter()
+            .any(|found| found.begin() == begin && found.end() == end)
+    }
+
+    /// Returns the text that replaces the match. References to the captured groups
+    /// are expanded in the regex mode, otherwise the format is inserted as is.
+    pub fn replacement(&self, buffer: &Buffer, region: Region, format: &str) -> String {
+        let (begin, end) = (region.begin(), region.end());
+        let text = &buffer.lines[begin.line].text;
+        let mut replacement = format.to_owned();
+        if self.options.regex {
+            let captures = self.regex.captures_iter(text)
+                .find(|captures| captures.pos(0) == Some((begin.column, end.column)));
+            if let Some(captures) = captures {
+                replacement = expand(format, &self.regex, &captures);
+            }
+        }
+        if self.options.preserve_case {
+            replacement = preserve_case(&text[begin.column..end.column], &replacement);
+        }
+        replacement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::regex::Regex;
+
+    use super::{expand, preserve_case};
+
+    fn expanded(pattern: &str, text: &str, format: &str) -> String {
+        let regex = Regex::new(pattern).unwrap();
+        let captures = regex.captures(text).unwrap();
+        expand(format, &regex, &captures)
+    }
+
+    #[test]
+    fn numbered_groups() {
+        assert_eq!(expanded(r"(\w+) (\w+)", "hello world", "$2 $1"), "world hello");
+        assert_eq!(expanded(r"(\w+) (\w+)", "hello world", r"\2-\1"), "world-hello");
+        assert_eq!(expanded(r"(\w+) (\w+)", "hello world", "${1}s $0"), "hellos hello world");
+        // missing groups are empty
+        assert_eq!(expanded(r"(\w+)", "hello", "[$3]"), "[]");
+        assert_eq!(expanded(r"(a)|(b)", "b", "[$1][$2]"), "[][b]");
+    }
+
+    #[test]
+    fn named_groups() {
+        let pattern = r"(?<key>\w+)=(?<value>\w+)";
+        assert_eq!(expanded(pattern, "a=1", "${value}=${key}"), "1=a");
+        assert_eq!(expanded(pattern, "a=1", "${missing}${key}"), "a");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(expanded(r"(\w+)", "word", r"$1\n\t$1"), "word\n\tword");
+        assert_eq!(expanded(r"(\w+)", "word", r"\$1 \\ \x"), "$1 \\ x");
+        assert_eq!(expanded(r"(\w+)", "word", "$ $x end\\"), "$ $x end\\");
+    }
+
+    #[test]
+    fn preserved_case() {
+        assert_eq!(preserve_case("FOO", "bar"), "BAR");
+        assert_eq!(preserve_case("foo", "BaR"), "bar");
+        assert_eq!(preserve_case("Foo", "bAR"), "Bar");
+        // single uppercase letter is capitalized
+        assert_eq!(preserve_case("F", "bar"), "Bar");
+        // mixed case is copied character by character
+        assert_eq!(preserve_case("fOoB", "barbaz"), "bArBaz");
+        assert_eq!(preserve_case("FOO_bar", "baz_quux"), "BAZ_quux");
+        // text without letters keeps the replacement
+        assert_eq!(preserve_case("123", "BaR"), "BaR");
+        assert_eq!(preserve_case("ÉTÉ", "hiver"), "HIVER");
+    }
+}
@@ -0,0 +1,248 @@
+use std::rc::Rc;
+use std::str::{FromStr, Chars};
+use std::iter::Peekable;
+
+use core::settings::{Settings, ParseSettings};
+use core::syntax::{Scope, ScopeCommand, ScopeSelectors, ScopeTree, ParseScopeError};
+use core::regex::{Regex, RegexError, Captures};
+use core::region::{Point, Region};
+use core::search;
+
+use self::ParseSymbolListError::*;
+
+/// Definition found in the buffer, like a function or a type.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub region: Region,
+    /// Name transformed by the `symbolTransformation` setting
+    pub name: String
+}
+
+/// Settings of `Symbol List.tmPreferences` files, that control which scopes
+/// are shown in the symbol list and how their names are displayed.
+#[derive(Debug)]
+pub struct SymbolList {
+    show_tree: ScopeTree<bool>,
+    transformation_tree: ScopeTree<Rc<Vec<Substitution>>>
+}
+
+/// Content of a single `.tmPreferences` file.
+#[derive(Debug)]
+pub struct SymbolPreferences {
+    pub scope: ScopeSelectors,
+    pub show_in_symbol_list: Option<bool>,
+    pub symbol_transformation: Option<Vec<Substitution>>
+}
+
+/// Substitution in the `s/regex/format/flags` form.
+#[derive(Debug)]
+pub struct Substitution {
+    regex: Regex,
+    format: String,
+    global: bool
+}
+
+#[derive(Debug)]
+pub enum ParseSymbolListError {
+    PreferencesIsNotObject,
+    ScopeIsNotString,
+    SettingsIsNotObject,
+    ShowInSymbolListIsNotBoolean,
+    SymbolTransformationIsNotString,
+    IncorrectSubstitution,
+    ScopeParse(ParseScopeError),
+    RegexParse(RegexError)
+}
+
+impl From<ParseScopeError> for ParseSymbolListError {
+    fn from(error: ParseScopeError) -> ParseSymbolListError {
+        ScopeParse(error)
+    }
+}
+
+impl From<RegexError> for ParseSymbolListError {
+    fn from(error: RegexError) -> ParseSymbolListError {
+        RegexParse(error)
+    }
+}
+
+/// Reads the part of the substitution until the unescaped delimiter.
+fn read_part(chars: &mut Peekable<Chars>, delimiter: char) -> Result<String, ParseSymbolListError> {
+    let mut part = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == delimiter => return Ok(part),
+            Some('\\') => match chars.next() {
+                Some(c) if c == delimiter => part.push(c),
+                Some(c) => {
+                    part.push('\\');
+                    part.push(c);
+                },
+                None => return Err(IncorrectSubstitution)
+            },
+            Some(c) => part.push(c),
+            None => return Err(IncorrectSubstitution)
+        }
+    }
+}
+
+/// Parses substitutions separated by semicolons. Lines starting with `#` are comments.
+fn parse_transformation(s: &str) -> Result<Vec<Substitution>, ParseSymbolListError> {
+    let mut substitutions = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {},
+            '#' => {
+                while chars.next().map_or(false, |c| c != '\n') {}
+            },
+            's' => {
+                let delimiter = match chars.next() {
+                    Some(c) => c,
+                    None => return Err(IncorrectSubstitution)
+                };
+                let regex = try!(read_part(&mut chars, delimiter));
+                let format = try!(read_part(&mut chars, delimiter));
+                let mut global = false;
+                while let Some(&c) = chars.peek() {
+                    if !c.is_alphabetic() {
+                        break
+                    }
+                    global |= c == 'g';
+                    chars.next();
+                }
+                substitutions.push(Substitution {
+                    regex: try!(Regex::new(&regex)),
+                    format: format,
+                    global: global
+                });
+            },
+            c if c.is_whitespace() => {},
+            _ => return Err(IncorrectSubstitution)
+        }
+    }
+    Ok(substitutions)
+}
+
+impl Substitution {
+    pub fn apply(&self, text: &str) -> String {
+        let limit = if self.global { 0 } else { 1 };
+        self.regex.replacen(text, limit, |captures: &Captures| {
+            search::expand(&self.format, &self.regex, captures)
+        })
+    }
+}
+
+impl ParseSettings for SymbolPreferences {
+    type Error = ParseSymbolListError;
+
+    fn parse_settings(settings: Settings) -> Result<SymbolPreferences, ParseSymbolListError> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(PreferencesIsNotObject)
+        };
+        let scope = match obj.remove("scope") {
+            Some(Settings::String(scope)) => try!(ScopeSelectors::from_str(&scope)),
+            // preferences without the scope are applied to everything
+            None => ScopeSelectors::default(),
+            _ => return Err(ScopeIsNotString)
+        };
+        let mut settings = match obj.remove("settings") {
+            Some(Settings::Object(settings)) => settings,
+            _ => return Err(SettingsIsNotObject)
+        };
+        let show_in_symbol_list = match settings.remove("showInSymbolList") {
+            Some(Settings::Boolean(value)) => Some(value),
+            Some(Settings::I64(value)) => Some(value != 0),
+            Some(Settings::U64(value)) => Some(value != 0),
+            None => None,
+            _ => return Err(ShowInSymbolListIsNotBoolean)
+        };
+        let symbol_transformation = match settings.remove("symbolTransformation") {
+            Some(Settings::String(s)) => Some(try!(parse_transformation(&s))),
+            None => None,
+            _ => return Err(SymbolTransformationIsNotString)
+        };
+        Ok(SymbolPreferences {
+            scope: scope,
+            show_in_symbol_list: show_in_symbol_list,
+            symbol_transformation: symbol_transformation
+        })
+    }
+}
+
+impl SymbolList {
+    pub fn new() -> SymbolList {
+        SymbolList {
+            show_tree: ScopeTree::new(),
+            transformation_tree: ScopeTree::new()
+        }
+    }
+
+    pub fn add(&mut self, preferences: SymbolPreferences) {
+        let transformation = preferences.symbol_transformation.map(Rc::new);
+        for selector in preferences.scope.selectors {
+            if selector.path().is_empty() {
+                continue
+            }
+            if let Some(show) = preferences.show_in_symbol_list {
+                self.show_tree.add(selector.path(), show);
+            }
+            if let Some(ref transformation) = transformation {
+                self.transformation_tree.add(selector.path(), transformation.clone());
+            }
+        }
+    }
+
+    fn is_symbol(&self, path: &[Scope]) -> bool {
+        self.show_tree.find(path).unwrap_or(false)
+    }
+
+    fn transform(&self, path: &[Scope], name: &str) -> String {
+        match self.transformation_tree.find(path) {
+            Some(substitutions) => {
+                substitutions.iter().fold(name.to_owned(), |name, s| s.apply(&name))
+            },
+            None => name.to_owned()
+        }
+    }
+
+    /// Adds symbols found in the line to the list. Symbol is the text of the outermost scope
+    /// that is shown in the symbol list.
+    pub fn line_symbols(&self, line: usize, text: &str, path: &[Scope],
+                        changes: &[(usize, ScopeCommand)], symbols: &mut Vec<Symbol>) {
+        let mut path = path.to_vec();
+        // depth and the beginning of the scope of the current symbol, and the symbol path
+        let mut current: Option<(usize, usize, Vec<Scope>)> = None;
+        let mut add = |begin: usize, end: usize, path: &[Scope]| {
+            let name = self.transform(path, &text[begin..end]);
+            if !name.trim().is_empty() {
+                symbols.push(Symbol {
+                    region: Region::new(Point::new(line, begin), Point::new(line, end)),
+                    name: name
+                });
+            }
+        };
+        for &(pos, ref command) in changes {
+            match *command {
+                ScopeCommand::Push(ref scope) => {
+                    path.push(scope.clone());
+                    if current.is_none() && self.is_symbol(&path) {
+                        current = Some((path.len(), pos, path.clone()));
+                    }
+                },
+                ScopeCommand::Pop => {
+                    if current.as_ref().map_or(false, |&(depth, _, _)| depth == path.len()) {
+                        let (_, begin, symbol_path) = current.take().unwrap();
+                        add(begin, pos, &symbol_path);
+                    }
+                    path.pop();
+                },
+                ScopeCommand::Noop => {}
+            }
+        }
+        if let Some((_, begin, symbol_path)) = current {
+            add(begin, text.len(), &symbol_path);
+        }
+    }
+}
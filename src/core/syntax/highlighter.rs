@@ -106,6 +106,10 @@ impl Highlighter {
         }
     }
 
+    pub fn settings(&self) -> &ThemeSettings {
+        &self.settings
+    }
+
     pub fn get_default(&self) -> Style {
         Style {
             foreground: self.settings.foreground.unwrap_or(WHITE),
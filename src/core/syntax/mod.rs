@@ -7,8 +7,10 @@ mod highlighter;
 mod style;
 
 pub use self::definition::{Syntax, ParseSyntaxError};
-pub use self::scope::{Scope, ScopePath, ScopeSelector, ScopeSelectors, ScopeCommand, ParseScopeError};
-pub use self::theme::{Theme, ParseThemeError};
+pub use self::scope::{
+    Scope, ScopePath, ScopeSelector, ScopeSelectors, ScopeCommand, ScopeTree, ParseScopeError
+};
+pub use self::theme::{Theme, ThemeSettings, ParseThemeError};
 pub use self::parser::{Parser, ParserState};
 pub use self::highlighter::{Highlighter, HighlightIterator};
 pub use self::style::{
@@ -54,6 +54,11 @@ impl ParserState {
         ::std::mem::swap(&mut self.changes, &mut other.changes);
     }
 
+    /// Returns `true` if both states have the same contexts and scopes.
+    pub fn same_context(&self, other: &ParserState) -> bool {
+        self.context_path == other.context_path && self.scope_path == other.scope_path
+    }
+
     fn change_scope(&mut self, pos: usize, command: ScopeCommand) {
         match command {
             ScopeCommand::Push(ref scope) => {
@@ -52,6 +52,15 @@ impl ScopeCommand {
     }
 }
 
+impl Scope {
+    /// Checks whether the scope is the given one or a more specific one,
+    /// like `source.rust` for `source`.
+    pub fn starts_with(&self, scope: &Scope) -> bool {
+        self.name == scope.name ||
+            (self.name.starts_with(&scope.name) && self.name[scope.name.len()..].starts_with('.'))
+    }
+}
+
 impl FromStr for Scope {
     type Err = ParseScopeError;
 
@@ -61,6 +61,13 @@ pub struct ThemeSettings {
     /// Foreground color of the gutter.
     pub gutter_foreground: Option<Color>,
 
+    /// Color of the gutter marker of added lines.
+    pub line_diff_added: Option<Color>,
+    /// Color of the gutter marker of modified lines.
+    pub line_diff_modified: Option<Color>,
+    /// Color of the gutter marker of deleted lines.
+    pub line_diff_deleted: Option<Color>,
+
     /// Color of the selection regions.
     pub selection: Option<Color>,
     /// Background color of the selection regions.
@@ -312,6 +319,12 @@ impl ParseSettings for ThemeSettings {
                     settings.gutter = Some(try!(Color::parse_settings(value))),
                 "gutterForeground" =>
                     settings.gutter_foreground = Some(try!(Color::parse_settings(value))),
+                "lineDiffAdded" =>
+                    settings.line_diff_added = Some(try!(Color::parse_settings(value))),
+                "lineDiffModified" =>
+                    settings.line_diff_modified = Some(try!(Color::parse_settings(value))),
+                "lineDiffDeleted" =>
+                    settings.line_diff_deleted = Some(try!(Color::parse_settings(value))),
                 "selection" =>
                     settings.selection = Some(try!(Color::parse_settings(value))),
                 "selectionBackground" =>
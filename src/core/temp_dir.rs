@@ -0,0 +1,54 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Folder in the system temp folder for the tests, it's removed when it's dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let index = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        let name = format!("sublimate-{}-{}-{}", name, nanos, index);
+        let path = env::temp_dir().join(name);
+        fs::create_dir_all(&path).unwrap();
+        // watched paths are compared with the canonical ones
+        TempDir { path: path.canonicalize().unwrap() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path)
+    }
+
+    /// Creates the folder with its parents.
+    pub fn create_dir(&self, path: &str) -> PathBuf {
+        let path = self.join(path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Writes the file, its folder should exist.
+    pub fn write<C: AsRef<[u8]>>(&self, path: &str, contents: C) -> PathBuf {
+        let path = self.join(path);
+        File::create(&path).unwrap().write_all(contents.as_ref()).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,340 @@
+use std::cmp::max;
+use std::collections::BTreeMap;
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time the requests of the status are collected for before git is run, in milliseconds.
+/// Watcher reports the changes in batches, a git command alone changes the git folder
+/// several times.
+const READ_DELAY: u64 = 200;
+/// Longest time the status is delayed by the requests that keep coming, in milliseconds.
+const MAX_READ_DELAY: u64 = 1000;
+
+/// Status of the file in the git working tree. Folders have the status of the changed files
+/// they contain, the most important one is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Modified
+}
+
+#[derive(Debug)]
+pub enum VcsError {
+    Io(IoError),
+    /// Git failed, usually the folder isn't in a repository
+    Git(String)
+}
+
+impl From<IoError> for VcsError {
+    fn from(error: IoError) -> VcsError {
+        VcsError::Io(error)
+    }
+}
+
+/// Statuses of the changed files in the project folders, as reported by `git status`.
+#[derive(Debug, Default)]
+pub struct VcsStatus {
+    /// Changed files, and untracked and ignored folders as a whole
+    entries: BTreeMap<PathBuf, FileStatus>,
+    /// Folders that contain changed files
+    folders: BTreeMap<PathBuf, FileStatus>,
+    /// Git folders of the repositories, they're changed by commits and staging
+    git_dirs: Vec<PathBuf>
+}
+
+impl VcsStatus {
+    /// Reads the status of the repository that contains the folder. Paths of the files
+    /// start with the folder path as it's given.
+    pub fn read_folder(&mut self, folder: &Path) -> Result<(), VcsError> {
+        let dirs = try!(git(folder, &["rev-parse", "--show-toplevel", "--absolute-git-dir"]));
+        let (top, git_dir) = {
+            let mut lines = dirs.lines();
+            match (lines.next(), lines.next()) {
+                (Some(top), Some(git_dir)) => (PathBuf::from(top), PathBuf::from(git_dir)),
+                _ => return Err(VcsError::Git(dirs.clone()))
+            }
+        };
+        let relative = match try!(folder.canonicalize()).strip_prefix(&top) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => return Ok(())
+        };
+        let output = try!(git(folder, &["status", "--porcelain", "-z", "--ignored"]));
+        let mut records = output.split('\0');
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue
+            }
+            let (code, path) = (&record[..2], Path::new(record[3..].trim_right_matches('/')));
+            if code.starts_with('R') || code.starts_with('C') {
+                // original path follows the renamed or copied one
+                records.next();
+            }
+            let status = match code {
+                "??" => FileStatus::Untracked,
+                "!!" => FileStatus::Ignored,
+                _ if code.starts_with('A') => FileStatus::Added,
+                _ => FileStatus::Modified
+            };
+            if let Ok(inner) = path.strip_prefix(&relative) {
+                self.insert(folder, folder.join(inner), status);
+            } else if relative.starts_with(path) {
+                // the whole project folder is untracked or ignored
+                self.entries.insert(folder.to_path_buf(), status);
+            }
+        }
+        if !self.git_dirs.contains(&git_dir) {
+            self.git_dirs.push(git_dir);
+        }
+        Ok(())
+    }
+
+    /// Adds the changed entry and marks the folders that contain it.
+    fn insert(&mut self, folder: &Path, path: PathBuf, status: FileStatus) {
+        if status != FileStatus::Ignored {
+            let mut parent = path.parent();
+            while let Some(path) = parent {
+                if !path.starts_with(folder) {
+                    break
+                }
+                let folder_status = self.folders.entry(path.to_path_buf()).or_insert(status);
+                *folder_status = max(*folder_status, status);
+                parent = path.parent();
+            }
+        }
+        self.entries.insert(path, status);
+    }
+
+    /// Returns the status of the file or the folder, entries of untracked and ignored folders
+    /// have the status of the folder.
+    pub fn status(&self, path: &Path) -> Option<FileStatus> {
+        if let Some(&status) = self.entries.get(path).or_else(|| self.folders.get(path)) {
+            return Some(status)
+        }
+        let mut parent = path.parent();
+        while let Some(path) = parent {
+            if let Some(&status) = self.entries.get(path) {
+                return Some(status)
+            }
+            parent = path.parent();
+        }
+        None
+    }
+
+    /// Returns git folders of the repositories that contain the project folders.
+    pub fn git_dirs(&self) -> &[PathBuf] {
+        &self.git_dirs
+    }
+}
+
+/// Reads git status in the background thread, so big repositories don't block the window.
+/// Requests that come in a row are read once.
+#[derive(Debug)]
+pub struct VcsReader {
+    requests: Sender<Vec<PathBuf>>,
+    results: Receiver<VcsStatus>
+}
+
+impl VcsReader {
+    pub fn new() -> VcsReader {
+        let (requests, requests_receiver) = channel::<Vec<PathBuf>>();
+        let (results_sender, results) = channel();
+        thread::spawn(move || {
+            while let Ok(mut folders) = requests_receiver.recv() {
+                let started = Instant::now();
+                while started.elapsed() < Duration::from_millis(MAX_READ_DELAY) {
+                    match requests_receiver.recv_timeout(Duration::from_millis(READ_DELAY)) {
+                        Ok(next) => folders = next,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return
+                    }
+                }
+                if results_sender.send(read_status(&folders)).is_err() {
+                    return
+                }
+            }
+        });
+        VcsReader {
+            requests: requests,
+            results: results
+        }
+    }
+
+    /// Asks to read the status of the folders again.
+    pub fn read(&self, folders: Vec<PathBuf>) {
+        if self.requests.send(folders).is_err() {
+            error!("Git status thread is stopped");
+        }
+    }
+
+    /// Takes the latest status read since the last call.
+    pub fn status(&self) -> Option<VcsStatus> {
+        let mut status = None;
+        while let Ok(result) = self.results.try_recv() {
+            status = Some(result);
+        }
+        status
+    }
+}
+
+/// Reads versions of the files in git HEAD in the background thread.
+#[derive(Debug)]
+pub struct HeadReader {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Option<Vec<String>>)>
+}
+
+impl HeadReader {
+    pub fn new() -> HeadReader {
+        let (requests, requests_receiver) = channel::<PathBuf>();
+        let (results_sender, results) = channel();
+        thread::spawn(move || {
+            while let Ok(path) = requests_receiver.recv() {
+                let lines = head_lines(&path).ok();
+                if results_sender.send((path, lines)).is_err() {
+                    return
+                }
+            }
+        });
+        HeadReader {
+            requests: requests,
+            results: results
+        }
+    }
+
+    /// Asks to read the HEAD version of the file.
+    pub fn read(&self, path: PathBuf) {
+        if self.requests.send(path).is_err() {
+            error!("Git HEAD thread is stopped");
+        }
+    }
+
+    /// Takes the files read since the last call with their lines, the lines are missing
+    /// for the files that aren't committed.
+    pub fn lines(&self) -> Vec<(PathBuf, Option<Vec<String>>)> {
+        let mut lines = Vec::new();
+        while let Ok(result) = self.results.try_recv() {
+            lines.push(result);
+        }
+        lines
+    }
+}
+
+/// Reads git status of the folders, folders outside of repositories are skipped.
+fn read_status(folders: &[PathBuf]) -> VcsStatus {
+    let mut status = VcsStatus::default();
+    for folder in folders {
+        match status.read_folder(folder) {
+            Ok(()) | Err(VcsError::Git(_)) => {},
+            Err(err) => warn!("Can't read git status of {:?}: {:?}", folder, err)
+        }
+    }
+    status
+}
+
+/// Returns lines of the file as it's committed in git HEAD.
+pub fn head_lines(path: &Path) -> Result<Vec<String>, VcsError> {
+    let (folder, name) = match (path.parent(), path.file_name()) {
+        (Some(folder), Some(name)) => (folder, name.to_string_lossy()),
+        _ => return Err(VcsError::Git(format!("{:?} is not a file", path)))
+    };
+    let folder = if folder.as_os_str().is_empty() { Path::new(".") } else { folder };
+    let text = try!(git(folder, &["show", &format!("HEAD:./{}", name)]));
+    Ok(text.lines().map(str::to_owned).collect())
+}
+
+/// Runs git in the folder and returns its output. Git doesn't refresh the index, otherwise
+/// watching the git folder would cause a new status read.
+fn git(folder: &Path, args: &[&str]) -> Result<String, VcsError> {
+    let output = try!(Command::new("git")
+        .arg("--no-optional-locks")
+        .arg("-C")
+        .arg(folder)
+        .args(args)
+        .output());
+    if !output.status.success() {
+        return Err(VcsError::Git(String::from_utf8_lossy(&output.stderr).into_owned()))
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use core::temp_dir::TempDir;
+
+    use super::{FileStatus, HeadReader, VcsReader, VcsStatus, git};
+
+    fn wait_for_status(reader: &VcsReader) -> Option<VcsStatus> {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(50));
+            if let Some(status) = reader.status() {
+                return Some(status)
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn read_in_background() {
+        let dir = TempDir::new("vcs-read");
+        git(dir.path(), &["init", "--quiet"]).unwrap();
+        dir.create_dir("src");
+        let file = dir.write("src/new.rs", "");
+
+        let reader = VcsReader::new();
+        // requests in a row are read once
+        for _ in 0..3 {
+            reader.read(vec![dir.path().to_path_buf()]);
+        }
+        let status = wait_for_status(&reader).unwrap();
+        assert_eq!(status.status(&file), Some(FileStatus::Untracked));
+        assert_eq!(status.status(&dir.join("src")), Some(FileStatus::Untracked));
+        assert!(status.status(&dir.join("other.rs")).is_none());
+        thread::sleep(Duration::from_millis(500));
+        assert!(reader.status().is_none());
+    }
+
+    #[test]
+    fn outside_of_repository() {
+        let dir = TempDir::new("vcs-outside");
+        dir.write("file.rs", "");
+        let reader = VcsReader::new();
+        reader.read(vec![dir.path().to_path_buf()]);
+        let status = wait_for_status(&reader).unwrap();
+        assert!(status.status(&dir.join("file.rs")).is_none());
+        assert!(status.git_dirs().is_empty());
+    }
+
+    #[test]
+    fn read_head_in_background() {
+        let dir = TempDir::new("vcs-head");
+        git(dir.path(), &["init", "--quiet"]).unwrap();
+        let file = dir.write("file.rs", "first\nsecond\n");
+        git(dir.path(), &["add", "file.rs"]).unwrap();
+        git(dir.path(), &["-c", "user.name=test", "-c", "user.email=test@example.com",
+                          "commit", "--quiet", "-m", "file"]).unwrap();
+        dir.write("file.rs", "changed\n");
+        let new_file = dir.write("new.rs", "");
+
+        let reader = HeadReader::new();
+        reader.read(file.clone());
+        reader.read(new_file.clone());
+        let mut lines = Vec::new();
+        let started = Instant::now();
+        while lines.len() < 2 && started.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(50));
+            lines.extend(reader.lines());
+        }
+        assert_eq!(lines, vec![(file, Some(vec!["first".to_owned(), "second".to_owned()])),
+                               (new_file, None)]);
+    }
+}
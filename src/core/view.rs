@@ -1,68 +1,682 @@
+use std::borrow::Cow;
+use std::cell::{Cell, Ref, RefMut, RefCell};
+use std::cmp::{min, max};
 use std::path::PathBuf;
-use std::fs::File;
-use std::io::{BufReader, BufRead, Error as IoError};
+use std::rc::Rc;
+use std::io::Error as IoError;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 
-use core::syntax::{Parser, ParserState, Highlighter, HighlightIterator};
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Debug)]
-pub struct View {
-    path: Option<PathBuf>,
-    pub lines: Vec<Line>
+use core::buffer::{Buffer, BufferId, Edit};
+use core::diff::Hunk;
+use core::region::{Point, Region};
+use core::syntax::Parser;
+use core::symbols::{Symbol, SymbolList};
+use core::search::Search;
+use core::preferences::Preferences;
+use core::wrap::{Row, wrap_line, row_index};
+
+pub type ViewId = usize;
+
+static NEXT_VIEW_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Unit of the selection made by mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectBy {
+    Characters,
+    Words,
+    Lines,
+    Columns
 }
 
+/// View into a text buffer. Multiple views may refer to the same buffer,
+/// but each of them has its own selection and viewport.
 #[derive(Debug)]
-pub struct Line {
-    pub text: String,
-    parser_state: ParserState,
+pub struct View {
+    id: ViewId,
+    buffer: Rc<RefCell<Buffer>>,
+    pub selection: Vec<Region>,
+    pub preferences: Preferences,
+    /// Horizontal offset (in cells) and the first visible line.
+    viewport: (usize, usize),
+    /// Width and height of the viewport, updated on each render.
+    extent: Cell<(usize, usize)>
 }
 
-impl Line {
-    fn new(text: String) -> Line {
-        Line {
-            text: text,
-            parser_state: ParserState::new()
+impl View {
+    pub fn new(buffer: Buffer) -> View {
+        View::with_buffer(Rc::new(RefCell::new(buffer)))
+    }
+
+    fn with_buffer(buffer: Rc<RefCell<Buffer>>) -> View {
+        View {
+            id: NEXT_VIEW_ID.fetch_add(1, Ordering::SeqCst),
+            buffer: buffer,
+            selection: vec![Region::default()],
+            preferences: Preferences::default(),
+            viewport: (0, 0),
+            extent: Cell::new((0, 0))
         }
     }
 
-    pub fn parse(&mut self, parser: &mut Parser, state: &mut ParserState) {
-        self.parser_state = state.clone();
-        parser.parse(&self.text, state);
-        self.parser_state.swap_changes(state);
+    pub fn open(path: PathBuf) -> Result<View, IoError> {
+        Ok(View::new(try!(Buffer::open(path))))
     }
 
-    pub fn highlight<'a>(&'a self, highlighter: &'a Highlighter) -> HighlightIterator<'a> {
-        HighlightIterator::new(
-            self.parser_state.scope_path.clone(),
-            &self.parser_state.changes,
-            &self.text,
-            highlighter
-        )
+    /// Creates new view into the same buffer.
+    pub fn clone_view(&self) -> View {
+        let mut view = View::with_buffer(self.buffer.clone());
+        view.selection = self.selection.clone();
+        view.preferences = self.preferences.clone();
+        view.viewport = self.viewport;
+        view
     }
-}
 
-impl View {
-    pub fn new() -> View {
-        View {
-            path: None,
-            lines: Vec::new()
+    pub fn id(&self) -> ViewId {
+        self.id
+    }
+
+    pub fn buffer_id(&self) -> BufferId {
+        self.buffer.borrow().id()
+    }
+
+    pub fn buffer(&self) -> Ref<Buffer> {
+        self.buffer.borrow()
+    }
+
+    pub fn buffer_mut(&self) -> RefMut<Buffer> {
+        self.buffer.borrow_mut()
+    }
+
+    pub fn name(&self) -> Cow<str> {
+        Cow::Owned(self.buffer().name().into_owned())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.buffer().is_dirty()
+    }
+
+    /// Extracts all the symbols defined in the buffer.
+    pub fn symbols(&self, list: &SymbolList) -> Vec<Symbol> {
+        self.buffer().symbols(list)
+    }
+
+    pub fn set_parser(&mut self, parser: Parser) {
+        self.buffer_mut().set_parser(parser);
+    }
+
+    /// Updates selection after the buffer was changed by another view.
+    pub fn adjust(&mut self, edits: &[Edit]) {
+        for edit in edits {
+            for region in &mut self.selection {
+                region.a = edit.transform(region.a);
+                region.b = edit.transform(region.b);
+            }
         }
     }
 
-    pub fn open(path: PathBuf) -> Result<View, IoError> {
-        let mut lines = Vec::new();
-        for text in BufReader::new(try!(File::open(&path))).lines() {
-            lines.push(Line::new(try!(text)));
+    /// Replaces each selected region by the text returned by `f`. Returns all performed edits.
+    fn replace_regions<F>(&mut self, mut f: F) -> Vec<Edit>
+            where F: FnMut(&Buffer, Region) -> (Point, Point, String) {
+        let mut edits = Vec::new();
+        let mut buffer = self.buffer.borrow_mut();
+        for i in 0..self.selection.len() {
+            let (begin, end, text) = f(&buffer, self.selection[i]);
+            let edit = buffer.replace(begin, end, &text);
+            self.selection[i] = Region::caret(edit.new_end);
+            for region in &mut self.selection[i + 1..] {
+                region.a = edit.transform(region.a);
+                region.b = edit.transform(region.b);
+            }
+            edits.push(edit);
         }
-        Ok(View {
-            path: Some(path),
-            lines: lines
+        edits
+    }
+
+    pub fn insert(&mut self, text: &str) -> Vec<Edit> {
+        self.replace_regions(|_, region| (region.begin(), region.end(), text.to_owned()))
+    }
+
+    pub fn left_delete(&mut self) -> Vec<Edit> {
+        self.replace_regions(|buffer, region| {
+            if region.is_empty() {
+                (buffer.prev_point(region.b), region.b, String::new())
+            } else {
+                (region.begin(), region.end(), String::new())
+            }
         })
     }
 
-    pub fn parse(&mut self, parser: &mut Parser) {
-        let mut state = ParserState::new();
-        for line in &mut self.lines {
-            line.parse(parser, &mut state);
+    pub fn right_delete(&mut self) -> Vec<Edit> {
+        self.replace_regions(|buffer, region| {
+            if region.is_empty() {
+                (region.b, buffer.next_point(region.b), String::new())
+            } else {
+                (region.begin(), region.end(), String::new())
+            }
+        })
+    }
+
+    fn move_carets<F>(&mut self, extend: bool, f: F) where F: Fn(&Buffer, Point) -> Point {
+        let buffer = self.buffer.borrow();
+        for region in &mut self.selection {
+            region.b = f(&buffer, region.b);
+            if !extend {
+                region.a = region.b;
+            }
+        }
+    }
+
+    pub fn move_by_characters(&mut self, forward: bool, extend: bool) {
+        self.move_carets(extend, |buffer, point| {
+            if forward {
+                buffer.next_point(point)
+            } else {
+                buffer.prev_point(point)
+            }
+        })
+    }
+
+    pub fn move_by_lines(&mut self, forward: bool, extend: bool) {
+        let wrap_width = self.wrap_width();
+        if wrap_width > 0 {
+            let indent = self.preferences.indent_subsequent_lines;
+            self.move_carets(extend, |buffer, point| {
+                move_by_rows(buffer, point, forward, wrap_width, indent)
+            });
+            return
+        }
+        self.move_carets(extend, |buffer, point| {
+            if forward {
+                if point.line + 1 < buffer.lines.len() {
+                    buffer.clip_point(point.line + 1, point.column)
+                } else {
+                    buffer.last_point()
+                }
+            } else if point.line > 0 {
+                buffer.clip_point(point.line - 1, point.column)
+            } else {
+                Point::default()
+            }
+        })
+    }
+
+    /// Moves carets to `bol`, `eol`, `bof` or `eof`.
+    pub fn move_to(&mut self, to: &str, extend: bool) {
+        self.move_carets(extend, |buffer, point| {
+            match to {
+                "bol" => Point::new(point.line, 0),
+                "eol" => Point::new(point.line, buffer.line_len(point.line)),
+                "bof" => Point::default(),
+                "eof" => buffer.last_point(),
+                _ => point
+            }
+        })
+    }
+
+    pub fn move_by_pages(&mut self, forward: bool, extend: bool) {
+        let lines = max(self.extent.get().1, 2) - 1;
+        self.move_carets(extend, |buffer, point| {
+            if forward {
+                buffer.clip_point(point.line + lines, point.column)
+            } else if point.line >= lines {
+                buffer.clip_point(point.line - lines, point.column)
+            } else {
+                Point::default()
+            }
+        });
+        let (x, y) = self.viewport;
+        if forward {
+            self.set_viewport_position(x, y + lines);
+        } else {
+            self.set_viewport_position(x, y.saturating_sub(lines));
         }
     }
+
+    /// Returns the horizontal position of the point in cells.
+    fn point_x(&self, point: Point) -> usize {
+        self.buffer().lines[point.line].text[..point.column].width()
+    }
+
+    /// Returns width at which lines are wrapped or zero if word wrap is disabled.
+    pub fn wrap_width(&self) -> usize {
+        if !self.preferences.word_wrap {
+            return 0;
+        }
+        let (width, _) = self.extent.get();
+        match self.preferences.wrap_width {
+            0 => width,
+            wrap_width => min(wrap_width, width)
+        }
+    }
+
+    /// Splits the line into visual rows according to the word wrap settings.
+    pub fn rows(&self, line: usize) -> Vec<Row> {
+        wrap_line(&self.buffer().lines[line].text,
+                  self.wrap_width(),
+                  self.preferences.indent_subsequent_lines)
+    }
+
+    /// Returns width of the gutter including padding around line numbers.
+    pub fn gutter_width(&self) -> usize {
+        if !self.preferences.gutter {
+            0
+        } else if self.preferences.line_numbers {
+            let mut digits = 1;
+            let mut n = self.buffer().lines.len();
+            while n >= 10 {
+                n /= 10;
+                digits += 1;
+            }
+            digits + 2
+        } else {
+            2
+        }
+    }
+
+    /// Returns the point displayed at the given position of the text area.
+    pub fn point_at(&self, x: usize, y: usize) -> Point {
+        let (skip, first) = self.viewport;
+        let skip = if self.wrap_width() > 0 { 0 } else { skip };
+        let buffer = self.buffer();
+        let mut rows_left = y;
+        for line in first..buffer.lines.len() {
+            let rows = self.rows(line);
+            if rows_left < rows.len() {
+                let column = rows[rows_left].x_column(&buffer.lines[line].text, x + skip);
+                return Point::new(line, column);
+            }
+            rows_left -= rows.len();
+        }
+        buffer.last_point()
+    }
+
+    /// Returns the horizontal offset and the first visible line.
+    pub fn viewport_position(&self) -> (usize, usize) {
+        self.viewport
+    }
+
+    pub fn set_viewport_position(&mut self, x: usize, y: usize) {
+        let last_line = self.buffer().lines.len() - 1;
+        self.viewport = (x, min(y, last_line));
+    }
+
+    /// Returns the width and height of the viewport.
+    pub fn viewport_extent(&self) -> (usize, usize) {
+        self.extent.get()
+    }
+
+    pub fn set_viewport_extent(&self, width: usize, height: usize) {
+        self.extent.set((width, height));
+    }
+
+    /// Returns the region of the fully visible lines.
+    pub fn visible_region(&self) -> Region {
+        let (_, y) = self.viewport;
+        let (_, height) = self.extent.get();
+        let len = self.buffer().lines.len();
+        let mut last = y;
+        let mut rows = self.rows(y).len();
+        while last + 1 < len && rows + self.rows(last + 1).len() <= height {
+            last += 1;
+            rows += self.rows(last).len();
+        }
+        Region::new(Point::new(y, 0), Point::new(last, self.buffer().line_len(last)))
+    }
+
+    /// Scrolls the viewport by the given amount of lines. Positive amount scrolls up.
+    pub fn scroll_lines(&mut self, amount: isize) {
+        let (x, y) = self.viewport;
+        if amount > 0 {
+            self.set_viewport_position(x, y.saturating_sub(amount as usize));
+        } else {
+            self.set_viewport_position(x, y + (-amount) as usize);
+        }
+    }
+
+    /// Scrolls the viewport to show the given point.
+    pub fn show(&mut self, point: Point) {
+        let (width, height) = self.extent.get();
+        let (mut x, mut y) = self.viewport;
+        if point.line < y {
+            y = point.line;
+        } else if height > 0 {
+            // lines above the point are taken while their rows fit in the height
+            let len = self.buffer().line_len(point.line);
+            let mut rows = row_index(&self.rows(point.line), point.column, len) + 1;
+            let mut line = point.line;
+            while line > y {
+                let above = self.rows(line - 1).len();
+                if rows + above > height {
+                    break
+                }
+                rows += above;
+                line -= 1;
+            }
+            y = line;
+        }
+        let px = self.point_x(point);
+        if self.wrap_width() > 0 {
+            x = 0;
+        } else if px < x {
+            x = px;
+        } else if width > 0 && px >= x + width {
+            x = px + 1 - width;
+        }
+        self.viewport = (x, y);
+    }
+
+    /// Scrolls the viewport to center on the point.
+    pub fn show_at_center(&mut self, point: Point) {
+        let (width, height) = self.extent.get();
+        let px = self.point_x(point);
+        let x = if px < width { 0 } else { px - width / 2 };
+        self.viewport = (x, point.line.saturating_sub(height / 2));
+    }
+
+    /// Replaces the selection by the region and scrolls the viewport to center on it.
+    pub fn select_region(&mut self, region: Region) {
+        self.selection = vec![region];
+        self.show_at_center(region.b);
+    }
+
+    /// Selects the match that follows the last selected region, or precedes it when searching
+    /// backward. Returns false if there are no matches.
+    pub fn find(&mut self, search: &Search, forward: bool) -> bool {
+        let point = match self.selection.last() {
+            Some(region) if forward => region.end(),
+            Some(region) => region.begin(),
+            None => Point::default()
+        };
+        self.find_from(search, point, forward)
+    }
+
+    /// Selects the match that follows the point, or precedes it when searching backward.
+    pub fn find_from(&mut self, search: &Search, point: Point, forward: bool) -> bool {
+        let found = search.find_next(&self.buffer(), point, forward);
+        match found {
+            Some(region) => {
+                self.selection = vec![region];
+                self.show(region.b);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Selects all matches. Returns false if there are no matches.
+    pub fn find_all(&mut self, search: &Search) -> bool {
+        let matches = search.find_all(&self.buffer());
+        if matches.is_empty() {
+            return false
+        }
+        self.selection = matches;
+        self.show_caret();
+        true
+    }
+
+    /// Replaces the selected match, if it's selected, and selects the next one.
+    pub fn replace_next(&mut self, search: &Search, format: &str) -> Vec<Edit> {
+        let region = match self.selection.last() {
+            Some(&region) if search.is_match(&self.buffer(), region) => region,
+            _ => {
+                self.find(search, true);
+                return Vec::new()
+            }
+        };
+        let text = search.replacement(&self.buffer(), region, format);
+        self.selection = vec![region];
+        let edits = self.replace_regions(|_, region| (region.begin(), region.end(), text.clone()));
+        self.find(search, true);
+        edits
+    }
+
+    /// Replaces all matches, carets are placed after the replaced text.
+    pub fn replace_all(&mut self, search: &Search, format: &str) -> Vec<Edit> {
+        let matches = search.find_all(&self.buffer());
+        if matches.is_empty() {
+            return Vec::new()
+        }
+        // replacements are expanded before the buffer is changed
+        let mut replacements: Vec<String> = matches.iter()
+            .map(|&region| search.replacement(&self.buffer(), region, format))
+            .collect();
+        replacements.reverse();
+        self.selection = matches;
+        let edits = self.replace_regions(|_, region| {
+            (region.begin(), region.end(), replacements.pop().unwrap_or_default())
+        });
+        self.show_caret();
+        edits
+    }
+
+    /// Moves the caret to the next changed lines, or to the previous ones. The search wraps
+    /// around the end of the buffer.
+    pub fn move_to_modification(&mut self, forward: bool) {
+        let line = self.selection.last().map_or(0, |region| region.b.line);
+        let found = match self.buffer().diff() {
+            Some(diff) => {
+                let lines: Vec<usize> = diff.hunks().iter().map(Hunk::line).collect();
+                if forward {
+                    lines.iter().find(|&&next| next > line).or(lines.first()).cloned()
+                } else {
+                    lines.iter().rev().find(|&&prev| prev < line).or(lines.last()).cloned()
+                }
+            },
+            None => None
+        };
+        if let Some(line) = found {
+            self.select_region(Region::caret(Point::new(line, 0)));
+        }
+    }
+
+    /// Reverts the changed lines touched by the selection to git HEAD. Returns the edits.
+    pub fn revert_hunks(&mut self) -> Vec<Edit> {
+        let replacements: Vec<(Point, Point, String)> = {
+            let buffer = self.buffer();
+            let diff = match buffer.diff() {
+                Some(diff) => diff,
+                None => return Vec::new()
+            };
+            diff.hunks().iter()
+                .filter(|hunk| self.selection.iter().any(|region| {
+                    hunk.touches(region.begin().line, region.end().line)
+                }))
+                .map(|hunk| diff.revert(hunk, &buffer.lines))
+                .collect()
+        };
+        // going backward, so the preceding lines stay in place
+        let edits: Vec<Edit> = replacements.into_iter().rev().map(|(begin, end, text)| {
+            self.buffer_mut().replace(begin, end, &text)
+        }).collect();
+        self.adjust(&edits);
+        edits
+    }
+
+    /// Selects the text changed by the edits.
+    fn select_edits(&mut self, edits: &[Edit]) {
+        let mut selection: Vec<Region> = Vec::new();
+        for edit in edits {
+            for region in &mut selection {
+                region.a = edit.transform(region.a);
+                region.b = edit.transform(region.b);
+            }
+            selection.push(Region::new(edit.begin, edit.new_end));
+        }
+        if !selection.is_empty() {
+            selection.sort_by_key(|region| region.begin());
+            self.selection = selection;
+            self.show_caret();
+        }
+    }
+
+    pub fn undo(&mut self) -> Vec<Edit> {
+        let edits = self.buffer_mut().undo();
+        self.select_edits(&edits);
+        edits
+    }
+
+    pub fn redo(&mut self) -> Vec<Edit> {
+        let edits = self.buffer_mut().redo();
+        self.select_edits(&edits);
+        edits
+    }
+
+    /// Scrolls the viewport to show the last caret.
+    pub fn show_caret(&mut self) {
+        if let Some(region) = self.selection.last().cloned() {
+            self.show(region.b);
+        }
+    }
+
+    fn unit_region(&self, point: Point, by: SelectBy) -> Region {
+        match by {
+            SelectBy::Characters | SelectBy::Columns => Region::caret(point),
+            SelectBy::Words => self.buffer().word_region(point),
+            SelectBy::Lines => self.buffer().line_region(point.line)
+        }
+    }
+
+    /// Starts mouse selection at the point. Returns the region under the point, which is
+    /// used as an anchor while dragging, and amount of regions that dragging doesn't touch.
+    /// Subtractive selection only removes regions under the point, so nothing is dragged.
+    pub fn drag_select(&mut self, point: Point, by: SelectBy,
+                       additive: bool, subtractive: bool, extend: bool) -> Option<(Region, usize)> {
+        if subtractive && !extend {
+            if self.selection.len() > 1 {
+                self.selection.retain(|region| !region.contains(point));
+            }
+            if self.selection.is_empty() {
+                self.selection.push(Region::caret(point));
+            }
+            return None;
+        }
+        let anchor = if extend {
+            match self.selection.pop() {
+                Some(region) => Region::caret(region.a),
+                None => Region::caret(point)
+            }
+        } else {
+            self.unit_region(point, by)
+        };
+        if !additive && !subtractive {
+            self.selection.clear();
+        }
+        let keep = self.selection.len();
+        self.drag_to(anchor, keep, point, by);
+        Some((anchor, keep))
+    }
+
+    /// Updates mouse selection started by `drag_select`.
+    pub fn drag_to(&mut self, anchor: Region, keep: usize, point: Point, by: SelectBy) {
+        self.selection.truncate(keep);
+        if by == SelectBy::Columns {
+            let (from, to) = (self.point_x(anchor.a), self.point_x(point));
+            let buffer = self.buffer.borrow();
+            let lines: Vec<usize> = if anchor.a.line <= point.line {
+                (anchor.a.line..point.line + 1).collect()
+            } else {
+                (point.line..anchor.a.line + 1).rev().collect()
+            };
+            for line in lines {
+                let text = &buffer.lines[line].text;
+                let row = Row { begin: 0, end: text.len(), indent: 0 };
+                self.selection.push(Region::new(Point::new(line, row.x_column(text, from)),
+                                                Point::new(line, row.x_column(text, to))));
+            }
+            return
+        }
+        let unit = self.unit_region(point, by);
+        let region = if unit.begin() < anchor.begin() {
+            Region::new(anchor.end(), unit.begin())
+        } else {
+            Region::new(anchor.begin(), unit.end())
+        };
+        self.selection.push(region);
+    }
+
+    /// Sorts regions and merges overlapping ones.
+    pub fn normalize_selection(&mut self) {
+        self.selection.sort_by(|x, y| x.begin().cmp(&y.begin()));
+        let mut selection: Vec<Region> = Vec::with_capacity(self.selection.len());
+        for region in self.selection.drain(..) {
+            if let Some(last) = selection.last_mut() {
+                if region.begin() < last.end() || region.begin() == last.begin() {
+                    let end = max(last.end(), region.end());
+                    *last = Region::new(last.begin(), end);
+                    continue
+                }
+            }
+            selection.push(region);
+        }
+        self.selection = selection;
+    }
+}
+
+/// Moves the point to the same cell offset in the next or previous visual row.
+fn move_by_rows(buffer: &Buffer, point: Point, forward: bool, width: usize, indent: bool) -> Point {
+    let text = &buffer.lines[point.line].text;
+    let rows = wrap_line(text, width, indent);
+    let index = row_index(&rows, point.column, text.len());
+    let x = rows[index].column_x(text, point.column);
+    let (line, row) = if forward {
+        if index + 1 < rows.len() {
+            (point.line, rows[index + 1])
+        } else if point.line + 1 < buffer.lines.len() {
+            (point.line + 1, wrap_line(&buffer.lines[point.line + 1].text, width, indent)[0])
+        } else {
+            return buffer.last_point();
+        }
+    } else if index > 0 {
+        (point.line, rows[index - 1])
+    } else if point.line > 0 {
+        let rows = wrap_line(&buffer.lines[point.line - 1].text, width, indent);
+        (point.line - 1, rows[rows.len() - 1])
+    } else {
+        return Point::default();
+    };
+    Point::new(line, row.x_column(&buffer.lines[line].text, x))
+}
+
+#[cfg(test)]
+mod tests {
+    use core::buffer::Buffer;
+    use core::region::Point;
+
+    use super::View;
+
+    fn view(lines: usize) -> View {
+        let mut buffer = Buffer::new();
+        let text: Vec<&str> = (0..lines).map(|_| "aaaa bbbb").collect();
+        buffer.insert(Point::new(0, 0), &text.join("\n"));
+        let view = View::new(buffer);
+        view.set_viewport_extent(5, 10);
+        view
+    }
+
+    #[test]
+    fn show_below_viewport() {
+        let mut view = view(100);
+        view.show(Point::new(50, 0));
+        assert_eq!(view.viewport_position(), (0, 41));
+        // lines above the viewport aren't shown by scrolling down
+        view.show(Point::new(45, 0));
+        assert_eq!(view.viewport_position(), (0, 41));
+        view.show(Point::new(20, 0));
+        assert_eq!(view.viewport_position(), (0, 20));
+    }
+
+    #[test]
+    fn show_wrapped_lines() {
+        let mut view = view(100);
+        view.preferences.word_wrap = true;
+        // each line is wrapped to two rows
+        view.show(Point::new(50, 0));
+        assert_eq!(view.viewport_position(), (0, 46));
+        view.show(Point::new(50, 5));
+        assert_eq!(view.viewport_position(), (0, 46));
+        view.show(Point::new(51, 5));
+        assert_eq!(view.viewport_position(), (0, 47));
+    }
 }
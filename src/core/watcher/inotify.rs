@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr};
+use std::io::{Error as IoError, ErrorKind};
+use std::os::raw::{c_char, c_int, c_short, c_ulong, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+use super::Change;
+
+extern "C" {
+    fn inotify_init1(flags: c_int) -> c_int;
+    fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+    fn inotify_rm_watch(fd: c_int, wd: c_int) -> c_int;
+    fn pipe2(fds: *mut c_int, flags: c_int) -> c_int;
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+    fn close(fd: c_int) -> c_int;
+}
+
+/// `struct pollfd`
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short
+}
+
+const IN_CLOEXEC: c_int = 0o2000000;
+const O_CLOEXEC: c_int = 0o2000000;
+
+const POLLIN: c_short = 0x1;
+
+const IN_MODIFY: u32 = 0x2;
+const IN_CLOSE_WRITE: u32 = 0x8;
+const IN_MOVED_FROM: u32 = 0x40;
+const IN_MOVED_TO: u32 = 0x80;
+const IN_CREATE: u32 = 0x100;
+const IN_DELETE: u32 = 0x200;
+const IN_Q_OVERFLOW: u32 = 0x4000;
+const IN_IGNORED: u32 = 0x8000;
+const IN_ONLYDIR: u32 = 0x1000000;
+
+const ENTRY_EVENTS: u32 = IN_MOVED_FROM | IN_MOVED_TO | IN_CREATE | IN_DELETE;
+const CONTENTS_EVENTS: u32 = IN_MODIFY | IN_CLOSE_WRITE;
+
+/// Size of `struct inotify_event` without the name.
+const EVENT_SIZE: usize = 16;
+/// Fits several events with the longest names.
+const BUFFER_SIZE: usize = 4096;
+
+/// Paths of the folders by the watch descriptors. The same folder can be given by several
+/// paths, inotify returns the same descriptor for them.
+type Watches = Arc<Mutex<HashMap<c_int, Vec<PathBuf>>>>;
+
+#[derive(Debug)]
+pub struct Backend {
+    /// Inotify instance, it's negative if inotify isn't available
+    fd: c_int,
+    watches: Watches,
+    /// Write end of the pipe that wakes the reading thread up when it's closed
+    stop: c_int,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Backend {
+    /// Starts the thread that reads the inotify events.
+    pub fn new(changes: Sender<Change>) -> Backend {
+        let mut backend = Backend {
+            fd: unsafe { inotify_init1(IN_CLOEXEC) },
+            watches: Arc::new(Mutex::new(HashMap::new())),
+            stop: -1,
+            thread: None
+        };
+        if backend.fd < 0 {
+            error!("Can't initialize inotify: {}", IoError::last_os_error());
+            return backend
+        }
+        let mut pipe = [-1; 2];
+        if unsafe { pipe2(pipe.as_mut_ptr(), O_CLOEXEC) } < 0 {
+            error!("Can't create inotify pipe: {}", IoError::last_os_error());
+            unsafe { close(backend.fd) };
+            backend.fd = -1;
+            return backend
+        }
+        let (fd, stopped, watches) = (backend.fd, pipe[0], backend.watches.clone());
+        backend.stop = pipe[1];
+        backend.thread = Some(thread::spawn(move || {
+            read_events(fd, stopped, watches, changes);
+            unsafe { close(stopped) };
+        }));
+        backend
+    }
+
+    pub fn add(&mut self, path: &Path) {
+        if self.fd < 0 {
+            return
+        }
+        let c_path = match CString::new(path.as_os_str().as_bytes()) {
+            Ok(c_path) => c_path,
+            Err(_) => return
+        };
+        let mask = ENTRY_EVENTS | CONTENTS_EVENTS | IN_ONLYDIR;
+        let wd = unsafe { inotify_add_watch(self.fd, c_path.as_ptr(), mask) };
+        if wd < 0 {
+            warn!("Can't watch {:?}: {}", path, IoError::last_os_error());
+            return
+        }
+        self.watches.lock().unwrap().entry(wd).or_insert_with(Vec::new).push(path.to_path_buf());
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        let mut watches = self.watches.lock().unwrap();
+        let wd = match watches.iter().find(|&(_, paths)| paths.iter().any(|p| p == path)) {
+            Some((&wd, _)) => wd,
+            None => return
+        };
+        let is_unused = {
+            let paths = watches.get_mut(&wd).unwrap();
+            paths.retain(|p| p != path);
+            paths.is_empty()
+        };
+        if is_unused {
+            watches.remove(&wd);
+            unsafe { inotify_rm_watch(self.fd, wd) };
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    unsafe { ptr::read_unaligned(bytes.as_ptr() as *const u32) }
+}
+
+impl Drop for Backend {
+    /// Stops the reading thread and closes the inotify instance.
+    fn drop(&mut self) {
+        if self.fd < 0 {
+            return
+        }
+        unsafe { close(self.stop) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        unsafe { close(self.fd) };
+    }
+}
+
+/// Reads the events until the `stopped` end of the pipe is closed on the other side,
+/// or the receiver of the changes is dropped.
+fn read_events(fd: c_int, stopped: c_int, watches: Watches, sender: Sender<Change>) {
+    let mut buffer = [0u8; BUFFER_SIZE];
+    loop {
+        let mut fds = [
+            PollFd { fd: fd, events: POLLIN, revents: 0 },
+            PollFd { fd: stopped, events: POLLIN, revents: 0 }
+        ];
+        if unsafe { poll(fds.as_mut_ptr(), fds.len() as c_ulong, -1) } < 0 {
+            let err = IoError::last_os_error();
+            if err.kind() == ErrorKind::Interrupted {
+                continue
+            }
+            error!("Can't wait for inotify events: {}", err);
+            return
+        }
+        if fds[1].revents != 0 {
+            return
+        }
+        if fds[0].revents == 0 {
+            continue
+        }
+        let len = unsafe { read(fd, buffer.as_mut_ptr() as *mut c_void, BUFFER_SIZE) };
+        if len < 0 {
+            let err = IoError::last_os_error();
+            if err.kind() == ErrorKind::Interrupted {
+                continue
+            }
+            error!("Can't read inotify events: {}", err);
+            return
+        }
+        let changes = parse_events(&buffer[..len as usize], &mut watches.lock().unwrap());
+        if changes.into_iter().any(|change| sender.send(change).is_err()) {
+            return
+        }
+    }
+}
+
+/// Converts the events to the changes in the watched folders.
+fn parse_events(mut events: &[u8], watches: &mut HashMap<c_int, Vec<PathBuf>>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    while events.len() >= EVENT_SIZE {
+        let (wd, mask) = (read_u32(events) as c_int, read_u32(&events[4..]));
+        let name_len = read_u32(&events[12..]) as usize;
+        // name is padded by zeros
+        let name: Vec<u8> = events[EVENT_SIZE..EVENT_SIZE + name_len].iter().cloned()
+            .take_while(|&byte| byte != 0)
+            .collect();
+        events = &events[EVENT_SIZE + name_len..];
+        if mask & IN_Q_OVERFLOW != 0 {
+            changes.push(Change::Overflow);
+            continue
+        }
+        if mask & IN_IGNORED != 0 {
+            // folder was deleted or isn't watched anymore
+            watches.remove(&wd);
+            continue
+        }
+        for folder in watches.get(&wd).into_iter().flat_map(|folders| folders.iter()) {
+            let path = folder.join(OsStr::from_bytes(&name));
+            changes.push(if mask & ENTRY_EVENTS != 0 {
+                Change::Entry(path)
+            } else {
+                Change::Contents(path)
+            });
+        }
+    }
+    changes
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+
+#[cfg(target_os = "linux")]
+mod inotify;
+#[cfg(not(target_os = "linux"))]
+mod poll;
+
+#[cfg(target_os = "linux")]
+use self::inotify::Backend;
+#[cfg(not(target_os = "linux"))]
+use self::poll::Backend;
+
+/// Change in the watched folder.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    /// File or folder was created, deleted or moved
+    Entry(PathBuf),
+    /// File was written
+    Contents(PathBuf),
+    /// Changes were lost, so all the folders should be read again
+    Overflow
+}
+
+/// Watches folders in the background, with inotify on Linux and by polling on other systems.
+/// Folders aren't watched recursively, so the nested ones are given separately.
+#[derive(Debug)]
+pub struct Watcher {
+    backend: Backend,
+    changes: Receiver<Change>,
+    folders: BTreeSet<PathBuf>
+}
+
+impl Watcher {
+    pub fn new() -> Watcher {
+        let (sender, changes) = channel();
+        Watcher {
+            backend: Backend::new(sender),
+            changes: changes,
+            folders: BTreeSet::new()
+        }
+    }
+
+    /// Replaces the watched folders.
+    pub fn watch(&mut self, folders: BTreeSet<PathBuf>) {
+        for folder in self.folders.difference(&folders) {
+            self.backend.remove(folder);
+        }
+        for folder in folders.difference(&self.folders) {
+            self.backend.add(folder);
+        }
+        self.folders = folders;
+    }
+
+    /// Takes the changes reported since the last call, without duplicates.
+    pub fn changes(&self) -> BTreeSet<Change> {
+        let mut changes = BTreeSet::new();
+        while let Ok(change) = self.changes.try_recv() {
+            changes.insert(change);
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use core::temp_dir::TempDir;
+
+    use super::{Change, Watcher};
+
+    /// Collects the changes until the expected one is reported or the time is out.
+    fn wait_for(watcher: &Watcher, expected: &Change) -> BTreeSet<Change> {
+        let started = Instant::now();
+        let mut changes = BTreeSet::new();
+        while !changes.contains(expected) && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(50));
+            changes.extend(watcher.changes());
+        }
+        changes
+    }
+
+    fn watcher(dir: &TempDir) -> Watcher {
+        let mut watcher = Watcher::new();
+        watcher.watch(vec![dir.path().to_path_buf()].into_iter().collect());
+        // poll backend reports the changes after its first scan
+        thread::sleep(Duration::from_millis(1500));
+        watcher
+    }
+
+    #[test]
+    fn created_and_deleted_entries() {
+        let dir = TempDir::new("watcher-entries");
+        let watcher = watcher(&dir);
+
+        let file = dir.write("file.txt", "text");
+        let expected = Change::Entry(file.clone());
+        assert!(wait_for(&watcher, &expected).contains(&expected));
+
+        fs::remove_file(&file).unwrap();
+        assert!(wait_for(&watcher, &expected).contains(&expected));
+
+        let folder = dir.create_dir("folder");
+        let expected = Change::Entry(folder);
+        assert!(wait_for(&watcher, &expected).contains(&expected));
+    }
+
+    #[test]
+    fn written_contents() {
+        let dir = TempDir::new("watcher-contents");
+        let file = dir.write("file.txt", "text");
+        let watcher = watcher(&dir);
+
+        // modification time of the poll backend is precise enough on the common file systems
+        thread::sleep(Duration::from_millis(10));
+        dir.write("file.txt", "changed text");
+        let expected = Change::Contents(file);
+        assert!(wait_for(&watcher, &expected).contains(&expected));
+    }
+
+    #[test]
+    fn unwatched_folders() {
+        let dir = TempDir::new("watcher-unwatched");
+        let mut watcher = watcher(&dir);
+        watcher.watch(BTreeSet::new());
+        thread::sleep(Duration::from_millis(1500));
+
+        let file = dir.write("file.txt", "text");
+        let changes = wait_for(&watcher, &Change::Entry(file));
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn nested_folders_are_not_watched() {
+        let dir = TempDir::new("watcher-nested");
+        dir.create_dir("nested");
+        let watcher = watcher(&dir);
+
+        let file = dir.write("nested/file.txt", "text");
+        let changes = wait_for(&watcher, &Change::Entry(file.clone()));
+        assert!(!changes.contains(&Change::Entry(file)));
+    }
+
+    #[test]
+    fn dropped_watcher() {
+        let dir = TempDir::new("watcher-dropped");
+        // backend waits for its thread to stop, so it would hang if the thread kept running
+        drop(watcher(&dir));
+        dir.write("file.txt", "text");
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{metadata, read_dir};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::Change;
+
+/// Interval between the scans of the folders, in milliseconds.
+const POLL_INTERVAL: u64 = 1000;
+
+/// Modification times of the folder entries.
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+#[derive(Debug)]
+pub struct Backend {
+    folders: Arc<Mutex<BTreeSet<PathBuf>>>
+}
+
+impl Backend {
+    /// Starts the thread that scans the folders and compares them with the previous scan.
+    pub fn new(changes: Sender<Change>) -> Backend {
+        let folders: Arc<Mutex<BTreeSet<PathBuf>>> = Arc::new(Mutex::new(BTreeSet::new()));
+        let watched = folders.clone();
+        thread::spawn(move || {
+            let mut snapshots: BTreeMap<PathBuf, Snapshot> = BTreeMap::new();
+            // thread is stopped when the backend is dropped
+            while Arc::strong_count(&watched) > 1 {
+                thread::sleep(Duration::from_millis(POLL_INTERVAL));
+                let folders = watched.lock().unwrap().clone();
+                let mut scanned = BTreeMap::new();
+                for folder in folders {
+                    let snapshot = scan(&folder);
+                    if let Some(previous) = snapshots.get(&folder) {
+                        for change in compare(previous, &snapshot) {
+                            if changes.send(change).is_err() {
+                                return
+                            }
+                        }
+                    }
+                    scanned.insert(folder, snapshot);
+                }
+                snapshots = scanned;
+            }
+        });
+        Backend {
+            folders: folders
+        }
+    }
+
+    pub fn add(&mut self, path: &Path) {
+        self.folders.lock().unwrap().insert(path.to_path_buf());
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.folders.lock().unwrap().remove(path);
+    }
+}
+
+fn scan(folder: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    if let Ok(entries) = read_dir(folder) {
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let modified = metadata(&path).and_then(|metadata| metadata.modified()).ok();
+            snapshot.insert(path, modified);
+        }
+    }
+    snapshot
+}
+
+/// Returns the changes between two scans of the folder.
+fn compare(previous: &Snapshot, snapshot: &Snapshot) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (path, modified) in snapshot {
+        match previous.get(path) {
+            None => changes.push(Change::Entry(path.clone())),
+            Some(previous) if previous != modified => changes.push(Change::Contents(path.clone())),
+            Some(_) => {}
+        }
+    }
+    for path in previous.keys().filter(|path| !snapshot.contains_key(*path)) {
+        changes.push(Change::Entry(path.clone()));
+    }
+    changes
+}
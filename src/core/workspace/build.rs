@@ -1,6 +1,231 @@
-#[derive(Debug)]
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use core::settings::{Settings, SettingsObject, ParseSettings};
+use core::syntax::{Scope, ScopeSelectors};
+
+use self::ParseBuildSystemError::*;
+
+/// Build system defined by the project or by a `.sublime-build` file.
+#[derive(Debug, Clone, Default)]
 pub struct BuildSystem {
-    cmd: Vec<String>,
-    file_regex: Option<String>,
-    selector: Option<String>
+    pub name: Option<String>,
+    /// Program and its arguments, it's run without a shell
+    pub cmd: Vec<String>,
+    /// Command run by the shell instead of `cmd`
+    pub shell_cmd: Option<String>,
+    pub working_dir: Option<String>,
+    /// Regex that captures the file, the line, the column and the message of an error
+    pub file_regex: Option<String>,
+    /// Regex that captures the line of an error, when the file is given by a previous line
+    pub line_regex: Option<String>,
+    /// Scope selector of the files the build system is chosen for automatically
+    pub selector: Option<String>,
+    pub env: BTreeMap<String, String>,
+    /// `PATH` of the build process, variables of the environment are expanded
+    pub path: Option<String>,
+    /// Command that runs the build instead of `exec`, it gets the build system as arguments
+    pub target: Option<String>,
+    /// Alternative builds, they override the settings of this one
+    pub variants: Vec<BuildSystem>
+}
+
+#[derive(Debug)]
+pub enum ParseBuildSystemError {
+    BuildSystemIsNotObject,
+    CmdIsNotArray,
+    ArgumentIsNotString,
+    EnvIsNotObject,
+    VariantsIsNotArray,
+    FieldIsNotString(&'static str)
+}
+
+/// Removes the string field from the object.
+fn take_string(obj: &mut SettingsObject, key: &'static str)
+               -> Result<Option<String>, ParseBuildSystemError> {
+    match obj.remove(key) {
+        Some(Settings::String(s)) => Ok(Some(s)),
+        None => Ok(None),
+        Some(_) => Err(FieldIsNotString(key))
+    }
+}
+
+impl ParseSettings for BuildSystem {
+    type Error = ParseBuildSystemError;
+
+    fn parse_settings(settings: Settings) -> Result<BuildSystem, ParseBuildSystemError> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(BuildSystemIsNotObject)
+        };
+
+        let mut shell_cmd = try!(take_string(&mut obj, "shell_cmd"));
+
+        let cmd = match obj.remove("cmd") {
+            Some(Settings::Array(arr)) => {
+                let mut cmd = Vec::new();
+                for arg in arr {
+                    match arg {
+                        Settings::String(s) => cmd.push(s),
+                        _ => return Err(ArgumentIsNotString)
+                    }
+                }
+                cmd
+            },
+            // command given by a string is run by the shell
+            Some(Settings::String(s)) => {
+                shell_cmd = shell_cmd.or(Some(s));
+                Vec::new()
+            },
+            None => Vec::new(),
+            Some(_) => return Err(CmdIsNotArray)
+        };
+
+        let mut env = BTreeMap::new();
+        match obj.remove("env") {
+            Some(Settings::Object(vars)) => for (name, value) in vars {
+                match value {
+                    Settings::String(s) => {
+                        env.insert(name, s);
+                    },
+                    _ => return Err(FieldIsNotString("env"))
+                }
+            },
+            None => {},
+            Some(_) => return Err(EnvIsNotObject)
+        }
+
+        let variants = match obj.remove("variants") {
+            Some(Settings::Array(arr)) => try!(arr.into_iter().map(BuildSystem::parse_settings)
+                                                            .collect()),
+            None => Vec::new(),
+            Some(_) => return Err(VariantsIsNotArray)
+        };
+
+        Ok(BuildSystem {
+            name: try!(take_string(&mut obj, "name")),
+            cmd: cmd,
+            shell_cmd: shell_cmd,
+            working_dir: try!(take_string(&mut obj, "working_dir")),
+            file_regex: try!(take_string(&mut obj, "file_regex")),
+            line_regex: try!(take_string(&mut obj, "line_regex")),
+            selector: try!(take_string(&mut obj, "selector")),
+            env: env,
+            path: try!(take_string(&mut obj, "path")),
+            target: try!(take_string(&mut obj, "target")),
+            variants: variants
+        })
+    }
+}
+
+/// Replaces `$name` and `${name}` by the values of the variables, unknown variables
+/// are kept as they are.
+pub fn expand_variables<F>(text: &str, lookup: F) -> String where F: Fn(&str) -> Option<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+        let (name, len) = if rest.starts_with("${") {
+            match rest.find('}') {
+                Some(end) => (&rest[2..end], end + 1),
+                None => ("", 1)
+            }
+        } else {
+            let end = rest[1..].find(|c: char| !c.is_alphanumeric() && c != '_')
+                               .map_or(rest.len(), |end| end + 1);
+            (&rest[1..end], end)
+        };
+        match lookup(name) {
+            Some(ref value) if !name.is_empty() => result.push_str(value),
+            _ => result.push_str(&rest[..len])
+        }
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+impl BuildSystem {
+    /// Returns the build system with the settings overridden by the variant.
+    pub fn variant(&self, name: &str) -> Option<BuildSystem> {
+        let found = self.variants.iter().find(|variant| {
+            variant.name.as_ref().map_or(false, |variant_name| variant_name == name)
+        });
+        let variant = match found {
+            Some(variant) => variant,
+            None => return None
+        };
+        // command of the variant replaces the command of either kind
+        let (cmd, shell_cmd) = if variant.cmd.is_empty() && variant.shell_cmd.is_none() {
+            (self.cmd.clone(), self.shell_cmd.clone())
+        } else {
+            (variant.cmd.clone(), variant.shell_cmd.clone())
+        };
+        let mut env = self.env.clone();
+        env.extend(variant.env.clone());
+        Some(BuildSystem {
+            name: self.name.clone(),
+            cmd: cmd,
+            shell_cmd: shell_cmd,
+            working_dir: variant.working_dir.clone().or_else(|| self.working_dir.clone()),
+            file_regex: variant.file_regex.clone().or_else(|| self.file_regex.clone()),
+            line_regex: variant.line_regex.clone().or_else(|| self.line_regex.clone()),
+            selector: self.selector.clone(),
+            env: env,
+            path: variant.path.clone().or_else(|| self.path.clone()),
+            target: variant.target.clone().or_else(|| self.target.clone()),
+            variants: Vec::new()
+        })
+    }
+
+    /// Checks whether the build system is chosen automatically for the file of the syntax.
+    /// Selectors are matched against the scope of the whole file.
+    pub fn matches(&self, scope: &Scope) -> bool {
+        let selectors = match self.selector.as_ref().map(|s| ScopeSelectors::from_str(s)) {
+            Some(Ok(selectors)) => selectors,
+            _ => return false
+        };
+        selectors.selectors.iter().any(|selector| {
+            selector.path().len() == 1 && scope.starts_with(&selector.path()[0])
+        })
+    }
+
+    /// Expands the variables of the file and the project in the command
+    /// and the working folder.
+    pub fn expand<F>(&mut self, lookup: F) where F: Fn(&str) -> Option<String> {
+        for arg in &mut self.cmd {
+            *arg = expand_variables(arg, &lookup);
+        }
+        if let Some(ref mut shell_cmd) = self.shell_cmd {
+            *shell_cmd = expand_variables(shell_cmd, &lookup);
+        }
+        if let Some(ref mut working_dir) = self.working_dir {
+            *working_dir = expand_variables(working_dir, &lookup);
+        }
+    }
+
+    /// Returns the arguments of the `exec` command that runs the build.
+    pub fn exec_args(&self) -> SettingsObject {
+        let mut args = SettingsObject::new();
+        if !self.cmd.is_empty() {
+            let cmd = self.cmd.iter().cloned().map(Settings::String).collect();
+            args.insert("cmd".to_owned(), Settings::Array(cmd));
+        }
+        let fields = [("shell_cmd", &self.shell_cmd), ("working_dir", &self.working_dir),
+                      ("file_regex", &self.file_regex), ("line_regex", &self.line_regex),
+                      ("path", &self.path)];
+        for &(key, value) in &fields {
+            if let Some(ref value) = *value {
+                args.insert(key.to_owned(), Settings::String(value.clone()));
+            }
+        }
+        if !self.env.is_empty() {
+            let env = self.env.iter().map(|(name, value)| {
+                (name.clone(), Settings::String(value.clone()))
+            }).collect();
+            args.insert("env".to_owned(), Settings::Object(env));
+        }
+        args
+    }
 }
@@ -1,4 +1,7 @@
 mod project;
 mod build;
+mod session;
 
-pub use self::project::{Project, Folder};
+pub use self::project::{Project, Folder, ProjectFile};
+pub use self::build::{BuildSystem, ParseBuildSystemError, expand_variables};
+pub use self::session::{Session, SessionGroup, SessionView};
@@ -1,12 +1,17 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
-use std::path::{Path, PathBuf};
-use std::io::{BufReader, Error as IoError};
-use std::fs::{File, Metadata, read_dir, metadata, symlink_metadata};
+use std::env::current_dir;
+use std::path::{Path, PathBuf, Component};
+use std::io::{BufReader, Error as IoError, ErrorKind};
+use std::fs::{self, File, Metadata, read_dir, metadata, symlink_metadata};
 
 use glob::{Pattern, PatternError};
 
-use core::settings::{Settings, ParseSettings, SettingsError, read_json};
+use core::file::write_atomically;
+use core::settings::{Settings, SettingsObject, ParseSettings, SettingsError, read_json};
+
+use super::build::{BuildSystem, ParseBuildSystemError};
+use super::session::{Session, ParseSessionError};
 
 use self::ParseProjectError::*;
 
@@ -15,26 +20,40 @@ pub struct Project {
     pub path: Option<PathBuf>,
     pub folders: Vec<ProjectFolder>,
     pub settings: Option<Settings>,
-    // todo: add buils system here
+    pub build_systems: Vec<BuildSystem>,
+    /// Workspace file that keeps the session of the project without a file
+    pub default_workspace: Option<PathBuf>,
+    /// Project file as it was read, it's written back with the changed folders
+    data: SettingsObject
 }
 
 #[derive(Debug)]
 pub struct ProjectFolder {
     name: Option<String>,
+    /// Path resolved against the folder of the project file
     pub path: PathBuf,
     pub folder: Folder,
-    settings: ProjectFolderSettings
+    settings: ProjectFolderSettings,
+    /// Folder entry of the project file as it was read
+    data: SettingsObject
 }
 
+/// Glob patterns matched against the name or the whole path of the entry.
 #[derive(Debug, Default)]
-struct ExcludePatterns {
+struct Patterns {
     patterns: Vec<Pattern>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ProjectFolderSettings {
-    folder_exclude_patterns: ExcludePatterns,
-    file_exclude_patterns: ExcludePatterns,
+    folder_include_patterns: Patterns,
+    folder_exclude_patterns: Patterns,
+    file_include_patterns: Patterns,
+    file_exclude_patterns: Patterns,
+    /// Files that are listed, but not indexed for symbols
+    index_exclude_patterns: Patterns,
+    /// Files that are listed, but skipped by goto anything and find in files
+    binary_file_patterns: Patterns,
     follow_symlinks: bool
 }
 
@@ -44,10 +63,20 @@ pub struct Folder {
     pub files: Vec<String>
 }
 
+/// File found in the project folders.
+#[derive(Debug, Clone)]
+pub struct ProjectFile {
+    /// Path relative to the project folder, prefixed by the folder name
+    /// when the project has several folders
+    pub name: String,
+    pub path: PathBuf
+}
+
 #[derive(Debug)]
 pub enum ProjectError {
     Settings(SettingsError),
     Parse(ParseProjectError),
+    Session(ParseSessionError),
     Io(IoError)
 }
 
@@ -59,9 +88,11 @@ pub enum ParseProjectError {
     FolderNameIsNotString,
     FolderPathIsNotString,
     FollowSymlinksIsNotBoolean,
-    ExcludePatternsIsNotArray,
+    PatternsIsNotArray,
     PatternIsNotString,
-    IncorrectPattern(PatternError)
+    IncorrectPattern(PatternError),
+    BuildSystemsIsNotArray,
+    BuildSystem(ParseBuildSystemError)
 }
 
 impl From<PatternError> for ParseProjectError {
@@ -70,12 +101,24 @@ impl From<PatternError> for ParseProjectError {
     }
 }
 
+impl From<ParseBuildSystemError> for ParseProjectError {
+    fn from(error: ParseBuildSystemError) -> ParseProjectError {
+        ParseProjectError::BuildSystem(error)
+    }
+}
+
 impl From<ParseProjectError> for ProjectError {
     fn from(error: ParseProjectError) -> ProjectError {
         ProjectError::Parse(error)
     }
 }
 
+impl From<ParseSessionError> for ProjectError {
+    fn from(error: ParseSessionError) -> ProjectError {
+        ProjectError::Session(error)
+    }
+}
+
 impl From<SettingsError> for ProjectError {
     fn from(error: SettingsError) -> ProjectError {
         ProjectError::Settings(error)
@@ -88,13 +131,13 @@ impl From<IoError> for ProjectError {
     }
 }
 
-impl ParseSettings for ExcludePatterns {
+impl ParseSettings for Patterns {
     type Error = ParseProjectError;
 
-    fn parse_settings(settings: Settings) -> Result<ExcludePatterns, ParseProjectError> {
+    fn parse_settings(settings: Settings) -> Result<Patterns, ParseProjectError> {
         let arr = match settings {
             Settings::Array(arr) => arr,
-            _ => return Err(ExcludePatternsIsNotArray)
+            _ => return Err(PatternsIsNotArray)
         };
 
         let mut patterns = Vec::new();
@@ -106,12 +149,32 @@ impl ParseSettings for ExcludePatterns {
             }
         }
 
-        Ok(ExcludePatterns {
+        Ok(Patterns {
             patterns: patterns
         })
     }
 }
 
+impl Patterns {
+    fn parse(obj: &mut SettingsObject, key: &str) -> Result<Patterns, ParseProjectError> {
+        match obj.remove(key) {
+            Some(patterns) => Patterns::parse_settings(patterns),
+            None => Ok(Patterns::default())
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let name = path.file_name().map(|name| name.to_string_lossy());
+        self.patterns.iter().any(|p| {
+            p.matches_path(path) || name.as_ref().map_or(false, |name| p.matches(name))
+        })
+    }
+}
+
 impl ParseSettings for ProjectFolder {
     type Error = ParseProjectError;
 
@@ -120,6 +183,7 @@ impl ParseSettings for ProjectFolder {
             Settings::Object(obj) => obj,
             _ => return Err(ProjectFolderIsNotObject)
         };
+        let data = obj.clone();
 
         let name = match obj.remove("name") {
             Some(Settings::String(s)) => Some(s),
@@ -132,16 +196,6 @@ impl ParseSettings for ProjectFolder {
             _ => return Err(FolderPathIsNotString)
         };
 
-        let folder_exclude_patterns = match obj.remove("folder_exclude_patterns") {
-            Some(obj) => try!(ExcludePatterns::parse_settings(obj)),
-            None => ExcludePatterns::default()
-        };
-
-        let file_exclude_patterns = match obj.remove("file_exclude_patterns") {
-            Some(obj) => try!(ExcludePatterns::parse_settings(obj)),
-            None => ExcludePatterns::default()
-        };
-
         let follow_symlinks = match obj.remove("follow_symlinks") {
             Some(Settings::Boolean(v)) => v,
             None => false,
@@ -149,16 +203,21 @@ impl ParseSettings for ProjectFolder {
         };
 
         let settings = ProjectFolderSettings {
-            follow_symlinks: follow_symlinks,
-            file_exclude_patterns: file_exclude_patterns,
-            folder_exclude_patterns: folder_exclude_patterns
+            folder_include_patterns: try!(Patterns::parse(&mut obj, "folder_include_patterns")),
+            folder_exclude_patterns: try!(Patterns::parse(&mut obj, "folder_exclude_patterns")),
+            file_include_patterns: try!(Patterns::parse(&mut obj, "file_include_patterns")),
+            file_exclude_patterns: try!(Patterns::parse(&mut obj, "file_exclude_patterns")),
+            index_exclude_patterns: try!(Patterns::parse(&mut obj, "index_exclude_patterns")),
+            binary_file_patterns: try!(Patterns::parse(&mut obj, "binary_file_patterns")),
+            follow_symlinks: follow_symlinks
         };
 
         Ok(ProjectFolder {
             name: name,
             path: path,
             folder: Folder::default(),
-            settings: settings
+            settings: settings,
+            data: data
         })
     }
 }
@@ -171,6 +230,7 @@ impl ParseSettings for Project {
             Settings::Object(obj) => obj,
             _ => return Err(ProjectIsNotObject)
         };
+        let data = obj.clone();
 
         let folders_arr = match obj.remove("folders") {
             Some(Settings::Array(arr)) => arr,
@@ -180,10 +240,20 @@ impl ParseSettings for Project {
         let folders = try!(folders_arr.into_iter().map(ProjectFolder::parse_settings).collect());
         let settings = obj.remove("settings");
 
+        let build_systems = match obj.remove("build_systems") {
+            Some(Settings::Array(arr)) => try!(arr.into_iter().map(BuildSystem::parse_settings)
+                                                            .collect()),
+            None => Vec::new(),
+            Some(_) => return Err(BuildSystemsIsNotArray)
+        };
+
         Ok(Project {
             path: None,
             folders: folders,
-            settings: settings
+            settings: settings,
+            build_systems: build_systems,
+            default_workspace: None,
+            data: data
         })
     }
 }
@@ -198,30 +268,37 @@ impl ProjectFolderSettings {
     }
 
     fn file_matched(&self, path: &Path) -> bool {
-        !self.file_exclude_patterns.patterns.iter().any(|p| p.matches_path(path))
+        (self.file_include_patterns.is_empty() || self.file_include_patterns.matches(path)) &&
+            !self.file_exclude_patterns.matches(path)
     }
 
     fn folder_matched(&self, path: &Path) -> bool {
-        !self.folder_exclude_patterns.patterns.iter().any(|p| p.matches_path(path))
+        (self.folder_include_patterns.is_empty() || self.folder_include_patterns.matches(path)) &&
+            !self.folder_exclude_patterns.matches(path)
     }
 }
 
 impl Folder {
-    fn walk(path: &Path, settings: &ProjectFolderSettings) -> Result<Folder, IoError> {
+    /// Reads the entries of the folder, nested folders are left empty. Entries removed
+    /// while the folder is read and dangling symlinks are skipped.
+    fn read(path: &Path, settings: &ProjectFolderSettings) -> Result<Folder, IoError> {
         let mut files = Vec::new();
         let mut folders = BTreeMap::new();
         for entry in try!(read_dir(path)) {
             let entry = try!(entry);
             let path = entry.path();
-            let metadata = try!(settings.metadata(&path));
+            let metadata = match settings.metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(ref err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err)
+            };
             let name = entry.file_name().to_string_lossy().into_owned();
             if metadata.is_file() {
                 if settings.file_matched(&path) {
                     files.push(name);
                 }
             } else if metadata.is_dir() && settings.folder_matched(&path) {
-                let folder = try!(Folder::walk(&path, settings));
-                folders.insert(name, folder);
+                folders.insert(name, Folder::default());
             }
         }
         Ok(Folder {
@@ -229,9 +306,81 @@ impl Folder {
             folders: folders
         })
     }
+
+    fn walk(path: &Path, settings: &ProjectFolderSettings) -> Result<Folder, IoError> {
+        let mut folder = try!(Folder::read(path, settings));
+        for (name, nested) in &mut folder.folders {
+            *nested = try!(Folder::walk(&path.join(name), settings));
+        }
+        Ok(folder)
+    }
+
+    /// Reads the entries again. Nested folders that remain keep their entries,
+    /// only the new ones are walked.
+    fn update(&mut self, path: &Path, settings: &ProjectFolderSettings) -> Result<(), IoError> {
+        let mut folder = try!(Folder::read(path, settings));
+        for (name, nested) in &mut folder.folders {
+            *nested = match self.folders.remove(name) {
+                Some(previous) => previous,
+                None => try!(Folder::walk(&path.join(name), settings))
+            };
+        }
+        *self = folder;
+        Ok(())
+    }
+
+    fn collect_folders(&self, path: &Path, paths: &mut Vec<PathBuf>) {
+        paths.push(path.to_path_buf());
+        for (name, folder) in &self.folders {
+            folder.collect_folders(&path.join(name), paths);
+        }
+    }
+
+    fn collect_files(&self, name: &str, path: &Path, files: &mut Vec<ProjectFile>) {
+        let join = |file: &str| if name.is_empty() {
+            file.to_owned()
+        } else {
+            format!("{}/{}", name, file)
+        };
+        for (folder_name, folder) in &self.folders {
+            folder.collect_files(&join(folder_name), &path.join(folder_name), files);
+        }
+        let mut names: Vec<&String> = self.files.iter().collect();
+        names.sort();
+        for file in names {
+            files.push(ProjectFile {
+                name: join(file),
+                path: path.join(file)
+            });
+        }
+    }
+
+    /// Returns the nested folder by the path relative to this folder.
+    fn folder_mut(&mut self, path: &Path) -> Option<&mut Folder> {
+        let mut folder = self;
+        for component in path.components() {
+            let name = component.as_os_str().to_string_lossy();
+            folder = match folder.folders.get_mut(&name[..]) {
+                Some(folder) => folder,
+                None => return None
+            };
+        }
+        Some(folder)
+    }
 }
 
 impl ProjectFolder {
+    fn new(path: PathBuf, data: SettingsObject) -> Result<ProjectFolder, IoError> {
+        let settings = ProjectFolderSettings::default();
+        Ok(ProjectFolder {
+            name: None,
+            folder: try!(Folder::walk(&path, &settings)),
+            path: path,
+            settings: settings,
+            data: data
+        })
+    }
+
     pub fn name(&self) -> Cow<str> {
         match self.name {
             Some(ref s) => Cow::Borrowed(s),
@@ -249,23 +398,393 @@ impl Project {
             path: None,
             folders: Vec::new(),
             settings: None,
+            build_systems: Vec::new(),
+            default_workspace: None,
+            data: SettingsObject::new()
         }
     }
 
+    /// Reads the project file, relative folder paths are resolved against its folder.
     pub fn open(path: PathBuf) -> Result<Project, ProjectError> {
         let file = try!(File::open(&path));
         let reader = BufReader::new(file);
         let settings = try!(read_json(reader));
         let mut project = try!(Project::parse_settings(settings));
+        // folders are absolute, like the paths of the files opened from the command line
+        let dir = resolve_path(&try!(current_dir()), project_dir(&path));
+        for pf in &mut project.folders {
+            pf.path = resolve_path(&dir, &pf.path);
+        }
         project.path = Some(path);
         try!(project.walk());
         Ok(project)
     }
 
+    /// Writes the project file with the current folders, the file is replaced as a whole.
+    /// Project without a file isn't saved.
+    pub fn save(&self) -> Result<(), ProjectError> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(())
+        };
+        let mut data = self.data.clone();
+        let folders = self.folders.iter().map(|pf| Settings::Object(pf.data.clone())).collect();
+        data.insert("folders".to_owned(), Settings::Array(folders));
+        let text = format!("{}\n", Settings::Object(data).pretty());
+        try!(write_atomically(path, text.as_bytes()));
+        Ok(())
+    }
+
+    /// Writes the project to another file. Relative folder paths are changed to stay
+    /// relative to the new location.
+    pub fn save_as(&mut self, path: PathBuf) -> Result<(), ProjectError> {
+        for pf in &mut self.folders {
+            let relative = match pf.data.get("path") {
+                Some(&Settings::String(ref s)) => Path::new(s).is_relative(),
+                _ => false
+            };
+            if relative {
+                pf.data.insert("path".to_owned(), path_setting(Some(project_dir(&path)), &pf.path));
+            }
+        }
+        self.path = Some(path);
+        self.save()
+    }
+
+    /// Returns the path of the `.sublime-workspace` file that keeps the session,
+    /// it's next to the project file. Project without a file uses the default workspace.
+    pub fn workspace_path(&self) -> Option<PathBuf> {
+        match self.path {
+            Some(ref path) => Some(path.with_extension("sublime-workspace")),
+            None => self.default_workspace.clone()
+        }
+    }
+
+    /// Reads the session saved on the last exit. Returns None if there is no workspace file.
+    pub fn read_session(&self) -> Result<Option<Session>, ProjectError> {
+        let path = match self.workspace_path() {
+            Some(path) => path,
+            None => return Ok(None)
+        };
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(ProjectError::Io(err))
+        };
+        let settings = try!(read_json(BufReader::new(file)));
+        Ok(Some(try!(Session::parse_settings(settings))))
+    }
+
+    /// Writes the session to the workspace file, the file is replaced as a whole, so
+    /// the previous session is kept if writing fails. Folder of the default workspace is
+    /// created when it's missing. Session isn't saved without a workspace file.
+    pub fn write_session(&self, session: &Session) -> Result<(), ProjectError> {
+        let path = match self.workspace_path() {
+            Some(path) => path,
+            None => return Ok(())
+        };
+        if self.path.is_none() {
+            if let Some(parent) = path.parent() {
+                try!(fs::create_dir_all(parent));
+            }
+        }
+        let text = format!("{}\n", session.to_settings().pretty());
+        try!(write_atomically(&path, text.as_bytes()));
+        Ok(())
+    }
+
+    /// Adds the folder to the project, it's written relative to the project file
+    /// when it's inside its folder.
+    pub fn add_folder(&mut self, path: &Path) -> Result<(), ProjectError> {
+        if self.folders.iter().any(|pf| pf.path == path) {
+            return Ok(())
+        }
+        let mut data = SettingsObject::new();
+        let setting = path_setting(self.path.as_ref().map(|path| project_dir(path)), path);
+        data.insert("path".to_owned(), setting);
+        let folder = try!(ProjectFolder::new(path.to_path_buf(), data));
+        self.folders.push(folder);
+        Ok(())
+    }
+
     pub fn walk(&mut self) -> Result<(), ProjectError> {
         for pf in &mut self.folders {
             pf.folder = try!(Folder::walk(&pf.path, &pf.settings));
         }
         Ok(())
     }
+
+    /// Walks the folder again after its entries were changed. Folders outside of the project
+    /// and excluded ones are ignored.
+    pub fn refresh(&mut self, path: &Path) -> Result<(), ProjectError> {
+        for pf in &mut self.folders {
+            let relative = match path.strip_prefix(&pf.path) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue
+            };
+            if let Some(folder) = pf.folder.folder_mut(&relative) {
+                *folder = try!(Folder::walk(path, &pf.settings));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the entries of the folder after they were changed, without walking
+    /// the nested folders again.
+    pub fn update_folder(&mut self, path: &Path) -> Result<(), ProjectError> {
+        for pf in &mut self.folders {
+            let relative = match path.strip_prefix(&pf.path) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue
+            };
+            if let Some(folder) = pf.folder.folder_mut(&relative) {
+                try!(folder.update(path, &pf.settings));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns paths of the project folders and all their not excluded nested folders.
+    pub fn folder_paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for pf in &self.folders {
+            pf.folder.collect_folders(&pf.path, &mut paths);
+        }
+        paths
+    }
+
+    /// Removes the folder from the project, files of the folder are kept.
+    pub fn remove_folder(&mut self, path: &Path) {
+        self.folders.retain(|pf| pf.path != path);
+    }
+
+    /// Returns not excluded files of the project folders accepted by the filter.
+    fn files<F>(&self, filter: F) -> Vec<ProjectFile>
+        where F: Fn(&ProjectFolderSettings, &Path) -> bool
+    {
+        let mut files = Vec::new();
+        for pf in &self.folders {
+            let name = if self.folders.len() > 1 { pf.name().into_owned() } else { String::new() };
+            let mut folder_files = Vec::new();
+            pf.folder.collect_files(&name, &pf.path, &mut folder_files);
+            folder_files.retain(|file| filter(&pf.settings, &file.path));
+            files.extend(folder_files);
+        }
+        files
+    }
+
+    /// Returns files of the project folders, except binary ones.
+    pub fn text_files(&self) -> Vec<ProjectFile> {
+        self.files(|settings, path| !settings.binary_file_patterns.matches(path))
+    }
+
+    /// Returns files of the project folders that are indexed for symbols.
+    pub fn index_files(&self) -> Vec<ProjectFile> {
+        self.files(|settings, path| {
+            !settings.binary_file_patterns.matches(path) &&
+                !settings.index_exclude_patterns.matches(path)
+        })
+    }
+}
+
+/// Returns the folder of the project file, it's empty for a file in the current folder.
+fn project_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+/// Joins the path to the base and removes `.` and `..` components, the file system
+/// isn't accessed.
+fn resolve_path(base: &Path, path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => match resolved.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    resolved.pop();
+                },
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {},
+                _ => resolved.push(".."),
+            },
+            component => resolved.push(component.as_os_str())
+        }
+    }
+    if resolved.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        resolved
+    }
+}
+
+/// Returns the folder path as it's written to the project file: relative to the folder
+/// of the project file if it's inside of it, absolute otherwise.
+fn path_setting(dir: Option<&Path>, path: &Path) -> Settings {
+    let cwd = current_dir().unwrap_or(PathBuf::from("/"));
+    let absolute = resolve_path(&cwd, path);
+    let relative = dir.and_then(|dir| {
+        absolute.strip_prefix(&resolve_path(&cwd, dir)).ok().map(|relative| {
+            if relative.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                relative.to_path_buf()
+            }
+        })
+    });
+    Settings::String(relative.unwrap_or(absolute).to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use glob::Pattern;
+
+    use core::settings::{ParseSettings, read_json};
+    use core::temp_dir::TempDir;
+    use core::workspace::Session;
+
+    use super::{Folder, Patterns, Project, ProjectFolder};
+
+    fn project_folder(dir: &TempDir, settings: &str) -> ProjectFolder {
+        let json = format!(r#"{{"path": {:?}, {}}}"#, dir.path(), settings);
+        let mut pf = ProjectFolder::parse_settings(read_json(json.as_bytes()).unwrap()).unwrap();
+        pf.folder = Folder::walk(&pf.path, &pf.settings).unwrap();
+        pf
+    }
+
+    fn names(folder: &Folder) -> (Vec<&str>, Vec<&str>) {
+        let mut files: Vec<&str> = folder.files.iter().map(|name| &name[..]).collect();
+        files.sort();
+        (folder.folders.keys().map(|name| &name[..]).collect(), files)
+    }
+
+    #[test]
+    fn exclude_patterns() {
+        let dir = TempDir::new("project-exclude");
+        dir.create_dir("src");
+        dir.create_dir("target/debug");
+        dir.write("src/main.rs", "");
+        dir.write("src/main.rs.orig", "");
+        dir.write("Cargo.toml", "");
+        let pf = project_folder(&dir, r#""folder_exclude_patterns": ["target"],
+                                         "file_exclude_patterns": ["*.orig"]"#);
+        assert_eq!(names(&pf.folder), (vec!["src"], vec!["Cargo.toml"]));
+        assert_eq!(names(&pf.folder.folders["src"]), (vec![], vec!["main.rs"]));
+    }
+
+    #[test]
+    fn include_patterns() {
+        let dir = TempDir::new("project-include");
+        dir.create_dir("src");
+        dir.create_dir("docs");
+        dir.write("src/main.rs", "");
+        dir.write("src/notes.txt", "");
+        let pf = project_folder(&dir, r#""folder_include_patterns": ["src"],
+                                         "file_include_patterns": ["*.rs"]"#);
+        assert_eq!(names(&pf.folder), (vec!["src"], vec![]));
+        assert_eq!(names(&pf.folder.folders["src"]), (vec![], vec!["main.rs"]));
+    }
+
+    #[test]
+    fn update() {
+        let dir = TempDir::new("project-update");
+        dir.create_dir("kept");
+        dir.create_dir("removed");
+        dir.write("kept/file.rs", "");
+        dir.write("removed.rs", "");
+        let mut pf = project_folder(&dir, r#""folder_exclude_patterns": ["excluded"]"#);
+
+        // entries of the kept folder aren't read again, so the new one isn't there yet
+        dir.write("kept/new.rs", "");
+        dir.create_dir("added/nested");
+        dir.write("added/nested/file.rs", "");
+        dir.create_dir("excluded");
+        dir.write("added.rs", "");
+        fs::remove_dir(dir.join("removed")).unwrap();
+        fs::remove_file(dir.join("removed.rs")).unwrap();
+        pf.folder.update(&pf.path, &pf.settings).unwrap();
+
+        assert_eq!(names(&pf.folder), (vec!["added", "kept"], vec!["added.rs"]));
+        assert_eq!(names(&pf.folder.folders["kept"]), (vec![], vec!["file.rs"]));
+        let added = &pf.folder.folders["added"];
+        assert_eq!(names(&added.folders["nested"]), (vec![], vec!["file.rs"]));
+
+        let kept = dir.join("kept");
+        pf.folder.folder_mut(Path::new("kept")).unwrap().update(&kept, &pf.settings).unwrap();
+        assert_eq!(names(&pf.folder.folders["kept"]), (vec![], vec!["file.rs", "new.rs"]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("project-symlinks");
+        dir.write("file.rs", "");
+        symlink(dir.join("file.rs"), dir.join("link.rs")).unwrap();
+        symlink(dir.join("missing.rs"), dir.join("dangling.rs")).unwrap();
+        let pf = project_folder(&dir, r#""follow_symlinks": true"#);
+        assert_eq!(names(&pf.folder), (vec![], vec!["file.rs", "link.rs"]));
+    }
+
+    #[test]
+    fn save_and_files() {
+        let dir = TempDir::new("project-save");
+        dir.create_dir("src");
+        dir.write("src/main.rs", "");
+        dir.write("image.png", "");
+        let mut project = Project::new();
+        project.add_folder(dir.path()).unwrap();
+        project.save_as(dir.join("test.sublime-project")).unwrap();
+        // only the project file is added, the temporary one is renamed
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+
+        let mut project = Project::open(dir.join("test.sublime-project")).unwrap();
+        assert_eq!(project.folder_paths(), vec![dir.path().to_path_buf(), dir.join("src")]);
+        project.folders[0].settings.binary_file_patterns = Patterns {
+            patterns: vec![Pattern::new("*.png").unwrap()]
+        };
+        let names: Vec<String> = project.text_files().into_iter().map(|file| file.name).collect();
+        assert_eq!(names, vec!["src/main.rs", "test.sublime-project"]);
+    }
+
+    #[test]
+    fn write_session() {
+        let dir = TempDir::new("project-session");
+        let mut project = Project::new();
+        project.path = Some(dir.join("test.sublime-project"));
+        dir.write("test.sublime-workspace", "{}");
+        let session = Session {
+            find_history: vec!["pattern".to_owned()],
+            ..Session::default()
+        };
+        project.write_session(&session).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let session = project.read_session().unwrap().unwrap();
+        assert_eq!(session.find_history, vec!["pattern"]);
+    }
+
+    #[test]
+    fn default_workspace() {
+        let dir = TempDir::new("project-default-workspace");
+        let mut project = Project::new();
+        project.write_session(&Session::default()).unwrap();
+        assert!(project.read_session().unwrap().is_none());
+
+        let workspace = dir.join("sublimate/Session.sublime-workspace");
+        project.default_workspace = Some(workspace.clone());
+        let session = Session {
+            find_history: vec!["pattern".to_owned()],
+            ..Session::default()
+        };
+        project.write_session(&session).unwrap();
+        assert!(workspace.is_file());
+        let session = project.read_session().unwrap().unwrap();
+        assert_eq!(session.find_history, vec!["pattern"]);
+
+        // the project file has its own workspace
+        project.path = Some(dir.join("test.sublime-project"));
+        assert!(project.read_session().unwrap().is_none());
+    }
 }
@@ -0,0 +1,245 @@
+use std::path::PathBuf;
+
+use core::layout::{Layout, ParseLayoutError};
+use core::region::{Point, Region};
+use core::settings::{Settings, SettingsArray, SettingsObject, ParseSettings};
+
+use self::ParseSessionError::*;
+
+/// State of the window that is restored on the next start: open files, layout
+/// and find history. It's stored in the `.sublime-workspace` file next to the project.
+#[derive(Debug, Default)]
+pub struct Session {
+    pub layout: Option<Layout>,
+    pub active_group: usize,
+    pub groups: Vec<SessionGroup>,
+    /// Searched patterns, the recent one is the last
+    pub find_history: Vec<String>,
+    /// Folders collapsed in the sidebar, others are expanded
+    pub closed_folders: Vec<PathBuf>
+}
+
+#[derive(Debug, Default)]
+pub struct SessionGroup {
+    pub views: Vec<SessionView>,
+    pub active: usize
+}
+
+#[derive(Debug, Default)]
+pub struct SessionView {
+    pub file: Option<PathBuf>,
+    /// Unsaved text of the buffer, it's kept on exit when `hot_exit` is enabled
+    pub contents: Option<String>,
+    pub selection: Vec<Region>,
+    /// Horizontal offset and the first visible line
+    pub viewport: (usize, usize)
+}
+
+#[derive(Debug)]
+pub enum ParseSessionError {
+    SessionIsNotObject,
+    GroupsIsNotArray,
+    GroupIsNotObject,
+    ViewsIsNotArray,
+    ViewIsNotObject,
+    FileIsNotString,
+    ContentsIsNotString,
+    SelectionIsNotArray,
+    IncorrectRegion,
+    IncorrectViewport,
+    IndexIsNotNumber,
+    ListIsNotArray,
+    ListItemIsNotString,
+    Layout(ParseLayoutError)
+}
+
+impl From<ParseLayoutError> for ParseSessionError {
+    fn from(error: ParseLayoutError) -> ParseSessionError {
+        ParseSessionError::Layout(error)
+    }
+}
+
+/// Parses an array of numbers, like the region or the viewport.
+fn parse_numbers(settings: Settings) -> Option<Vec<usize>> {
+    match settings {
+        Settings::Array(arr) => arr.iter().map(|n| n.as_u64().map(|n| n as usize)).collect(),
+        _ => None
+    }
+}
+
+fn parse_index(obj: &mut SettingsObject, key: &str) -> Result<usize, ParseSessionError> {
+    match obj.remove(key) {
+        Some(settings) => settings.as_u64().map(|n| n as usize).ok_or(IndexIsNotNumber),
+        None => Ok(0)
+    }
+}
+
+fn parse_strings(settings: Option<Settings>) -> Result<Vec<String>, ParseSessionError> {
+    let arr = match settings {
+        Some(Settings::Array(arr)) => arr,
+        None => return Ok(Vec::new()),
+        Some(_) => return Err(ListIsNotArray)
+    };
+    let mut strings = Vec::new();
+    for item in arr {
+        match item {
+            Settings::String(s) => strings.push(s),
+            _ => return Err(ListItemIsNotString)
+        }
+    }
+    Ok(strings)
+}
+
+fn numbers(numbers: &[usize]) -> Settings {
+    Settings::Array(numbers.iter().map(|&n| Settings::U64(n as u64)).collect())
+}
+
+impl ParseSettings for SessionView {
+    type Error = ParseSessionError;
+
+    fn parse_settings(settings: Settings) -> Result<SessionView, ParseSessionError> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(ViewIsNotObject)
+        };
+
+        let file = match obj.remove("file") {
+            Some(Settings::String(s)) => Some(PathBuf::from(s)),
+            None => None,
+            Some(_) => return Err(FileIsNotString)
+        };
+
+        let contents = match obj.remove("contents") {
+            Some(Settings::String(s)) => Some(s),
+            None => None,
+            Some(_) => return Err(ContentsIsNotString)
+        };
+
+        let mut selection = Vec::new();
+        match obj.remove("selection") {
+            Some(Settings::Array(arr)) => for region in arr {
+                match parse_numbers(region) {
+                    Some(ref n) if n.len() == 4 => {
+                        selection.push(Region::new(Point::new(n[0], n[1]), Point::new(n[2], n[3])));
+                    },
+                    _ => return Err(IncorrectRegion)
+                }
+            },
+            None => {},
+            Some(_) => return Err(SelectionIsNotArray)
+        }
+
+        let viewport = match obj.remove("viewport").map(parse_numbers) {
+            Some(Some(ref n)) if n.len() == 2 => (n[0], n[1]),
+            None => (0, 0),
+            Some(_) => return Err(IncorrectViewport)
+        };
+
+        Ok(SessionView {
+            file: file,
+            contents: contents,
+            selection: selection,
+            viewport: viewport
+        })
+    }
+}
+
+impl ParseSettings for SessionGroup {
+    type Error = ParseSessionError;
+
+    fn parse_settings(settings: Settings) -> Result<SessionGroup, ParseSessionError> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(GroupIsNotObject)
+        };
+
+        let views = match obj.remove("views") {
+            Some(Settings::Array(arr)) => try!(arr.into_iter().map(SessionView::parse_settings)
+                                                            .collect()),
+            _ => return Err(ViewsIsNotArray)
+        };
+
+        Ok(SessionGroup {
+            views: views,
+            active: try!(parse_index(&mut obj, "active"))
+        })
+    }
+}
+
+impl ParseSettings for Session {
+    type Error = ParseSessionError;
+
+    fn parse_settings(settings: Settings) -> Result<Session, ParseSessionError> {
+        let mut obj = match settings {
+            Settings::Object(obj) => obj,
+            _ => return Err(SessionIsNotObject)
+        };
+
+        let layout = match obj.remove("layout") {
+            Some(layout) => Some(try!(Layout::parse_settings(layout))),
+            None => None
+        };
+
+        let groups = match obj.remove("groups") {
+            Some(Settings::Array(arr)) => try!(arr.into_iter().map(SessionGroup::parse_settings)
+                                                            .collect()),
+            None => Vec::new(),
+            Some(_) => return Err(GroupsIsNotArray)
+        };
+
+        let closed_folders = try!(parse_strings(obj.remove("closed_folders")));
+
+        Ok(Session {
+            layout: layout,
+            active_group: try!(parse_index(&mut obj, "active_group")),
+            groups: groups,
+            find_history: try!(parse_strings(obj.remove("find_history"))),
+            closed_folders: closed_folders.into_iter().map(PathBuf::from).collect()
+        })
+    }
+}
+
+impl SessionView {
+    fn to_settings(&self) -> Settings {
+        let mut obj = SettingsObject::new();
+        if let Some(ref file) = self.file {
+            obj.insert("file".to_owned(), Settings::String(file.to_string_lossy().into_owned()));
+        }
+        if let Some(ref contents) = self.contents {
+            obj.insert("contents".to_owned(), Settings::String(contents.clone()));
+        }
+        let selection = self.selection.iter().map(|region| {
+            numbers(&[region.a.line, region.a.column, region.b.line, region.b.column])
+        }).collect();
+        obj.insert("selection".to_owned(), Settings::Array(selection));
+        obj.insert("viewport".to_owned(), numbers(&[self.viewport.0, self.viewport.1]));
+        Settings::Object(obj)
+    }
+}
+
+impl Session {
+    /// Returns the session as it's written to the workspace file.
+    pub fn to_settings(&self) -> Settings {
+        let mut obj = SettingsObject::new();
+        if let Some(ref layout) = self.layout {
+            obj.insert("layout".to_owned(), layout.to_settings());
+        }
+        obj.insert("active_group".to_owned(), Settings::U64(self.active_group as u64));
+        let groups = self.groups.iter().map(|group| {
+            let mut obj = SettingsObject::new();
+            let views = group.views.iter().map(SessionView::to_settings).collect();
+            obj.insert("views".to_owned(), Settings::Array(views));
+            obj.insert("active".to_owned(), Settings::U64(group.active as u64));
+            Settings::Object(obj)
+        }).collect();
+        obj.insert("groups".to_owned(), Settings::Array(groups));
+        let history: SettingsArray = self.find_history.iter().cloned().map(Settings::String)
+                                                                       .collect();
+        obj.insert("find_history".to_owned(), Settings::Array(history));
+        let closed = self.closed_folders.iter().map(|path| {
+            Settings::String(path.to_string_lossy().into_owned())
+        }).collect();
+        obj.insert("closed_folders".to_owned(), Settings::Array(closed));
+        Settings::Object(obj)
+    }
+}
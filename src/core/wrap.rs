@@ -0,0 +1,80 @@
+use unicode_width::{UnicodeWidthStr, UnicodeWidthChar};
+
+/// Visual row of a soft wrapped line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Row {
+    /// Byte offset of the first character of the row.
+    pub begin: usize,
+    /// Byte offset after the last character of the row.
+    pub end: usize,
+    /// Amount of cells the row is shifted to the right.
+    pub indent: usize
+}
+
+impl Row {
+    /// Checks whether the caret at the given column is displayed in this row.
+    /// Caret at the row end belongs to the next row, unless this row is the last one.
+    pub fn contains(&self, column: usize, line_len: usize) -> bool {
+        column >= self.begin && (column < self.end || self.end == line_len)
+    }
+
+    /// Returns cell offset of the column inside the row, including indent.
+    pub fn column_x(&self, text: &str, column: usize) -> usize {
+        self.indent + text[self.begin..column].width()
+    }
+
+    /// Returns the column closest to the given cell offset.
+    pub fn x_column(&self, text: &str, x: usize) -> usize {
+        let mut width = self.indent;
+        let mut last = self.begin;
+        for (i, c) in text[self.begin..self.end].char_indices() {
+            if width >= x {
+                return self.begin + i;
+            }
+            width += c.width().unwrap_or(0);
+            last = self.begin + i;
+        }
+        if self.end == text.len() { self.end } else { last }
+    }
+}
+
+/// Splits the line into visual rows not wider than `width` cells. Lines are broken after
+/// whitespace when it's possible, words longer than the row are broken at any character.
+/// Zero width disables wrapping.
+pub fn wrap_line(text: &str, width: usize, indent_subsequent_lines: bool) -> Vec<Row> {
+    if width == 0 {
+        return vec![Row { begin: 0, end: text.len(), indent: 0 }];
+    }
+    let indent = if indent_subsequent_lines {
+        let leading = text.len() - text.trim_left().len();
+        let indent = text[..leading].width();
+        if indent < width / 2 { indent } else { 0 }
+    } else {
+        0
+    };
+    let mut rows = Vec::new();
+    let mut row = Row { begin: 0, end: 0, indent: 0 };
+    let mut row_width = 0;
+    let mut last_break = 0;
+    for (i, c) in text.char_indices() {
+        let c_width = c.width().unwrap_or(0);
+        // trailing whitespace is allowed to overflow the row
+        if row_width + c_width + row.indent > width && i > row.begin && !c.is_whitespace() {
+            let end = if last_break > row.begin { last_break } else { i };
+            rows.push(Row { begin: row.begin, end: end, indent: row.indent });
+            row = Row { begin: end, end: 0, indent: indent };
+            row_width = text[end..i].width();
+        }
+        row_width += c_width;
+        if c.is_whitespace() {
+            last_break = i + c.len_utf8();
+        }
+    }
+    rows.push(Row { begin: row.begin, end: text.len(), indent: row.indent });
+    rows
+}
+
+/// Returns index of the row that displays the caret at the given column.
+pub fn row_index(rows: &[Row], column: usize, line_len: usize) -> usize {
+    rows.iter().position(|row| row.contains(column, line_len)).unwrap_or(rows.len() - 1)
+}
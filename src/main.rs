@@ -25,15 +25,153 @@ extern crate plist;
 
 extern crate clap;
 
+use std::env;
+#[cfg(unix)]
+use std::fs::File;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::raw::c_int;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::process;
+
 use ncurses::*;
 
 use core::Core;
-use core::bindings::Key;
+use core::workspace::Project;
+use core::history::History;
 use view::window::Window;
 use toolkit::*;
+use toolkit::events::{Event, EventReader};
 use view::theme::PALETTE;
 use clap::{App, Arg};
 
+#[cfg(unix)]
+extern "C" {
+    fn dup2(old_fd: c_int, new_fd: c_int) -> c_int;
+}
+
+/// Folders of the default packages that come with the editor, relative to the folder
+/// of the executable: packages placed next to it, installed to `share` of its prefix,
+/// or the packages of the source tree the executable is built in by cargo.
+const BUNDLED_PACKAGES: [&'static str; 3] = [
+    "packages",
+    "../share/sublimate/packages",
+    "../../packages"
+];
+
+/// Returns the folder of the bundled packages, the first one that exists.
+fn bundled_packages() -> PathBuf {
+    let exe_dir = env::current_exe().ok()
+        .and_then(|exe| exe.canonicalize().ok())
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .unwrap_or_default();
+    let mut candidates: Vec<PathBuf> = BUNDLED_PACKAGES.iter().map(|path| exe_dir.join(path))
+                                                             .collect();
+    match candidates.iter().position(|path| path.is_dir()) {
+        Some(index) => candidates.swap_remove(index),
+        // missing packages are reported when they're read
+        None => candidates.swap_remove(0)
+    }
+}
+
+/// Returns the folder of the user packages, `$XDG_CONFIG_HOME/sublimate/packages`,
+/// followed by the bundled packages.
+fn default_packages() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::home_dir().map(|home| home.join(".config"))
+    };
+    if let Some(config) = config {
+        paths.push(config.join("sublimate").join("packages"));
+    }
+    paths.push(bundled_packages());
+    paths
+}
+
+/// Returns the folder of the user data, `$XDG_DATA_HOME/sublimate`.
+fn data_dir() -> Option<PathBuf> {
+    let data = match env::var_os("XDG_DATA_HOME") {
+        Some(ref dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::home_dir().map(|home| home.join(".local").join("share"))
+    };
+    data.map(|data| data.join("sublimate"))
+}
+
+/// Returns the file of the recent files, folders and projects,
+/// `$XDG_DATA_HOME/sublimate/history.json`.
+fn history_path() -> Option<PathBuf> {
+    data_dir().map(|data| data.join("history.json"))
+}
+
+/// Returns the workspace file that keeps the session when no project file is given,
+/// `$XDG_DATA_HOME/sublimate/Session.sublime-workspace`.
+fn default_workspace_path() -> Option<PathBuf> {
+    data_dir().map(|data| data.join("Session.sublime-workspace"))
+}
+
+/// Splits the encoded position `file:line:column` or `file:line`, line and column are
+/// counted from 1. Existing file is opened as is, even if its name has a colon.
+fn parse_position(arg: &str) -> (PathBuf, Option<(usize, usize)>) {
+    let path = PathBuf::from(arg);
+    if path.exists() {
+        return (path, None)
+    }
+    let mut file = arg;
+    let mut numbers = Vec::new();
+    while numbers.len() < 2 {
+        let index = match file.rfind(':') {
+            Some(index) => index,
+            None => break
+        };
+        match file[index + 1..].parse::<usize>() {
+            Ok(number) => numbers.insert(0, number),
+            Err(_) => break
+        }
+        file = &file[..index];
+    }
+    match numbers.len() {
+        0 => (path, None),
+        1 => (PathBuf::from(file), Some((numbers[0], 1))),
+        _ => (PathBuf::from(file), Some((numbers[0], numbers[1])))
+    }
+}
+
+/// Prints the error before the terminal is taken by the editor and exits.
+fn exit_with_error(message: String) -> ! {
+    let _ = writeln!(io::stderr(), "{}", message);
+    process::exit(1)
+}
+
+/// Makes the terminal the standard input again, after the piped text was read.
+#[cfg(unix)]
+fn reopen_terminal() -> io::Result<()> {
+    let tty = try!(File::open("/dev/tty"));
+    if unsafe { dup2(tty.as_raw_fd(), 0) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(())
+}
+
+/// Console input isn't read from the standard input, so there is nothing to reopen.
+#[cfg(not(unix))]
+fn reopen_terminal() -> io::Result<()> {
+    Ok(())
+}
+
+/// Reads the text piped to the editor. Keys are read from the terminal afterwards.
+fn read_stdin() -> io::Result<String> {
+    let mut text = String::new();
+    try!(io::stdin().read_to_string(&mut text));
+    try!(reopen_terminal());
+    if text.ends_with('\n') {
+        text.pop();
+    }
+    Ok(text)
+}
+
 fn main() {
 
     let matches = App::new("sublimate")
@@ -43,27 +181,85 @@ fn main() {
                     .arg(Arg::with_name("packages")
                         .long("packages")
                         .value_name("PACKAGES PATH")
-                        .help("Sets packages path")
-                        .takes_value(true)
-                        .required(true))
-                    .arg(Arg::with_name("file")
+                        .help("Sets packages path, instead of the user and the bundled packages")
+                        .takes_value(true))
+                    .arg(Arg::with_name("paths")
                         .index(1)
-                        .value_name("FILE PATH")
-                        .help("Sets a path to viewing file")
-                        .takes_value(true)
-                        .required(true))
+                        .multiple(true)
+                        .value_name("PATH")
+                        .help("Files to open, as file:line:column to go to the position, \
+                               folders to add to the project, or - to read from stdin"))
                     .arg(Arg::with_name("project")
                         .long("project")
                         .value_name("PROJECT PATH")
                         .help("Sets path to sublime project")
-                        .takes_value(true)
-                        .required(true))
+                        .takes_value(true))
+                    .arg(Arg::with_name("new_window")
+                        .short("n")
+                        .long("new-window")
+                        .help("Opens a new window instead of restoring the last session"))
                     .get_matches();
 
-    let core = Core::load(
-        matches.value_of("packages").unwrap(),
-        matches.value_of("file").unwrap(),
-        matches.value_of("project").unwrap());
+    let packages = match matches.value_of("packages") {
+        Some(path) => vec![PathBuf::from(path)],
+        None => default_packages()
+    };
+
+    let mut project = match matches.value_of("project") {
+        Some(path) => match Project::open(PathBuf::from(path)) {
+            Ok(project) => project,
+            Err(err) => exit_with_error(format!("Can't open project {}: {:?}", path, err))
+        },
+        None => Project::new()
+    };
+    project.default_workspace = default_workspace_path();
+
+    let mut files = Vec::new();
+    let mut stdin_text = None;
+    for arg in matches.values_of("paths").into_iter().flat_map(|values| values) {
+        if arg == "-" {
+            match read_stdin() {
+                Ok(text) => stdin_text = Some(text),
+                Err(err) => exit_with_error(format!("Can't read stdin: {:?}", err))
+            }
+            continue
+        }
+        let (path, position) = parse_position(arg);
+        // paths are compared with the paths of the project folders, so they're absolute
+        let path = path.canonicalize().unwrap_or(path);
+        if path.is_dir() {
+            // folders make the implicit project, or are added to the given one
+            if let Err(err) = project.add_folder(&path) {
+                exit_with_error(format!("Can't open folder {}: {:?}", arg, err));
+            }
+        } else {
+            files.push((path, position));
+        }
+    }
+
+    // broken history isn't overwritten
+    let history = match history_path().map(History::open) {
+        Some(Ok(history)) => history,
+        Some(Err(err)) => {
+            warn!("Can't read history: {:?}", err);
+            History::new()
+        },
+        None => History::new()
+    };
+
+    let mut core = Core::load(packages, project, history, !matches.is_present("new_window"));
+    for (path, position) in files {
+        core.open_file(path, false);
+        if let Some((line, column)) = position {
+            core.goto_position(line, column);
+        }
+    }
+    if let Some(text) = stdin_text {
+        core.new_file(&text);
+    }
+    if core.active_view().is_none() {
+        core.new_file("");
+    }
 
     setlocale(LcCategory::all, "en_US.utf-8");
 
@@ -81,17 +277,22 @@ fn main() {
         init_pair(i as i16, fg.to_term(), bg.to_term());
     }
 
+    enable_mouse();
+
     let mut window = Window::new(core);
+    let mut events = EventReader::new();
     window.render(Canvas::screen());
-    loop {
-        if let Some(key) = Key::from_keycode(getch()) {
-            if key == Key::Enter {
-                break;
-            }
-            window.on_keypress(Canvas::screen(), key);
+    while !window.is_closed() {
+        match events.read() {
+            Some(Event::Key(hotkey)) => window.on_keypress(Canvas::screen(), hotkey),
+            Some(Event::Mouse(event)) => window.on_mouse(Canvas::screen(), event),
+            Some(Event::Idle) => window.on_idle(Canvas::screen()),
+            None => {}
         }
     }
 
+    disable_mouse();
+
 
     // println!("{:?}", window);
 
@@ -108,8 +309,6 @@ fn main() {
 
     // Start ncurses.
 
-    // mousemask(ALL_MOUSE_EVENTS as u64, None);
-
     // attron(COLOR_PAIR(150));
 
     // mvaddstr(10, 10, "hello world");
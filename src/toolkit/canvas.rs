@@ -1,5 +1,7 @@
 use std::cmp::min;
 
+use unicode_width::UnicodeWidthChar;
+
 use toolkit::style::Style;
 use toolkit::draw::*;
 
@@ -119,6 +121,30 @@ impl Canvas {
         }
     }
 
+    /// Returns part of canvas, bounds are relative to the left top point.
+    pub fn region(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> Canvas {
+        Canvas {
+            win: self.win,
+            x0: self.x0,
+            y0: self.y0,
+            x1: min(self.x2, self.x1 + x1),
+            y1: min(self.y2, self.y1 + y1),
+            x2: min(self.x2, self.x1 + x2),
+            y2: min(self.y2, self.y1 + y2)
+        }
+    }
+
+    /// Checks whether the screen position is inside the canvas.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x0 + self.x1 && x < self.x0 + self.x2 &&
+        y >= self.y0 + self.y1 && y < self.y0 + self.y2
+    }
+
+    /// Converts the screen position to the position relative to the left top point.
+    pub fn relative(&self, x: usize, y: usize) -> (usize, usize) {
+        (x.saturating_sub(self.x0 + self.x1), y.saturating_sub(self.y0 + self.y1))
+    }
+
     pub fn cut_left(&mut self, mut width: usize) -> Canvas {
         width = min(width, self.width());
         let left = self.left(width);
@@ -139,6 +165,13 @@ impl Canvas {
         self.y1 += height;
         top
     }
+
+    pub fn cut_bottom(&mut self, mut height: usize) -> Canvas {
+        height = min(height, self.height());
+        let bottom = self.bottom(height);
+        self.y2 -= height;
+        bottom
+    }
 }
 
 impl Drawing for Canvas {
@@ -155,12 +188,29 @@ impl Drawing for Canvas {
     }
 
     fn char(&self, c: char, y: usize, x: usize) {
+        if y >= self.height() || x + c.width().unwrap_or(0) > self.width() {
+            return
+        }
         let mut s = String::new();
         s.push(c);
         mvwaddstr(self.win, (self.y1 + y) as i32, (self.x1 + x) as i32, &s);
     }
 
     fn text(&self, s: &str, y: usize, x: usize) {
-        mvwaddstr(self.win, (self.y1 + y) as i32, (self.x1 + x) as i32, s);
+        if y >= self.height() || x >= self.width() {
+            return
+        }
+        // clip text by the right border, otherwise curses wraps it to the next line
+        let available = self.width() - x;
+        let mut width = 0;
+        let mut end = s.len();
+        for (i, c) in s.char_indices() {
+            width += c.width().unwrap_or(0);
+            if width > available {
+                end = i;
+                break
+            }
+        }
+        mvwaddstr(self.win, (self.y1 + y) as i32, (self.x1 + x) as i32, &s[..end]);
     }
 }
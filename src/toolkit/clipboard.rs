@@ -0,0 +1,13 @@
+use std::io::{stdout, Write};
+
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+/// Copies the text to the system clipboard with the OSC 52 escape sequence, it's supported
+/// by most of terminal emulators and works over ssh.
+pub fn set_clipboard(text: &str) {
+    let sequence = format!("\x1b]52;c;{}\x07", text.as_bytes().to_base64(STANDARD));
+    let mut stdout = stdout();
+    if let Err(err) = stdout.write_all(sequence.as_bytes()).and_then(|_| stdout.flush()) {
+        error!("Can't copy to the clipboard: {:?}", err);
+    }
+}
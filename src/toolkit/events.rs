@@ -0,0 +1,42 @@
+use ncurses::{getch, timeout, KEY_MOUSE, ERR};
+
+use core::bindings::Hotkey;
+use toolkit::keys::read_hotkey;
+use toolkit::mouse::{MouseReader, MouseEvent};
+
+/// How long the input is waited for before the idle event, in milliseconds.
+const IDLE_TIMEOUT: i32 = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    Key(Hotkey),
+    Mouse(MouseEvent),
+    /// There was no input for a while
+    Idle
+}
+
+#[derive(Debug, Default)]
+pub struct EventReader {
+    mouse: MouseReader
+}
+
+impl EventReader {
+    pub fn new() -> EventReader {
+        EventReader::default()
+    }
+
+    /// Waits for the next input event, or returns the idle event after the timeout.
+    /// Returns `None` for unsupported input.
+    pub fn read(&mut self) -> Option<Event> {
+        // reading of the escape sequences changes the delay, so it's set each time
+        timeout(IDLE_TIMEOUT);
+        let keycode = getch();
+        if keycode == ERR {
+            Some(Event::Idle)
+        } else if keycode == KEY_MOUSE {
+            self.mouse.read_event().map(Event::Mouse)
+        } else {
+            read_hotkey(keycode).map(Event::Key)
+        }
+    }
+}
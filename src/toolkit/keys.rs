@@ -1,4 +1,6 @@
-use core::bindings::Key;
+use ncurses::{getch, nodelay, stdscr};
+
+use core::bindings::{Key, Hotkey, Modifiers, MODIFIER_CTRL, MODIFIER_ALT, MODIFIER_SHIFT};
 
 // Ctrl AZ: 1 - 26
 // az 97 122
@@ -8,6 +10,7 @@ use core::bindings::Key;
 // 09       48 57
 
 // f1-f12   265 276
+// shift f10 286
 
 // left     260
 // right    261
@@ -37,15 +40,38 @@ use core::bindings::Key;
 // tab 9
 // shift tab 353
 
+// home     262
+// end      360
+// delete   330
+// insert   331
+// pagedown 338
+// pageup   339
+
 impl Key {
     pub fn from_keycode(keycode: i32) -> Option<Key> {
         match keycode {
-            10  => Some(Key::Enter),
+            9   => Some(Key::Tab),
+            10 | 13 => Some(Key::Enter),
+            27  => Some(Key::Escape),
+            127 | 263 => Some(Key::Backspace),
+            32...126 => Some(Key::Char(keycode as u8 as char)),
 
             260 => Some(Key::Left),
             261 => Some(Key::Right),
             259 => Some(Key::Up),
             258 => Some(Key::Down),
+
+            262 => Some(Key::Home),
+            360 => Some(Key::End),
+            330 => Some(Key::Delete),
+            331 => Some(Key::Insert),
+            338 => Some(Key::PageDown),
+            339 => Some(Key::PageUp),
+            343 => Some(Key::KeypadEnter),
+
+            265...276 => Some(Key::F((keycode - 264) as u8)),
+            // shift+f10 opens the context menu, like in desktop applications
+            286 => Some(Key::ContextMenu),
             _ => {
 
                 // TODO: warning
@@ -54,3 +80,80 @@ impl Key {
         }
     }
 }
+
+/// Returns digit for the character typed with shift on US keyboard layout.
+fn unshift_char(c: char) -> Option<char> {
+    let r = match c {
+        '!' => '1',
+        '@' => '2',
+        '#' => '3',
+        '$' => '4',
+        '%' => '5',
+        '^' => '6',
+        '&' => '7',
+        '*' => '8',
+        '(' => '9',
+        ')' => '0',
+        'A'...'Z' => return c.to_lowercase().next(),
+        _ => return None
+    };
+    Some(r)
+}
+
+impl Hotkey {
+    pub fn from_keycode(keycode: i32) -> Option<Hotkey> {
+        let (key, modifiers) = match keycode {
+            0 => (Key::Char(' '), MODIFIER_CTRL),
+            9 | 10 | 13 => return Key::from_keycode(keycode).map(Hotkey::from),
+            1...26 => (Key::Char((b'a' + keycode as u8 - 1) as char), MODIFIER_CTRL),
+
+            353 => (Key::Tab, MODIFIER_SHIFT),
+
+            393 => (Key::Left, MODIFIER_SHIFT),
+            402 => (Key::Right, MODIFIER_SHIFT),
+            337 => (Key::Up, MODIFIER_SHIFT),
+            336 => (Key::Down, MODIFIER_SHIFT),
+
+            541 => (Key::Left, MODIFIER_CTRL),
+            556 => (Key::Right, MODIFIER_CTRL),
+            562 => (Key::Up, MODIFIER_CTRL),
+            521 => (Key::Down, MODIFIER_CTRL),
+
+            542 => (Key::Left, MODIFIER_CTRL | MODIFIER_SHIFT),
+            557 => (Key::Right, MODIFIER_CTRL | MODIFIER_SHIFT),
+            563 => (Key::Up, MODIFIER_CTRL | MODIFIER_SHIFT),
+            522 => (Key::Down, MODIFIER_CTRL | MODIFIER_SHIFT),
+
+            _ => return Key::from_keycode(keycode).map(Hotkey::from)
+        };
+        Some(Hotkey::new(key, modifiers))
+    }
+
+    /// Converts keycode that follows escape prefix, which terminals send for alt.
+    pub fn from_alt_keycode(keycode: i32) -> Option<Hotkey> {
+        if 32 <= keycode && keycode <= 126 {
+            if let Some(c) = unshift_char(keycode as u8 as char) {
+                return Some(Hotkey::new(Key::Char(c), MODIFIER_ALT | MODIFIER_SHIFT));
+            }
+        }
+        Hotkey::from_keycode(keycode).map(|hotkey| {
+            Hotkey::new(hotkey.key(), hotkey.modifiers() | MODIFIER_ALT)
+        })
+    }
+}
+
+/// Converts the keycode returned by `getch` into hotkey. Escape starts an alt sequence,
+/// so the next pending keycode is read in that case.
+pub fn read_hotkey(keycode: i32) -> Option<Hotkey> {
+    if keycode != 27 {
+        return Hotkey::from_keycode(keycode);
+    }
+    nodelay(stdscr, true);
+    let next = getch();
+    nodelay(stdscr, false);
+    if next == -1 {
+        Some(Hotkey::new(Key::Escape, Modifiers::empty()))
+    } else {
+        Hotkey::from_alt_keycode(next)
+    }
+}
@@ -4,7 +4,9 @@ mod canvas;
 pub mod views;
 pub mod keys;
 pub mod mouse;
+pub mod events;
 pub mod palette;
+pub mod clipboard;
 
 pub use toolkit::canvas::*;
 pub use toolkit::draw::*;
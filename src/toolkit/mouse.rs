@@ -1,8 +1,137 @@
+use std::io::{stdout, Write};
+use std::time::{Duration, Instant};
 
-// enum MouseButton {
+use ncurses::*;
 
-// }
+use core::bindings::{MouseButton, Modifiers, MODIFIER_CTRL, MODIFIER_ALT, MODIFIER_SHIFT};
 
-// struct MouseEvent {
+/// Presses closer in time than this are counted as double and triple clicks.
+const MULTI_CLICK_INTERVAL: u64 = 500;
 
-// }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    Press,
+    Release,
+    /// Mouse moved while the button is pressed
+    Drag
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MouseEvent {
+    pub action: MouseAction,
+    pub button: MouseButton,
+    /// Number of sequential clicks, e.g. 2 for double click
+    pub count: u8,
+    pub modifiers: Modifiers,
+    /// Screen position of the event
+    pub x: usize,
+    pub y: usize
+}
+
+/// Decodes curses mouse events, keeping track of the pressed button and click count.
+#[derive(Debug, Default)]
+pub struct MouseReader {
+    pressed: Option<MouseButton>,
+    last_press: Option<(MouseButton, usize, usize, Instant)>,
+    count: u8
+}
+
+/// Enables reporting of mouse presses, releases and drags.
+pub fn enable_mouse() {
+    mousemask((ALL_MOUSE_EVENTS | REPORT_MOUSE_POSITION) as mmask_t, None);
+    // clicks are counted by MouseReader, so curses shouldn't wait for them
+    mouseinterval(0);
+    // ask terminal to report motion while a button is pressed
+    print!("\x1b[?1002h");
+    stdout().flush().unwrap();
+}
+
+pub fn disable_mouse() {
+    print!("\x1b[?1002l");
+    stdout().flush().unwrap();
+}
+
+fn has(bstate: mmask_t, mask: i32) -> bool {
+    bstate & mask as mmask_t != 0
+}
+
+impl MouseReader {
+    pub fn new() -> MouseReader {
+        MouseReader::default()
+    }
+
+    /// Reads the pending event after `getch` returned `KEY_MOUSE`.
+    pub fn read_event(&mut self) -> Option<MouseEvent> {
+        let mut event = MEVENT { id: 0, x: 0, y: 0, z: 0, bstate: 0 };
+        if getmouse(&mut event) != OK {
+            return None;
+        }
+        let (x, y, bstate) = (event.x as usize, event.y as usize, event.bstate);
+
+        let mut modifiers = Modifiers::empty();
+        if has(bstate, BUTTON_CTRL) {
+            modifiers.insert(MODIFIER_CTRL);
+        }
+        if has(bstate, BUTTON_ALT) {
+            modifiers.insert(MODIFIER_ALT);
+        }
+        if has(bstate, BUTTON_SHIFT) {
+            modifiers.insert(MODIFIER_SHIFT);
+        }
+
+        // Curses numbers buttons from left to right, sublime numbers the right button second
+        let (action, button) = if has(bstate, BUTTON4_PRESSED) {
+            (MouseAction::Press, MouseButton::ScrollUp)
+        } else if has(bstate, BUTTON5_PRESSED) {
+            (MouseAction::Press, MouseButton::ScrollDown)
+        } else if has(bstate, BUTTON1_PRESSED) {
+            (MouseAction::Press, MouseButton::Button(1))
+        } else if has(bstate, BUTTON3_PRESSED) {
+            (MouseAction::Press, MouseButton::Button(2))
+        } else if has(bstate, BUTTON2_PRESSED) {
+            (MouseAction::Press, MouseButton::Button(3))
+        } else if has(bstate, BUTTON1_RELEASED | BUTTON2_RELEASED | BUTTON3_RELEASED) {
+            match self.pressed.take() {
+                Some(button) => (MouseAction::Release, button),
+                None => return None
+            }
+        } else if has(bstate, REPORT_MOUSE_POSITION) {
+            match self.pressed {
+                Some(button) => (MouseAction::Drag, button),
+                None => return None
+            }
+        } else {
+            return None;
+        };
+
+        let count = match (action, button) {
+            (MouseAction::Press, MouseButton::Button(_)) => {
+                self.pressed = Some(button);
+                self.count_press(button, x, y)
+            },
+            _ => 1
+        };
+
+        Some(MouseEvent {
+            action: action,
+            button: button,
+            count: count,
+            modifiers: modifiers,
+            x: x,
+            y: y
+        })
+    }
+
+    fn count_press(&mut self, button: MouseButton, x: usize, y: usize) -> u8 {
+        let now = Instant::now();
+        let interval = Duration::from_millis(MULTI_CLICK_INTERVAL);
+        self.count = match self.last_press {
+            Some((last_button, last_x, last_y, time))
+                if last_button == button && last_x == x && last_y == y &&
+                   now.duration_since(time) < interval => self.count % 3 + 1,
+            _ => 1
+        };
+        self.last_press = Some((button, x, y, now));
+        self.count
+    }
+}
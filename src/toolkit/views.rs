@@ -1,4 +1,5 @@
 use toolkit::canvas::Canvas;
+use toolkit::mouse::MouseEvent;
 use core::bindings::Key;
 
 pub trait Widget<'a> {
@@ -17,6 +18,12 @@ pub trait Widget<'a> {
         false
     }
 
+    /// Handles mouse event, its position is relative to the screen.
+    #[allow(unused_variables)]
+    fn on_mouse(&'a mut self, context: Self::Context, canvas: Canvas, event: MouseEvent) -> bool {
+        false
+    }
+
     #[allow(unused_variables)]
     fn focus(&mut self, context: Self::Context) {
     }
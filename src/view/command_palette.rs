@@ -0,0 +1,63 @@
+use core::Core;
+use core::bindings::Key;
+use core::command::Command;
+use core::command_list::CommandList;
+use core::settings::SettingsObject;
+
+use toolkit::*;
+
+use view::quick_panel::{QuickPanel, QuickPanelItem, QuickPanelView};
+
+/// Overlay that runs commands found by caption.
+#[derive(Debug)]
+pub struct CommandPalette {
+    panel: QuickPanel,
+    commands: Vec<Command>
+}
+
+impl CommandPalette {
+    pub fn new(core: &Core, list: CommandList) -> CommandPalette {
+        let mut items = Vec::new();
+        let mut commands = Vec::new();
+        for item in list {
+            items.push(QuickPanelItem {
+                detail: core.hotkeys.get_hotkeys(&item.command)
+                                    .map(|h| h.to_string())
+                                    .unwrap_or_default(),
+                caption: item.caption
+            });
+            commands.push(item.command);
+        }
+        CommandPalette {
+            panel: QuickPanel::new(items),
+            commands: commands
+        }
+    }
+}
+
+impl<'a> Widget<'a> for CommandPalette {
+    type Context = &'a Core;
+    type View = QuickPanelView<'a>;
+
+    fn view(&'a self, _: &'a Core) -> QuickPanelView<'a> {
+        self.panel.view(())
+    }
+
+    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        match key {
+            Key::Enter => {
+                // overlay is hidden first, so the command may show another one
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                if let Some(index) = self.panel.selected() {
+                    core.run_command(self.commands[index].clone());
+                }
+                true
+            },
+            Key::Escape => {
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                true
+            },
+            _ => self.panel.on_keypress((), canvas, key)
+        }
+    }
+}
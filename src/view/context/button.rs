@@ -1,5 +1,6 @@
 use core::Core;
 use core::command::Command;
+use core::bindings::Key;
 
 use toolkit::*;
 
@@ -52,6 +53,14 @@ impl<'a> Widget<'a> for Button {
             }
         }
     }
+
+    fn on_keypress(&'a mut self, (core, _): Self::Context, _: Canvas, key: Key) -> bool {
+        if key == Key::Enter {
+            core.run_command(self.command.clone());
+            return true
+        }
+        false
+    }
 }
 
 impl ButtonState {
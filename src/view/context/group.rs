@@ -68,6 +68,10 @@ impl<'a> Widget<'a> for Group {
         false
     }
 
+    fn on_mouse(&'a mut self, (core, _): Self::Context, canvas: Canvas, event: MouseEvent) -> bool {
+        self.is_opened && self.modal.on_mouse(core, canvas, event)
+    }
+
     fn focus(&mut self, (core, _): Self::Context) {
         self.is_opened = true;
         self.modal.focus(core);
@@ -55,6 +55,19 @@ impl<'a> Widget<'a> for ContextMenuItem {
         }
     }
 
+    fn on_mouse(&'a mut self, context: Self::Context, canvas: Canvas, event: MouseEvent) -> bool {
+        match *self {
+            ContextMenuItem::Group(ref mut group) => group.on_mouse(context, canvas, event),
+            _ => false
+        }
+    }
+
+    fn focus(&mut self, context: Self::Context) {
+        if let ContextMenuItem::Group(ref mut group) = *self {
+            group.focus(context);
+        }
+    }
+
     fn unfocus(&mut self, context: Self::Context) {
         match *self {
             ContextMenuItem::Button(ref mut button) => button.unfocus(context),
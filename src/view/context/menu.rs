@@ -1,5 +1,5 @@
 use core::Core;
-use core::bindings::Key;
+use core::bindings::{Key, MouseButton};
 use core::menu::Menu;
 
 use toolkit::*;
@@ -96,6 +96,41 @@ impl<'a> Widget<'a> for ContextMenu {
         false
     }
 
+    fn on_mouse(&mut self, core: &Core, canvas: Canvas, event: MouseEvent) -> bool {
+        let mut processed = false;
+        // opened submenu takes the event first
+        if let Some(ref mut item) = self.focused() {
+            if item.on_mouse((core, true), canvas, event) {
+                processed = true
+            }
+        }
+        if processed {
+            return true
+        }
+        if !canvas.contains(event.x, event.y) {
+            return false
+        }
+        if event.action != MouseAction::Press || event.button != MouseButton::Button(1) {
+            return true
+        }
+        let (_, index) = canvas.relative(event.x, event.y);
+        if index >= self.items.len() || !self.items[index].enabled((core, false)) {
+            return true
+        }
+        if self.focused != Some(index) {
+            self.unfocus(core);
+            self.focused = Some(index);
+        }
+        if let Some(ref mut item) = self.focused() {
+            match **item {
+                ContextMenuItem::Group(_) => item.focus((core, true)),
+                _ => { item.on_keypress((core, true), canvas, Key::Enter); }
+            }
+        }
+        self.view(core).render(canvas);
+        true
+    }
+
     fn focus(&mut self, core: &Core) {
         if self.focused == None {
             self.focus_next(core);
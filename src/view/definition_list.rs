@@ -0,0 +1,95 @@
+use core::Core;
+use core::bindings::Key;
+use core::command::Command;
+use core::index::Definition;
+use core::settings::{Settings, SettingsObject};
+
+use toolkit::*;
+
+use view::quick_panel::{QuickPanel, QuickPanelItem, QuickPanelView};
+
+/// Overlay that chooses one of the symbol definitions found in the project.
+/// The selected definition is previewed in the transient view.
+#[derive(Debug)]
+pub struct DefinitionList {
+    panel: QuickPanel,
+    definitions: Vec<Definition>,
+    /// Definition that was previewed last time
+    preview: Option<usize>
+}
+
+/// Returns command that opens the file at the definition.
+pub fn open_command(definition: &Definition, transient: bool) -> Command {
+    let point = definition.symbol.region.a;
+    let path = definition.file.path.to_string_lossy().into_owned();
+    let mut args = SettingsObject::new();
+    args.insert("file".to_owned(), Settings::String(path));
+    args.insert("transient".to_owned(), Settings::Boolean(transient));
+    args.insert("line".to_owned(), Settings::U64(point.line as u64 + 1));
+    args.insert("column".to_owned(), Settings::U64(point.column as u64 + 1));
+    Command::new("open_file", args)
+}
+
+impl DefinitionList {
+    pub fn new(core: &Core, definitions: Vec<Definition>) -> DefinitionList {
+        let items = definitions.iter().map(|definition| QuickPanelItem {
+            caption: definition.symbol.name.clone(),
+            detail: format!("{}:{}", definition.file.name, definition.symbol.region.a.line + 1)
+        }).collect();
+        let mut list = DefinitionList {
+            panel: QuickPanel::new(items),
+            definitions: definitions,
+            preview: None
+        };
+        list.preview(core);
+        list
+    }
+
+    /// Returns command that opens the file at the selected definition.
+    fn command(&self, transient: bool) -> Option<Command> {
+        self.panel.selected().map(|index| open_command(&self.definitions[index], transient))
+    }
+
+    fn preview(&mut self, core: &Core) {
+        let selected = self.panel.selected();
+        if selected.is_none() || selected == self.preview {
+            return
+        }
+        self.preview = selected;
+        if let Some(command) = self.command(true) {
+            core.run_command(command);
+        }
+    }
+}
+
+impl<'a> Widget<'a> for DefinitionList {
+    type Context = &'a Core;
+    type View = QuickPanelView<'a>;
+
+    fn view(&'a self, _: &'a Core) -> QuickPanelView<'a> {
+        self.panel.view(())
+    }
+
+    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        match key {
+            Key::Enter => {
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                if let Some(command) = self.command(false) {
+                    core.run_command(command);
+                }
+                true
+            },
+            Key::Escape => {
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                true
+            },
+            _ => {
+                if !self.panel.on_keypress((), canvas, key) {
+                    return false
+                }
+                self.preview(core);
+                true
+            }
+        }
+    }
+}
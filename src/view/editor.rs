@@ -1,11 +1,25 @@
-use unicode_width::UnicodeWidthStr;
+use std::cmp::{min, max};
+
+use unicode_width::{UnicodeWidthStr, UnicodeWidthChar};
 
 use toolkit::*;
 
 use core::Core;
 use core::view::{View as CoreView};
 use core::bindings::Key;
-use core::syntax::Highlighter;
+use core::diff::LineMarker;
+use core::region::Region;
+use core::search::Search;
+use core::syntax::{Highlighter, Style as SyntaxStyle, Color as SyntaxColor, WHITE, BLACK};
+use core::wrap::Row;
+
+/// Background of the search matches, when the theme doesn't define it.
+const FIND_HIGHLIGHT: SyntaxColor = SyntaxColor {r: 0xFF, g: 0xE7, b: 0x92, a: 0xFF};
+
+/// Colors of the changed lines markers, when the theme doesn't define them.
+const DIFF_ADDED: SyntaxColor = SyntaxColor {r: 0x6A, g: 0xB0, b: 0x5A, a: 0xFF};
+const DIFF_MODIFIED: SyntaxColor = SyntaxColor {r: 0xD8, g: 0xA6, b: 0x3C, a: 0xFF};
+const DIFF_DELETED: SyntaxColor = SyntaxColor {r: 0xD0, g: 0x50, b: 0x50, a: 0xFF};
 
 #[derive(Debug)]
 pub struct Editor {
@@ -16,7 +30,37 @@ pub struct Editor {
 pub struct EditorView<'a> {
     view: &'a CoreView,
     highlighter: &'a Highlighter,
-    palette: &'a ColorPalette
+    palette: &'a ColorPalette,
+    /// Search which matches are highlighted
+    search: Option<&'a Search>
+}
+
+fn to_term_color(color: SyntaxColor) -> Color {
+    Color::from_rgb256(color.r, color.g, color.b)
+}
+
+/// Skips `skip` cells from the beginning of the text. Returns the rest of the text
+/// and the amount of cells that wasn't skipped.
+fn skip_cells(text: &str, skip: usize) -> (&str, usize) {
+    let mut width = 0;
+    for (i, c) in text.char_indices() {
+        if width >= skip {
+            return (&text[i..], 0);
+        }
+        width += c.width().unwrap_or(0);
+    }
+    ("", skip - min(width, skip))
+}
+
+/// Returns byte range of the part of the region that is displayed in the row of the line.
+fn row_range(region: &Region, line: usize, text: &str, row: Row) -> Option<(usize, usize)> {
+    let (begin, end) = (region.begin(), region.end());
+    if line < begin.line || line > end.line {
+        return None
+    }
+    let from = max(if begin.line == line { begin.column } else { 0 }, row.begin);
+    let to = min(if end.line == line { end.column } else { text.len() }, row.end);
+    if from < to { Some((from, to)) } else { None }
 }
 
 impl Editor {
@@ -29,55 +73,210 @@ impl Editor {
 }
 
 impl<'a> Widget<'a> for Editor {
-    type Context = &'a Core;
+    type Context = (&'a Core, &'a CoreView, Option<&'a Search>);
     type View = EditorView<'a>;
 
-    fn view(&'a self, core: &'a Core) -> EditorView<'a> {
+    fn view(&'a self, (_, view, search): Self::Context) -> EditorView<'a> {
         EditorView {
-            view: &core.view,
+            view: view,
             highlighter: &self.highlighter,
-            palette: &self.palette
+            palette: &self.palette,
+            search: search
         }
     }
 
     #[allow(unused_variables)]
-    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+    fn on_keypress(&mut self, context: Self::Context, canvas: Canvas, key: Key) -> bool {
         false
     }
 
 }
 
+impl<'a> EditorView<'a> {
+    fn style(&self, foreground: SyntaxColor, background: SyntaxColor) -> Style {
+        Style {
+            colors: self.palette.color_pair(to_term_color(foreground), to_term_color(background)),
+            attrs: Attr::empty() // impl convert
+        }
+    }
+
+    fn selection_style(&self) -> Style {
+        let settings = self.highlighter.settings();
+        self.style(
+            settings.foreground.unwrap_or(WHITE),
+            settings.selection.unwrap_or(WHITE)
+        )
+    }
+
+    fn caret_style(&self) -> Style {
+        let settings = self.highlighter.settings();
+        self.style(
+            settings.background.unwrap_or(BLACK),
+            settings.caret.unwrap_or(WHITE)
+        )
+    }
+
+    fn find_highlight_style(&self) -> Style {
+        let settings = self.highlighter.settings();
+        self.style(
+            settings.find_highlight_foreground.unwrap_or(BLACK),
+            settings.find_highlight.unwrap_or(FIND_HIGHLIGHT)
+        )
+    }
+
+    fn gutter_style(&self, is_current: bool) -> Style {
+        let settings = self.highlighter.settings();
+        let default = self.highlighter.get_default();
+        let foreground = if is_current {
+            settings.foreground.unwrap_or(default.foreground)
+        } else {
+            settings.gutter_foreground.unwrap_or(default.foreground)
+        };
+        self.style(foreground, settings.gutter.unwrap_or(default.background))
+    }
+
+    fn marker_style(&self, marker: LineMarker) -> Style {
+        let settings = self.highlighter.settings();
+        let foreground = match marker {
+            LineMarker::Added => settings.line_diff_added.unwrap_or(DIFF_ADDED),
+            LineMarker::Modified => settings.line_diff_modified.unwrap_or(DIFF_MODIFIED),
+            LineMarker::Deleted => settings.line_diff_deleted.unwrap_or(DIFF_DELETED)
+        };
+        self.style(foreground, settings.gutter.unwrap_or(self.highlighter.get_default().background))
+    }
+
+    /// Draws line number, only the first row of the line is numbered. Changed lines
+    /// are marked at the left edge.
+    fn render_gutter(&self, canvas: Canvas, line: usize, is_first_row: bool) {
+        let is_current = self.view.selection.iter().any(|region| region.b.line == line);
+        canvas.style(self.gutter_style(is_current));
+        canvas.fill();
+        if self.view.preferences.line_numbers && is_first_row {
+            let number = (line + 1).to_string();
+            canvas.text(&number, 0, canvas.width().saturating_sub(number.len() + 1));
+        }
+        let marker = match self.view.buffer().diff().and_then(|diff| diff.marker(line)) {
+            Some(marker) => marker,
+            None => return
+        };
+        let c = match marker {
+            LineMarker::Deleted if is_first_row => '▁',
+            LineMarker::Deleted => return,
+            LineMarker::Added | LineMarker::Modified => '▎'
+        };
+        canvas.style(self.marker_style(marker));
+        canvas.char(c, 0, 0);
+    }
+
+    /// Draws part of the highlighted line displayed in the row, skipping `x` cells.
+    /// Returns canvas of the row text, that follows the indent.
+    fn render_row(&self, mut canvas: Canvas, segments: &[(SyntaxStyle, &str)],
+                  row: Row, x: usize) -> Canvas {
+        let default = self.highlighter.get_default();
+        canvas.style(self.style(default.foreground, default.background));
+        canvas.cut_left(row.indent).fill();
+        let text_canvas = canvas;
+        let mut skip = x;
+        let mut pos = 0;
+        for &(style, segment) in segments {
+            let (begin, end) = (pos, pos + segment.len());
+            pos = end;
+            if end <= row.begin || begin >= row.end {
+                continue
+            }
+            let segment = &segment[max(begin, row.begin) - begin..min(end, row.end) - begin];
+            let (segment, rest) = skip_cells(segment, skip);
+            skip = rest;
+            canvas.style(self.style(style.foreground, style.background));
+            canvas.cut_left(segment.width()).text(segment, 0, 0);
+        }
+        canvas.style(self.style(default.foreground, default.background));
+        canvas.fill();
+        text_canvas
+    }
+
+    /// Draws the text between the byte offsets in the style, skipping `x` cells of the row.
+    fn render_text(&self, canvas: Canvas, style: Style, text: &str, row: Row,
+                   (from, to): (usize, usize), x: usize) {
+        let from_x = text[row.begin..from].width();
+        let to_x = text[row.begin..to].width();
+        if to_x > x {
+            let (visible, _) = skip_cells(&text[from..to], x.saturating_sub(from_x));
+            canvas.style(style);
+            canvas.text(visible, 0, from_x.saturating_sub(x));
+        }
+    }
+
+    /// Draws search matches over the highlighted row.
+    fn render_matches(&self, canvas: Canvas, line: usize, text: &str, row: Row, x: usize,
+                      matches: &[Region]) {
+        for region in matches {
+            if let Some(range) = row_range(region, line, text, row) {
+                self.render_text(canvas, self.find_highlight_style(), text, row, range, x);
+            }
+        }
+    }
+
+    /// Draws selected regions and carets over the highlighted row.
+    fn render_selection(&self, canvas: Canvas, line: usize, text: &str, row: Row, x: usize) {
+        for region in &self.view.selection {
+            if let Some(range) = row_range(region, line, text, row) {
+                self.render_text(canvas, self.selection_style(), text, row, range, x);
+            }
+            if region.b.line == line && row.contains(region.b.column, text.len()) {
+                let column = region.b.column;
+                let caret_x = text[row.begin..column].width();
+                if caret_x >= x {
+                    let c = text[column..].chars().next().unwrap_or(' ');
+                    canvas.style(self.caret_style());
+                    canvas.char(c, 0, caret_x - x);
+                }
+            }
+        }
+    }
+}
+
 impl<'a> View for EditorView<'a> {
     fn width(&self) -> usize {
-        self.view.lines.iter().map(|line| line.text.width()).max().unwrap_or(0)
+        self.view.buffer().lines.iter().map(|line| line.text.width()).max().unwrap_or(0)
     }
 
     fn height(&self) -> usize {
-        self.view.lines.len()
+        self.view.buffer().lines.len()
     }
 
     fn render(&self, mut canvas: Canvas) {
-        for line in &self.view.lines {
-            let mut canvas = canvas.cut_top(1);
-            canvas.cut_left(2).fill();
-            for (style, text) in line.highlight(self.highlighter) {
-                let foreground = Color::from_rgb256(
-                    style.foreground.r,
-                    style.foreground.g,
-                    style.foreground.b
-                );
-                let background = Color::from_rgb256(
-                    style.background.r,
-                    style.background.g,
-                    style.background.b
-                );
-                canvas.style(Style {
-                    colors: self.palette.color_pair(foreground, background),
-                    attrs: Attr::empty() // impl convert
-                });
-                canvas.cut_left(text.width()).text(text, 0, 0);
+        let gutter_width = self.view.gutter_width();
+        self.view.set_viewport_extent(canvas.width().saturating_sub(gutter_width), canvas.height());
+        let (x, y) = self.view.viewport_position();
+        // horizontal scrolling makes no sense when lines are wrapped
+        let x = if self.view.wrap_width() > 0 { 0 } else { x };
+        let buffer = self.view.buffer();
+        // wrapped lines take several rows, so there are no more visible lines than rows
+        let matches = match self.search {
+            Some(search) => search.find_in_lines(&buffer, y, y + canvas.height()),
+            None => Vec::new()
+        };
+        let mut index = y;
+        while canvas.height() > 0 && index < buffer.lines.len() {
+            let line = &buffer.lines[index];
+            let segments: Vec<_> = line.highlight(self.highlighter).collect();
+            for (i, row) in self.view.rows(index).into_iter().enumerate() {
+                if canvas.height() == 0 {
+                    break
+                }
+                let mut canvas = canvas.cut_top(1);
+                self.render_gutter(canvas.cut_left(gutter_width), index, i == 0);
+                let canvas = self.render_row(canvas, &segments, row, x);
+                self.render_matches(canvas, index, &line.text, row, x, &matches);
+                self.render_selection(canvas, index, &line.text, row, x);
             }
-            canvas.fill();
+            index += 1;
         }
+        canvas.style(self.gutter_style(false));
+        canvas.cut_left(gutter_width).fill();
+        canvas.style(self.style(self.highlighter.get_default().foreground,
+                                self.highlighter.get_default().background));
+        canvas.fill();
     }
 }
@@ -0,0 +1,337 @@
+use unicode_width::UnicodeWidthStr;
+
+use core::bindings::Key;
+use core::find_in_files::Where;
+use core::region::{Point, Region};
+use core::search::{self, Search, SearchOptions};
+use core::view::View as CoreView;
+
+use toolkit::*;
+
+use view::theme::*;
+
+const FIND_LABEL: &'static str = " Find:    ";
+const REPLACE_LABEL: &'static str = " Replace: ";
+const WHERE_LABEL: &'static str = " Where:   ";
+
+/// Number of searched patterns kept in the history.
+const MAX_HISTORY: usize = 50;
+
+/// Input field of the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Pattern,
+    Location,
+    Replacement
+}
+
+/// Panel at the bottom of the window with the search pattern and options, and optionally
+/// the files to search and the replacement. They're kept while the panel is hidden
+/// to continue the search.
+#[derive(Debug)]
+pub struct FindPanel {
+    input: String,
+    location: String,
+    replacement: String,
+    /// Location is shown to find in files
+    has_location: bool,
+    /// Replacement is shown and can be edited
+    has_replacement: bool,
+    /// Field that has the input focus
+    focus: Field,
+    /// Question shown instead of the fields until it's answered by a command
+    confirmation: Option<String>,
+    pub options: SearchOptions,
+    /// Regions selected when the panel was shown, they're searched in selection
+    scope: Vec<Region>,
+    /// Point the incremental search starts from
+    origin: Point,
+    /// Incremental search goes backward from the origin
+    reverse: bool,
+    /// Searched patterns, the recent one is the last
+    history: Vec<String>,
+    /// Entry of the history shown in the pattern field, while it's browsed by up and down keys
+    history_index: Option<usize>
+}
+
+pub struct FindPanelView<'a> {
+    input: &'a str,
+    location: Option<&'a str>,
+    replacement: Option<&'a str>,
+    focus: Field,
+    confirmation: Option<&'a str>,
+    options: SearchOptions,
+    is_valid: bool,
+    is_location_valid: bool
+}
+
+impl FindPanel {
+    pub fn new() -> FindPanel {
+        FindPanel {
+            input: String::new(),
+            location: String::new(),
+            replacement: String::new(),
+            has_location: false,
+            has_replacement: false,
+            focus: Field::Pattern,
+            confirmation: None,
+            options: SearchOptions::default(),
+            scope: Vec::new(),
+            origin: Point::default(),
+            reverse: false,
+            history: Vec::new(),
+            history_index: None
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = history;
+        self.history_index = None;
+    }
+
+    /// Adds the pattern to the history after it's searched, repeated pattern is moved
+    /// to the end.
+    pub fn add_history(&mut self) {
+        self.history_index = None;
+        if self.input.is_empty() {
+            return
+        }
+        let input = self.input.clone();
+        self.history.retain(|pattern| *pattern != input);
+        self.history.push(input);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Shows the previous or the next pattern of the history in the pattern field.
+    fn browse_history(&mut self, forward: bool) {
+        let last = match self.history.len() {
+            0 => return,
+            len => len - 1
+        };
+        let index = match self.history_index {
+            None if !forward => last,
+            Some(index) if !forward => index.saturating_sub(1),
+            Some(index) if index < last => index + 1,
+            _ => return
+        };
+        self.history_index = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn set_input(&mut self, input: &str) {
+        self.input = input.to_owned();
+    }
+
+    /// Returns files to search as they're typed in the `Where:` field.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn clear_location(&mut self) {
+        self.location.clear();
+    }
+
+    /// Appends an item to the comma separated location.
+    pub fn add_location(&mut self, item: &str) {
+        if !self.location.trim().is_empty() {
+            self.location.push_str(", ");
+        }
+        self.location.push_str(item);
+    }
+
+    /// Returns the replacement format.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    /// Prepares the panel to search the view, or files for the `find_in_files` panel.
+    /// Text selected within a line becomes the pattern.
+    pub fn show(&mut self, panel: &str, view: Option<&CoreView>, reverse: bool) {
+        self.has_location = panel == "find_in_files";
+        self.has_replacement = panel == "replace" || panel == "find_in_files";
+        self.focus = Field::Pattern;
+        self.confirmation = None;
+        self.history_index = None;
+        let view = match view {
+            Some(view) => view,
+            None => return
+        };
+        self.scope = view.selection.clone();
+        self.reverse = reverse;
+        self.origin = match view.selection.last() {
+            Some(region) if reverse => region.end(),
+            Some(region) => region.begin(),
+            None => Point::default()
+        };
+        if let Some(region) = view.selection.last() {
+            let (begin, end) = (region.begin(), region.end());
+            if begin != end && begin.line == end.line && !self.options.in_selection {
+                let text = view.buffer().substr(begin, end);
+                self.input = if self.options.regex { search::escape(&text) } else { text };
+            }
+        }
+    }
+
+    /// Shows the question instead of the fields, until the panel is shown again.
+    pub fn confirm(&mut self, question: String) {
+        self.confirmation = Some(question);
+    }
+
+    /// Returns the point the incremental search starts from and whether it goes forward.
+    pub fn origin(&self) -> (Point, bool) {
+        (self.origin, !self.reverse)
+    }
+
+    /// Returns search for the input, or None if the input is empty or isn't a valid regex.
+    pub fn search(&self) -> Option<Search> {
+        if self.input.is_empty() {
+            return None
+        }
+        Search::new(&self.input, self.options, &self.scope).ok()
+    }
+
+    /// Returns shown fields in the order of the focus switching.
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field::Pattern];
+        if self.has_location {
+            fields.push(Field::Location);
+        }
+        if self.has_replacement {
+            fields.push(Field::Replacement);
+        }
+        fields
+    }
+}
+
+impl<'a> Widget<'a> for FindPanel {
+    type Context = ();
+    type View = FindPanelView<'a>;
+
+    fn view(&'a self, _: ()) -> FindPanelView<'a> {
+        FindPanelView {
+            input: &self.input,
+            location: if self.has_location { Some(&self.location) } else { None },
+            replacement: if self.has_replacement { Some(&self.replacement) } else { None },
+            focus: self.focus,
+            confirmation: self.confirmation.as_ref().map(|question| &question[..]),
+            options: self.options,
+            is_valid: self.input.is_empty() || self.search().is_some(),
+            is_location_valid: Where::parse(&self.location).is_ok()
+        }
+    }
+
+    fn on_keypress(&mut self, _: (), _: Canvas, key: Key) -> bool {
+        if self.confirmation.is_some() {
+            // the question is answered by the key bindings, the text isn't typed anywhere
+            return match key {
+                Key::Char(_) | Key::Backspace | Key::Tab => true,
+                _ => false
+            }
+        }
+        if key == Key::Tab {
+            // tab switches the input, it's never typed into the view
+            let fields = self.fields();
+            let index = fields.iter().position(|&field| field == self.focus).unwrap_or(0);
+            self.focus = fields[(index + 1) % fields.len()];
+            return true
+        }
+        if self.focus == Field::Pattern && (key == Key::Up || key == Key::Down) {
+            self.browse_history(key == Key::Down);
+            return true
+        }
+        let input = match self.focus {
+            Field::Pattern => &mut self.input,
+            Field::Location => &mut self.location,
+            Field::Replacement => &mut self.replacement
+        };
+        match key {
+            Key::Char(c) => input.push(c),
+            Key::Backspace => {
+                input.pop();
+            },
+            _ => return false
+        }
+        true
+    }
+}
+
+/// Draws the label and the input, the end of the input is displayed when it doesn't fit.
+pub fn render_input(mut canvas: Canvas, label: &str, input: &str, is_valid: bool,
+                    is_focused: bool) {
+    canvas.style(MODAL_LOW_STYLE);
+    canvas.cut_left(label.width()).text(label, 0, 0);
+    canvas.style(if is_valid { MODAL_SELECTED_LOW_STYLE } else { MODAL_DISABLED_STYLE });
+    canvas.fill();
+    let mut input = input;
+    while !input.is_empty() && input.width() + 2 > canvas.width() {
+        let mut chars = input.chars();
+        chars.next();
+        input = chars.as_str();
+    }
+    canvas.text(input, 0, 1);
+    if is_focused {
+        canvas.style(MODAL_SELECTED_STYLE);
+        canvas.char(' ', 0, input.width() + 1);
+    }
+}
+
+impl<'a> View for FindPanelView<'a> {
+    fn width(&self) -> usize {
+        FIND_LABEL.width() + self.input.width() + 2
+    }
+
+    fn height(&self) -> usize {
+        if self.confirmation.is_some() {
+            return 1
+        }
+        let rows = [Some(self.input), self.location, self.replacement];
+        rows.iter().filter(|row| row.is_some()).count()
+    }
+
+    fn render(&self, mut canvas: Canvas) {
+        canvas.style(MODAL_STYLE);
+        canvas.fill();
+        if let Some(question) = self.confirmation {
+            canvas.text(question, 0, 1);
+            return
+        }
+        let mut buttons = vec![(".*", self.options.regex),
+                               ("Aa", self.options.case_sensitive),
+                               ("\"\"", self.options.whole_word)];
+        // files are searched entirely
+        if self.location.is_none() {
+            buttons.push(("sel", self.options.in_selection));
+        }
+        if self.replacement.is_some() {
+            buttons.push(("aA", self.options.preserve_case));
+        }
+        let mut find = canvas.cut_top(1);
+        for &(caption, is_enabled) in buttons.iter().rev() {
+            let button = find.cut_right(caption.width() + 2);
+            button.style(if is_enabled { MODAL_SELECTED_STYLE } else { MODAL_DISABLED_STYLE });
+            button.fill();
+            button.text(caption, 0, 1);
+        }
+        find.cut_right(1);
+        render_input(find, FIND_LABEL, self.input, self.is_valid, self.focus == Field::Pattern);
+        canvas.cut_right(1);
+        if let Some(location) = self.location {
+            render_input(canvas.cut_top(1), WHERE_LABEL, location, self.is_location_valid,
+                         self.focus == Field::Location);
+        }
+        if let Some(replacement) = self.replacement {
+            render_input(canvas.cut_top(1), REPLACE_LABEL, replacement, true,
+                         self.focus == Field::Replacement);
+        }
+    }
+}
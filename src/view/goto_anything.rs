@@ -0,0 +1,168 @@
+use core::Core;
+use core::bindings::Key;
+use core::command::Command;
+use core::settings::{Settings, SettingsObject};
+use core::workspace::ProjectFile;
+use core::symbols::Symbol;
+
+use toolkit::*;
+
+use view::quick_panel::{QuickPanel, QuickPanelItem, QuickPanelView};
+
+/// Characters that start the location in the file: line, symbol or text.
+const SEPARATORS: &'static [char] = &[':', '@', '#'];
+
+/// Overlay that opens project files found by path and goes to the location in them,
+/// like `main.rs:10`, `main.rs@main` or `main.rs#todo`. Without the path
+/// the location is looked up in the active view, and `@` lists its symbols.
+#[derive(Debug)]
+pub struct GotoAnything {
+    panel: QuickPanel,
+    files: Vec<ProjectFile>,
+    /// Symbols of the active view filtered by the input after `@`
+    symbol_panel: QuickPanel,
+    /// Selected file and location that were previewed last time
+    preview: Option<(Option<usize>, String)>
+}
+
+impl GotoAnything {
+    pub fn new(files: Vec<ProjectFile>, symbols: Vec<Symbol>, text: &str) -> GotoAnything {
+        let items = files.iter().map(|file| QuickPanelItem {
+            caption: file.name.clone(),
+            detail: String::new()
+        }).collect();
+        let symbol_items = symbols.into_iter().map(|symbol| QuickPanelItem {
+            caption: symbol.name,
+            detail: (symbol.region.b.line + 1).to_string()
+        }).collect();
+        let mut panel = QuickPanel::new(items).with_separators(SEPARATORS);
+        panel.set_input(text);
+        let mut goto = GotoAnything {
+            panel: panel,
+            files: files,
+            symbol_panel: QuickPanel::new(symbol_items),
+            preview: None
+        };
+        goto.update_symbols();
+        goto
+    }
+
+    /// Checks whether the symbols of the active view are listed instead of files.
+    fn is_symbol_mode(&self) -> bool {
+        self.panel.pattern().is_empty() && self.panel.suffix().starts_with('@')
+    }
+
+    fn update_symbols(&mut self) {
+        if self.is_symbol_mode() {
+            let input = self.panel.suffix()[1..].to_owned();
+            self.symbol_panel.set_input(&input);
+        }
+    }
+
+    /// Returns index of the selected file, or None if the active view is used.
+    fn selected_file(&self) -> Option<usize> {
+        if self.panel.pattern().is_empty() && !self.panel.suffix().is_empty() {
+            None
+        } else {
+            self.panel.selected()
+        }
+    }
+
+    /// Returns command that goes to the location described by the input suffix.
+    fn location_command(&self) -> Option<Command> {
+        let suffix = self.panel.suffix();
+        let mut chars = suffix.chars();
+        let (name, key, value) = match (chars.next(), chars.as_str()) {
+            (_, "") => return None,
+            (Some(':'), line) => match line.parse::<u64>() {
+                Ok(line) => ("goto_line", "line", Settings::U64(line)),
+                Err(_) => return None
+            },
+            (Some('@'), symbol) => ("goto_symbol", "symbol", Settings::String(symbol.to_owned())),
+            (Some('#'), text) => ("goto_text", "text", Settings::String(text.to_owned())),
+            _ => return None
+        };
+        let mut args = SettingsObject::new();
+        args.insert(key.to_owned(), value);
+        Some(Command::new(name, args))
+    }
+
+    /// Returns commands that open the selected file and go to the location.
+    fn commands(&self, transient: bool) -> Vec<Command> {
+        let mut commands = Vec::new();
+        if self.is_symbol_mode() {
+            if let Some(index) = self.symbol_panel.selected() {
+                let mut args = SettingsObject::new();
+                args.insert("index".to_owned(), Settings::U64(index as u64));
+                commands.push(Command::new("goto_symbol", args));
+            }
+            return commands
+        }
+        if let Some(index) = self.selected_file() {
+            let mut args = SettingsObject::new();
+            let path = self.files[index].path.to_string_lossy().into_owned();
+            args.insert("file".to_owned(), Settings::String(path));
+            args.insert("transient".to_owned(), Settings::Boolean(transient));
+            commands.push(Command::new("open_file", args));
+        }
+        commands.extend(self.location_command());
+        commands
+    }
+
+    /// Opens the selected file in the transient view, if the selection was changed.
+    fn preview(&mut self, core: &Core) {
+        let preview = (self.selected_file(), self.panel.suffix().to_owned());
+        if self.preview.as_ref() == Some(&preview) {
+            return
+        }
+        let has_file = preview.0.is_some();
+        self.preview = Some(preview);
+        // the active view is changed only when the input is accepted
+        if has_file {
+            for command in self.commands(true) {
+                core.run_command(command);
+            }
+        }
+    }
+}
+
+impl<'a> Widget<'a> for GotoAnything {
+    type Context = &'a Core;
+    type View = QuickPanelView<'a>;
+
+    fn view(&'a self, _: &'a Core) -> QuickPanelView<'a> {
+        if self.is_symbol_mode() {
+            self.symbol_panel.view(()).with_input(self.panel.input())
+        } else {
+            self.panel.view(())
+        }
+    }
+
+    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        match key {
+            Key::Enter => {
+                // overlay is hidden first to close the preview and focus the previous view
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                for command in self.commands(false) {
+                    core.run_command(command);
+                }
+                true
+            },
+            Key::Escape => {
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                true
+            },
+            Key::Up | Key::Down | Key::PageUp | Key::PageDown if self.is_symbol_mode() => {
+                self.symbol_panel.on_keypress((), canvas, key)
+            },
+            _ => {
+                if !self.panel.on_keypress((), canvas, key) {
+                    return false
+                }
+                self.update_symbols();
+                self.preview(core);
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use unicode_width::UnicodeWidthStr;
+
+use core::Core;
+use core::bindings::Key;
+use core::command::Command;
+use core::settings::{Settings, SettingsObject};
+
+use toolkit::*;
+
+use view::find_panel::render_input;
+use view::theme::*;
+
+/// Panel at the bottom of the window that asks for a text or a confirmation before
+/// the command is performed, or shows a message.
+#[derive(Debug)]
+pub struct InputPanel {
+    caption: String,
+    /// Typed text, or None when the panel asks a question
+    input: Option<String>,
+    /// Performed by enter with the typed text as the `name` argument,
+    /// or with the `confirm` argument when it's a question. Message has no command
+    command: Option<Command>
+}
+
+pub struct InputPanelView<'a> {
+    caption: &'a str,
+    input: Option<&'a str>
+}
+
+impl InputPanel {
+    /// Asks for a text, the initial text can be edited.
+    pub fn input(caption: &str, text: &str, command: Command) -> InputPanel {
+        InputPanel {
+            caption: caption.to_owned(),
+            input: Some(text.to_owned()),
+            command: Some(command)
+        }
+    }
+
+    /// Asks whether the command should be performed.
+    pub fn question(question: String, command: Command) -> InputPanel {
+        InputPanel {
+            caption: question,
+            input: None,
+            command: Some(command)
+        }
+    }
+
+    /// Shows the message until enter or escape is pressed.
+    pub fn message(message: String) -> InputPanel {
+        InputPanel {
+            caption: message,
+            input: None,
+            command: None
+        }
+    }
+
+    /// Hides the panel and performs the command with the answer.
+    fn done(&self, core: &Core) {
+        core.run_command(Command::new("hide_panel", SettingsObject::new()));
+        let mut command = match self.command {
+            Some(ref command) => command.clone(),
+            None => return
+        };
+        let (name, value) = match self.input {
+            Some(ref input) => ("name", Settings::String(input.clone())),
+            None => ("confirm", Settings::Boolean(true))
+        };
+        command.args.insert(name.to_owned(), value);
+        core.run_command(command);
+    }
+}
+
+impl<'a> Widget<'a> for InputPanel {
+    type Context = &'a Core;
+    type View = InputPanelView<'a>;
+
+    fn view(&'a self, _: &'a Core) -> InputPanelView<'a> {
+        InputPanelView {
+            caption: &self.caption,
+            input: self.input.as_ref().map(|input| &input[..])
+        }
+    }
+
+    fn on_keypress(&mut self, core: &Core, _: Canvas, key: Key) -> bool {
+        if key == Key::Enter {
+            self.done(core);
+            return true
+        }
+        match (key, self.input.as_mut()) {
+            (Key::Char(c), Some(input)) => input.push(c),
+            (Key::Backspace, Some(input)) => {
+                input.pop();
+            },
+            // the question is answered by enter, the text isn't typed anywhere
+            (Key::Char(_), None) | (Key::Backspace, None) => {},
+            _ => return false
+        }
+        true
+    }
+}
+
+impl<'a> View for InputPanelView<'a> {
+    fn width(&self) -> usize {
+        self.caption.width() + self.input.map_or(0, |input| input.width()) + 4
+    }
+
+    fn height(&self) -> usize {
+        1
+    }
+
+    fn render(&self, mut canvas: Canvas) {
+        canvas.style(MODAL_STYLE);
+        canvas.fill();
+        canvas.cut_left(1);
+        match self.input {
+            Some(input) => {
+                canvas.cut_right(1);
+                render_input(canvas, self.caption, input, true, true);
+            },
+            None => canvas.text(self.caption, 0, 0)
+        }
+    }
+}
@@ -67,6 +67,26 @@ impl Menubar {
         })
     }
 
+    /// Hides opened menu and returns focus to the window.
+    pub fn close(&mut self, core: &Core) {
+        if let Some(index) = self.focused {
+            self.items[index].modal.unfocus(core);
+        }
+        self.focused = None;
+    }
+
+    /// Returns index of the item displayed at the given column.
+    fn item_at(&self, x: usize) -> Option<usize> {
+        let mut left = 0;
+        for (index, item) in self.items.iter().enumerate() {
+            left += item.caption.width() + 2;
+            if x < left {
+                return Some(index)
+            }
+        }
+        None
+    }
+
     fn focus_next(&mut self, core: &Core) {
         if self.items.is_empty() {
             return
@@ -107,6 +127,15 @@ impl<'a> Widget<'a> for Menubar {
     }
 
     fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        if self.focused.is_none() {
+            // menubar takes focus only by F10, like most of terminal applications
+            if key != Key::F(10) {
+                return false
+            }
+            self.focus_next(core);
+            self.view(core).render(canvas);
+            return true
+        }
         if let Some(child) = self.focused() {
             if child.modal.on_keypress(core, canvas, key) {
                 return true
@@ -115,11 +144,47 @@ impl<'a> Widget<'a> for Menubar {
         match key {
             Key::Left => self.focus_prev(core),
             Key::Right => self.focus_next(core),
-            _ => return false
+            Key::Escape | Key::F(10) => self.close(core),
+            // opened menu captures all other keys
+            _ => return true
         }
         self.view(core).render(canvas);
         true
     }
+
+    fn on_mouse(&mut self, core: &Core, canvas: Canvas, event: MouseEvent) -> bool {
+        let mut processed = false;
+        if let Some(child) = self.focused() {
+            if child.modal.on_mouse(core, canvas, event) {
+                processed = true
+            }
+        }
+        if processed {
+            return true
+        }
+        if event.action != MouseAction::Press {
+            // opened menu captures all events
+            return self.focused.is_some()
+        }
+        if canvas.contains(event.x, event.y) {
+            let (x, _) = canvas.relative(event.x, event.y);
+            match self.item_at(x) {
+                Some(index) if self.focused != Some(index) => {
+                    self.close(core);
+                    self.focused = Some(index);
+                },
+                _ => self.close(core)
+            }
+            self.view(core).render(canvas);
+            return true
+        }
+        // click outside of the opened menu closes it
+        if self.focused.is_some() {
+            self.close(core);
+            return true
+        }
+        false
+    }
 }
 
 impl<'a> MenubarItemView<'a> {
@@ -5,3 +5,12 @@ pub mod window;
 pub mod modal;
 pub mod editor;
 pub mod sidebar;
+pub mod tabs;
+pub mod quick_panel;
+pub mod command_palette;
+pub mod goto_anything;
+pub mod definition_list;
+pub mod project_list;
+pub mod find_panel;
+pub mod input_panel;
+pub mod overlay;
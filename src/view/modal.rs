@@ -67,6 +67,21 @@ impl<'a, T: Widget<'a>> Widget<'a> for Modal<T> {
         r
     }
 
+    fn on_mouse(&'a mut self, core: Self::Context, _: Canvas, event: MouseEvent) -> bool {
+        let canvas = match self.panel.get() {
+            Some((_, canvas)) => canvas,
+            None => return false
+        };
+        if !canvas.contains(event.x, event.y) {
+            return self.content.on_mouse(core, canvas, event)
+        }
+        // modal captures all events inside it
+        self.content.on_mouse(core, canvas, event);
+        update_panels();
+        doupdate();
+        true
+    }
+
     fn focus(&mut self, context: Self::Context) {
         self.content.focus(context);
     }
@@ -102,6 +117,7 @@ impl ModalPosition {
     fn get_window(&self, base: Canvas, w: usize, h: usize) -> (Canvas, PANEL) {
         let (x, y) = match *self {
             ModalPosition::UnderLeft => (base.x0 + base.x1, base.y0 + base.y2),
+            ModalPosition::AboveLeft => (base.x0 + base.x1, (base.y0 + base.y1).saturating_sub(h)),
             ModalPosition::RightTop  => (base.x0 + base.x2, base.y0 + base.y1),
             _ => unimplemented!()
         };
@@ -0,0 +1,42 @@
+use core::Core;
+use core::bindings::Key;
+
+use toolkit::*;
+
+use view::command_palette::CommandPalette;
+use view::goto_anything::GotoAnything;
+use view::definition_list::DefinitionList;
+use view::project_list::ProjectList;
+use view::quick_panel::QuickPanelView;
+
+/// Panel shown over the window, like the command palette.
+#[derive(Debug)]
+pub enum Overlay {
+    CommandPalette(CommandPalette),
+    Goto(GotoAnything),
+    Definitions(DefinitionList),
+    Projects(ProjectList)
+}
+
+impl<'a> Widget<'a> for Overlay {
+    type Context = &'a Core;
+    type View = QuickPanelView<'a>;
+
+    fn view(&'a self, core: &'a Core) -> QuickPanelView<'a> {
+        match *self {
+            Overlay::CommandPalette(ref palette) => palette.view(core),
+            Overlay::Goto(ref goto) => goto.view(core),
+            Overlay::Definitions(ref list) => list.view(core),
+            Overlay::Projects(ref list) => list.view(core)
+        }
+    }
+
+    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        match *self {
+            Overlay::CommandPalette(ref mut palette) => palette.on_keypress(core, canvas, key),
+            Overlay::Goto(ref mut goto) => goto.on_keypress(core, canvas, key),
+            Overlay::Definitions(ref mut list) => list.on_keypress(core, canvas, key),
+            Overlay::Projects(ref mut list) => list.on_keypress(core, canvas, key)
+        }
+    }
+}
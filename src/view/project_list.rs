@@ -0,0 +1,54 @@
+use core::Core;
+use core::bindings::Key;
+use core::command::Command;
+use core::settings::{Settings, SettingsObject};
+
+use toolkit::*;
+
+use view::quick_panel::{QuickPanel, QuickPanelItem, QuickPanelView};
+
+/// Overlay that switches the window to one of the recent projects.
+#[derive(Debug)]
+pub struct ProjectList {
+    panel: QuickPanel
+}
+
+impl ProjectList {
+    pub fn new(core: &Core) -> ProjectList {
+        let items = core.history().projects().iter().map(|path| QuickPanelItem {
+            caption: path.file_stem().map_or(String::new(), |name| {
+                name.to_string_lossy().into_owned()
+            }),
+            detail: path.to_string_lossy().into_owned()
+        }).collect();
+        ProjectList { panel: QuickPanel::new(items) }
+    }
+}
+
+impl<'a> Widget<'a> for ProjectList {
+    type Context = &'a Core;
+    type View = QuickPanelView<'a>;
+
+    fn view(&'a self, _: &'a Core) -> QuickPanelView<'a> {
+        self.panel.view(())
+    }
+
+    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        match key {
+            Key::Enter => {
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                if let Some(index) = self.panel.selected() {
+                    let mut args = SettingsObject::new();
+                    args.insert("index".to_owned(), Settings::U64(index as u64));
+                    core.run_command(Command::new("open_recent_project", args));
+                }
+                true
+            },
+            Key::Escape => {
+                core.run_command(Command::new("hide_overlay", SettingsObject::new()));
+                true
+            },
+            _ => self.panel.on_keypress((), canvas, key)
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::cmp::min;
+
+use unicode_width::{UnicodeWidthStr, UnicodeWidthChar};
+
+use toolkit::*;
+use core::bindings::Key;
+use core::fuzzy::{self, FuzzyMatch};
+use view::theme::*;
+
+/// Maximum amount of items displayed at once.
+const MAX_VISIBLE_ITEMS: usize = 10;
+
+/// Input line with the list of items filtered by it.
+#[derive(Debug)]
+pub struct QuickPanel {
+    input: String,
+    /// Characters that end the part of the input used to filter items
+    separators: Vec<char>,
+    /// Pattern the items were filtered by
+    pattern: String,
+    items: Vec<QuickPanelItem>,
+    /// Indices of the items that match the input with their matches, the best ones first
+    matches: Vec<(usize, FuzzyMatch)>,
+    /// Position of the selected item in matches
+    selected: usize,
+    /// Position of the first visible item in matches
+    scroll: usize
+}
+
+#[derive(Debug)]
+pub struct QuickPanelItem {
+    pub caption: String,
+    /// Text displayed on the right side, like a hotkey
+    pub detail: String
+}
+
+pub struct QuickPanelView<'a> {
+    input: &'a str,
+    items: Vec<QuickPanelItemView<'a>>
+}
+
+struct QuickPanelItemView<'a> {
+    item: &'a QuickPanelItem,
+    /// Byte offsets of the caption characters matched by the input
+    positions: &'a [usize],
+    is_selected: bool
+}
+
+impl QuickPanel {
+    pub fn new(items: Vec<QuickPanelItem>) -> QuickPanel {
+        let mut panel = QuickPanel {
+            input: String::new(),
+            separators: Vec::new(),
+            pattern: String::new(),
+            items: items,
+            matches: Vec::new(),
+            selected: 0,
+            scroll: 0
+        };
+        panel.filter();
+        panel
+    }
+
+    /// Sets characters that end the filtered part of the input. The rest of the input,
+    /// starting with the separator, doesn't affect the list.
+    pub fn with_separators(mut self, separators: &[char]) -> QuickPanel {
+        self.separators = separators.to_vec();
+        self.filter();
+        self
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn set_input(&mut self, input: &str) {
+        self.input = input.to_owned();
+        self.filter();
+    }
+
+    fn split_input(&self) -> usize {
+        self.input.find(&self.separators[..]).unwrap_or(self.input.len())
+    }
+
+    /// Returns the part of the input used to filter items.
+    pub fn pattern(&self) -> &str {
+        &self.input[..self.split_input()]
+    }
+
+    /// Returns the part of the input after the pattern, including the separator.
+    pub fn suffix(&self) -> &str {
+        &self.input[self.split_input()..]
+    }
+
+    /// Returns index of the selected item.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.selected).map(|&(index, _)| index)
+    }
+
+    fn filter(&mut self) {
+        self.pattern = self.pattern().to_owned();
+        let captions = self.items.iter().map(|item| &item.caption[..]);
+        self.matches = fuzzy::filter(&self.pattern, captions);
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// Filters items again if the pattern was changed.
+    fn update(&mut self) {
+        if self.pattern() != self.pattern {
+            self.filter();
+        }
+    }
+
+    fn select(&mut self, selected: usize) {
+        if self.matches.is_empty() {
+            return
+        }
+        self.selected = min(selected, self.matches.len() - 1);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + MAX_VISIBLE_ITEMS {
+            self.scroll = self.selected + 1 - MAX_VISIBLE_ITEMS;
+        }
+    }
+}
+
+impl<'a> Widget<'a> for QuickPanel {
+    type Context = ();
+    type View = QuickPanelView<'a>;
+
+    fn view(&'a self, _: ()) -> QuickPanelView<'a> {
+        let end = min(self.scroll + MAX_VISIBLE_ITEMS, self.matches.len());
+        QuickPanelView {
+            input: &self.input,
+            items: (self.scroll..end).map(|i| QuickPanelItemView {
+                item: &self.items[self.matches[i].0],
+                positions: &self.matches[i].1.positions,
+                is_selected: i == self.selected
+            }).collect()
+        }
+    }
+
+    fn on_keypress(&mut self, _: (), _: Canvas, key: Key) -> bool {
+        match key {
+            Key::Char(c) => {
+                self.input.push(c);
+                self.update();
+            },
+            Key::Backspace => {
+                self.input.pop();
+                self.update();
+            },
+            Key::Up => {
+                let selected = self.selected.saturating_sub(1);
+                self.select(selected)
+            },
+            Key::Down => {
+                let selected = self.selected + 1;
+                self.select(selected)
+            },
+            Key::PageUp => {
+                let selected = self.selected.saturating_sub(MAX_VISIBLE_ITEMS);
+                self.select(selected)
+            },
+            Key::PageDown => {
+                let selected = self.selected + MAX_VISIBLE_ITEMS;
+                self.select(selected)
+            },
+            _ => return false
+        }
+        true
+    }
+}
+
+impl<'a> View for QuickPanelItemView<'a> {
+    fn width(&self) -> usize {
+        self.item.caption.width() + self.item.detail.width() + 4
+    }
+
+    fn height(&self) -> usize {
+        1
+    }
+
+    fn render(&self, mut canvas: Canvas) {
+        let (style, low_style) = if self.is_selected {
+            (MODAL_SELECTED_STYLE, MODAL_SELECTED_LOW_STYLE)
+        } else {
+            (MODAL_STYLE, MODAL_LOW_STYLE)
+        };
+        canvas.style(style);
+        canvas.fill();
+        let mut x = 1;
+        for (offset, c) in self.item.caption.char_indices() {
+            if self.positions.contains(&offset) {
+                if self.is_selected {
+                    canvas.style(MODAL_SELECTED_MATCH_STYLE);
+                    canvas.char(c, 0, x);
+                    canvas.style(MODAL_SELECTED_STYLE);
+                } else {
+                    canvas.style(MODAL_MATCH_STYLE);
+                    canvas.char(c, 0, x);
+                    canvas.style(MODAL_STYLE);
+                }
+            } else {
+                canvas.char(c, 0, x);
+            }
+            x += c.width().unwrap_or(0);
+        }
+        let detail = canvas.cut_right(self.item.detail.width() + 1);
+        detail.style(low_style);
+        detail.text(&self.item.detail, 0, 0);
+    }
+}
+
+impl<'a> QuickPanelView<'a> {
+    /// Replaces the displayed input, when the items are filtered by another panel.
+    pub fn with_input(mut self, input: &'a str) -> QuickPanelView<'a> {
+        self.input = input;
+        self
+    }
+}
+
+impl<'a> View for QuickPanelView<'a> {
+    fn width(&self) -> usize {
+        self.items.iter().map(|item| item.width()).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> usize {
+        self.items.len() + 1
+    }
+
+    fn render(&self, mut canvas: Canvas) {
+        let input = canvas.cut_top(1);
+        input.style(MODAL_SELECTED_LOW_STYLE);
+        input.fill();
+        input.text(self.input, 0, 1);
+        input.style(MODAL_SELECTED_STYLE);
+        input.char(' ', 0, self.input.width() + 1);
+        for item in &self.items {
+            item.render(canvas.cut_top(1));
+        }
+    }
+}
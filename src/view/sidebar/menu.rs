@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use core::menu::{Menu, MenuItem};
+use core::settings::Settings;
+
+/// Fills the path arguments of the menu commands for the entry. Commands that take files
+/// are removed from the menu of a folder and vice versa, with dividers left around them.
+/// Only the folders of the project can be removed from it.
+pub fn entry_menu(menu: Menu, path: &Path, is_folder: bool, is_root: bool) -> Menu {
+    let path = Settings::String(path.to_string_lossy().into_owned());
+    let mut items = Menu::new();
+    for item in menu {
+        match item {
+            MenuItem::Button(caption, mut command, is_checkbox) => {
+                let mut is_applicable = is_root || command.name != "remove_folder";
+                for (name, value) in &mut command.args {
+                    let takes_entry = match &name[..] {
+                        "files" => !is_folder,
+                        "dirs" => is_folder,
+                        "paths" => true,
+                        _ => continue
+                    };
+                    is_applicable = is_applicable && takes_entry;
+                    *value = Settings::Array(vec![path.clone()]);
+                }
+                if is_applicable {
+                    items.push(MenuItem::Button(caption, command, is_checkbox));
+                }
+            },
+            MenuItem::Divider => match items.last() {
+                Some(&MenuItem::Divider) | None => {},
+                Some(_) => items.push(MenuItem::Divider)
+            },
+            group => items.push(group)
+        }
+    }
+    if let Some(&MenuItem::Divider) = items.last() {
+        items.pop();
+    }
+    items
+}
@@ -1,30 +1,470 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::{min, max};
+use std::path::{Path, PathBuf};
+
 use core::Core;
+use core::command::Command;
+use core::settings::{Settings, SettingsObject};
+use core::bindings::{Key, MouseButton};
+use core::vcs::FileStatus;
 
 mod tree;
+mod menu;
 
-pub use self::tree::{ProjectEntries, ProjectEntriesView};
+pub use self::tree::{ProjectEntries, ProjectEntriesView, ProjectEntry, ProjectEntryView};
 
 use toolkit::*;
+use view::context::{ContextMenu, ContextMenuView};
+use view::modal::{Modal, ModalView, ModalPosition};
+use view::theme::*;
+
+/// Amount of rows scrolled by the mouse wheel.
+const SCROLL_ROWS: usize = 3;
+
+const OPEN_FILES_HEADER: &'static str = "OPEN FILES";
+const FOLDERS_HEADER: &'static str = "FOLDERS";
 
+/// Project tree, optionally preceded by the list of open files. Rows are selected
+/// by the keyboard when the sidebar has focus.
 #[derive(Debug)]
 pub struct Sidebar {
-    project_tree: ProjectEntries
+    project_tree: ProjectEntries,
+    is_visible: bool,
+    /// Keys are handled by the sidebar instead of the active view
+    is_focused: bool,
+    show_open_files: bool,
+    selected: usize,
+    /// First visible row
+    scroll: usize,
+    /// Height of the sidebar, updated on each render
+    height: Cell<usize>,
+    /// Context menu opened for the row
+    menu: Option<(usize, Modal<ContextMenu>)>
 }
 
+pub struct SidebarView<'a> {
+    rows: Vec<SidebarRow<'a>>,
+    scroll: usize,
+    height: &'a Cell<usize>,
+    /// Context menu and its row counted from the first visible one
+    menu: Option<(usize, ModalView<'a, ContextMenuView<'a>>)>
+}
+
+enum SidebarRow<'a> {
+    Header(&'static str),
+    Entry(ProjectEntryView<'a>)
+}
+
+/// Returns the group, the index, the name and the git status of each open view.
+fn open_files(core: &Core) -> Vec<(usize, usize, Cow<str>, Option<FileStatus>)> {
+    let mut files = Vec::new();
+    for group in 0..core.num_groups() {
+        for (index, view) in core.views_in_group(group).iter().enumerate() {
+            let status = view.buffer().path().and_then(|path| core.vcs_status.status(path));
+            files.push((group, index, view.name(), status));
+        }
+    }
+    files
+}
 
 impl Sidebar {
     pub fn new(core: &Core) -> Sidebar {
         Sidebar {
-            project_tree: ProjectEntries::from_project(&core.project)
+            project_tree: ProjectEntries::from_project(&core.project, &core.vcs_status),
+            is_visible: true,
+            is_focused: false,
+            show_open_files: true,
+            selected: 0,
+            scroll: 0,
+            height: Cell::new(0),
+            menu: None
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_visible = !self.is_visible;
+        self.is_focused = self.is_focused && self.is_visible;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    pub fn toggle_open_files(&mut self) {
+        self.show_open_files = !self.show_open_files;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// Returns the number of rows before the project tree.
+    fn tree_offset(&self, core: &Core) -> usize {
+        if self.show_open_files {
+            open_files(core).len() + 2
+        } else {
+            0
+        }
+    }
+
+    fn num_rows(&self, core: &Core) -> usize {
+        self.tree_offset(core) + self.project_tree.num_rows()
+    }
+
+    fn is_header(&self, core: &Core, row: usize) -> bool {
+        self.show_open_files && (row == 0 || row + 1 == self.tree_offset(core))
+    }
+
+    /// Selects the row, headers are skipped in the direction of the movement.
+    fn select(&mut self, core: &Core, row: usize, forward: bool) {
+        let num_rows = self.num_rows(core);
+        let row = min(row, num_rows.saturating_sub(1));
+        let rows: Vec<usize> = if forward {
+            (row..num_rows).chain((0..row).rev()).collect()
+        } else {
+            (0..row + 1).rev().chain(row + 1..num_rows).collect()
+        };
+        let row = match rows.into_iter().find(|&row| !self.is_header(core, row)) {
+            Some(row) => row,
+            None => return
+        };
+        self.selected = row;
+        let height = max(self.height.get(), 1);
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + height {
+            self.scroll = row + 1 - height;
+        }
+    }
+
+    fn scroll(&mut self, core: &Core, amount: isize) {
+        let max_scroll = self.num_rows(core).saturating_sub(self.height.get());
+        let scroll = self.first_row(core);
+        self.scroll = if amount < 0 {
+            scroll.saturating_sub(-amount as usize)
+        } else {
+            min(scroll + amount as usize, max_scroll)
+        };
+    }
+
+    /// Opens the file of the row and moves focus to it, or opens and closes the folder.
+    fn activate(&mut self, core: &Core, row: usize) {
+        let offset = self.tree_offset(core);
+        if row < offset {
+            let files = open_files(core);
+            if let Some(&(group, index, _, _)) = row.checked_sub(1).and_then(|i| files.get(i)) {
+                let mut args = SettingsObject::new();
+                args.insert("group".to_owned(), Settings::U64(group as u64));
+                core.run_command(Command::new("focus_group", args));
+                let mut args = SettingsObject::new();
+                args.insert("index".to_owned(), Settings::U64(index as u64));
+                core.run_command(Command::new("select_by_index", args));
+                self.is_focused = false;
+            }
+            return
+        }
+        let path = match self.project_tree.entry_mut(row - offset) {
+            Some(&mut ProjectEntry::Folder(ref mut folder)) => {
+                let opened = folder.is_opened();
+                folder.set_opened(!opened);
+                return
+            },
+            Some(entry) => entry.path().to_string_lossy().into_owned(),
+            None => return
+        };
+        let mut args = SettingsObject::new();
+        args.insert("file".to_owned(), Settings::String(path));
+        core.run_command(Command::new("open_file", args));
+        self.is_focused = false;
+    }
+
+    /// Returns the first visible row, rows may be removed since the sidebar was scrolled.
+    fn first_row(&self, core: &Core) -> usize {
+        min(self.scroll, self.num_rows(core).saturating_sub(self.height.get()))
+    }
+
+    /// Opens the selected folder, or selects its first entry if it's already opened.
+    /// Closes the selected folder, or selects the parent one.
+    fn expand(&mut self, core: &Core, expand: bool) {
+        let offset = self.tree_offset(core);
+        if self.selected < offset {
+            return
+        }
+        let row = self.selected - offset;
+        let is_opened = match self.project_tree.entry_mut(row) {
+            Some(&mut ProjectEntry::Folder(ref mut folder)) => {
+                let is_opened = folder.is_opened();
+                if expand != is_opened {
+                    folder.set_opened(expand);
+                    return
+                }
+                is_opened && !folder.is_empty()
+            },
+            _ => false
+        };
+        if expand && is_opened {
+            self.select(core, self.selected + 1, true);
+        } else if !expand {
+            if let Some(parent) = self.project_tree.parent_row(row) {
+                self.select(core, offset + parent, false);
+            }
+        }
+    }
+
+    /// Returns the path of the row and whether it's a folder.
+    fn row_path(&mut self, core: &Core, row: usize) -> Option<(PathBuf, bool)> {
+        let offset = self.tree_offset(core);
+        if row >= offset {
+            return self.project_tree.entry_mut(row - offset).map(|entry| {
+                (entry.path().to_path_buf(), entry.is_folder())
+            })
+        }
+        let files = open_files(core);
+        row.checked_sub(1).and_then(|i| files.get(i)).and_then(|&(group, index, _, _)| {
+            let buffer = core.views_in_group(group)[index].buffer();
+            buffer.path().map(|path| (path.to_path_buf(), false))
+        })
+    }
+
+    /// Opens the context menu of the row, under the row if there is enough space for it.
+    fn open_menu(&mut self, core: &Core, row: usize) {
+        let (path, is_folder) = match self.row_path(core, row) {
+            Some(path) => path,
+            None => return
+        };
+        let is_root = core.project.folders.iter().any(|pf| pf.path == path);
+        let menu = menu::entry_menu(core.create_sidebar_menu(), &path, is_folder, is_root);
+        if menu.is_empty() {
+            return
+        }
+        let y = row.saturating_sub(self.first_row(core));
+        let position = if y + 1 + menu.len() <= self.height.get() {
+            ModalPosition::UnderLeft
+        } else {
+            ModalPosition::AboveLeft
+        };
+        let mut modal = Modal::new(ContextMenu::new(menu), position);
+        modal.focus(core);
+        self.menu = Some((row, modal));
+    }
+
+    /// Hides the context menu, it's closed after a command is chosen.
+    pub fn close_menu(&mut self, core: &Core) {
+        if let Some((_, mut menu)) = self.menu.take() {
+            menu.unfocus(core);
+        }
+    }
+
+    /// Rebuilds the project tree after the project folders or their git status were changed.
+    pub fn refresh(&mut self, core: &Core) {
+        self.project_tree.update(&core.project, &core.vcs_status);
+        let row = self.selected;
+        self.select(core, row, false);
+    }
+
+    /// Returns paths of the collapsed folders of the project tree.
+    pub fn closed_folders(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        self.project_tree.closed_folders(&mut paths);
+        paths
+    }
+
+    /// Collapses the folders of the project tree, like they were on the last exit.
+    pub fn close_folders(&mut self, core: &Core, paths: &[PathBuf]) {
+        self.project_tree.close_folders(paths);
+        let row = self.selected;
+        self.select(core, row, false);
+    }
+
+    /// Opens folders that contain the path and selects it in the focused sidebar.
+    pub fn reveal(&mut self, core: &Core, path: &Path) {
+        if let Some(row) = self.project_tree.reveal(path) {
+            self.is_visible = true;
+            self.is_focused = true;
+            let offset = self.tree_offset(core);
+            self.select(core, offset + row, true);
         }
     }
 }
 
 impl<'a> Widget<'a> for Sidebar {
     type Context = &'a Core;
-    type View = ProjectEntriesView<'a>;
+    type View = SidebarView<'a>;
+
+    fn view(&'a self, core: &'a Core) -> SidebarView<'a> {
+        let mut rows = Vec::new();
+        if self.show_open_files {
+            rows.push(SidebarRow::Header(OPEN_FILES_HEADER));
+            for (_, _, name, status) in open_files(core) {
+                let name = Cow::Owned(name.into_owned());
+                rows.push(SidebarRow::Entry(ProjectEntryView::file(name, status)));
+            }
+            rows.push(SidebarRow::Header(FOLDERS_HEADER));
+        }
+        rows.extend(self.project_tree.views().map(SidebarRow::Entry));
+        for row in &mut rows {
+            if let SidebarRow::Entry(ref mut view) = *row {
+                view.dim_ignored = core.preferences.exclude_ignored;
+            }
+        }
+        if self.is_focused {
+            if let Some(&mut SidebarRow::Entry(ref mut view)) = rows.get_mut(self.selected) {
+                view.is_selected = true;
+            }
+        }
+        let scroll = self.first_row(core);
+        SidebarView {
+            rows: rows,
+            scroll: scroll,
+            height: &self.height,
+            menu: self.menu.as_ref().map(|&(row, ref menu)| {
+                (row.saturating_sub(scroll), menu.view(core))
+            })
+        }
+    }
+
+    fn on_keypress(&mut self, core: &Core, canvas: Canvas, key: Key) -> bool {
+        if let Some((_, ref mut menu)) = self.menu {
+            if menu.on_keypress(core, canvas, key) {
+                return true
+            }
+        }
+        if self.menu.is_some() {
+            if key == Key::Escape {
+                self.close_menu(core);
+            }
+            // opened menu captures all other keys
+            return true
+        }
+        let page = max(self.height.get(), 1);
+        match key {
+            Key::Up => {
+                let row = self.selected.saturating_sub(1);
+                self.select(core, row, false);
+            },
+            Key::Down => {
+                let row = self.selected + 1;
+                self.select(core, row, true);
+            },
+            Key::PageUp => {
+                let row = self.selected.saturating_sub(page);
+                self.select(core, row, false);
+            },
+            Key::PageDown => {
+                let row = self.selected + page;
+                self.select(core, row, true);
+            },
+            Key::Home => self.select(core, 0, true),
+            Key::End => {
+                let row = self.num_rows(core);
+                self.select(core, row, false);
+            },
+            Key::Left => self.expand(core, false),
+            Key::Right => self.expand(core, true),
+            Key::Enter => {
+                let row = self.selected;
+                self.activate(core, row);
+            },
+            Key::ContextMenu => {
+                let row = self.selected;
+                self.open_menu(core, row);
+            },
+            Key::Escape => self.is_focused = false,
+            // typed characters don't go to the editor while the sidebar has focus
+            Key::Char(_) => {},
+            _ => return false
+        }
+        true
+    }
 
-    fn view(&'a self, _: &'a Core) -> ProjectEntriesView<'a> {
-        self.project_tree.view(())
+    fn on_mouse(&mut self, core: &Core, canvas: Canvas, event: MouseEvent) -> bool {
+        if let Some((_, ref mut menu)) = self.menu {
+            if menu.on_mouse(core, canvas, event) {
+                return true
+            }
+        }
+        if self.menu.is_some() {
+            // click outside of the opened menu closes it
+            if event.action == MouseAction::Press {
+                self.close_menu(core);
+            }
+            return true
+        }
+        if !canvas.contains(event.x, event.y) {
+            return false
+        }
+        if event.action == MouseAction::Press {
+            let (_, y) = canvas.relative(event.x, event.y);
+            let row = self.first_row(core) + y;
+            let is_entry = row < self.num_rows(core) && !self.is_header(core, row);
+            match event.button {
+                MouseButton::Button(1) if is_entry => {
+                    self.is_focused = true;
+                    self.selected = row;
+                    self.activate(core, row);
+                },
+                MouseButton::Button(3) if is_entry => {
+                    self.is_focused = true;
+                    self.selected = row;
+                    self.open_menu(core, row);
+                },
+                MouseButton::ScrollUp => self.scroll(core, -(SCROLL_ROWS as isize)),
+                MouseButton::ScrollDown => self.scroll(core, SCROLL_ROWS as isize),
+                _ => {}
+            }
+        }
+        true
+    }
+
+    fn focus(&mut self, core: &Core) {
+        self.is_visible = true;
+        self.is_focused = true;
+        let row = self.selected;
+        self.select(core, row, true);
+    }
+
+    fn unfocus(&mut self, core: &Core) {
+        self.is_focused = false;
+        self.close_menu(core);
+    }
+}
+
+impl<'a> View for SidebarView<'a> {
+    fn width(&self) -> usize {
+        self.rows.iter().map(|row| match *row {
+            SidebarRow::Header(header) => header.len() + 1,
+            SidebarRow::Entry(ref view) => view.width()
+        }).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn render(&self, mut canvas: Canvas) {
+        let sidebar = canvas;
+        self.height.set(canvas.height());
+        for row in self.rows.iter().skip(self.scroll) {
+            if canvas.height() == 0 {
+                break
+            }
+            let row_canvas = canvas.cut_top(1);
+            match *row {
+                SidebarRow::Header(header) => {
+                    row_canvas.style(SIDEBAR_LOW_STYLE);
+                    row_canvas.fill();
+                    row_canvas.text(header, 0, 1);
+                },
+                SidebarRow::Entry(ref view) => view.render(row_canvas)
+            }
+        }
+        canvas.style(SIDEBAR_STYLE);
+        canvas.fill();
+        if let Some((y, ref menu)) = self.menu {
+            menu.render(sidebar.region(1, y, sidebar.width(), y + 1));
+        }
     }
 }
@@ -1,7 +1,10 @@
 use unicode_width::UnicodeWidthStr;
 
+use std::borrow::Cow;
 use std::slice::{Iter as SliceIter};
+use std::path::{Path, PathBuf};
 
+use core::vcs::{FileStatus, VcsStatus};
 use core::workspace::{Project, Folder};
 
 use toolkit::*;
@@ -25,13 +28,17 @@ pub enum ProjectEntry {
 #[derive(Debug)]
 pub struct ProjectFolder {
     name: String,
+    path: PathBuf,
+    status: Option<FileStatus>,
     opened: bool,
     entries: ProjectEntries
 }
 
 #[derive(Debug)]
 pub struct ProjectFile {
-    name: String
+    name: String,
+    path: PathBuf,
+    status: Option<FileStatus>
 }
 
 #[derive(Debug)]
@@ -42,8 +49,12 @@ pub struct ProjectEntriesView<'a> {
 #[derive(Debug)]
 pub struct ProjectEntryView<'a> {
     level: u8,
-    name: &'a str,
-    entry_type: ProjectEntryType
+    name: Cow<'a, str>,
+    entry_type: ProjectEntryType,
+    status: Option<FileStatus>,
+    pub is_selected: bool,
+    /// Ignored entry is dimmed instead of being marked by the badge
+    pub dim_ignored: bool
 }
 
 #[derive(Debug)]
@@ -54,51 +65,176 @@ enum ProjectEntryType {
 }
 
 impl ProjectEntry {
-    pub fn file(name: String) -> ProjectEntry {
+    pub fn file(name: String, path: PathBuf, vcs: &VcsStatus) -> ProjectEntry {
         let file = ProjectFile {
-            name: name
+            name: name,
+            status: vcs.status(&path),
+            path: path
         };
         ProjectEntry::File(file)
     }
 
-    pub fn folder(name: String, folder: &Folder) -> ProjectEntry {
+    pub fn folder(name: String, path: PathBuf, folder: &Folder, vcs: &VcsStatus) -> ProjectEntry {
         let folder = ProjectFolder {
             name: name,
             opened: true,
-            entries: ProjectEntries::from_folder(folder)
+            entries: ProjectEntries::from_folder(&path, folder, vcs),
+            status: vcs.status(&path),
+            path: path
         };
         ProjectEntry::Folder(folder)
     }
+
+    pub fn path(&self) -> &Path {
+        match *self {
+            ProjectEntry::File(ref file) => &file.path,
+            ProjectEntry::Folder(ref folder) => &folder.path
+        }
+    }
+
+    pub fn is_folder(&self) -> bool {
+        match *self {
+            ProjectEntry::Folder(_) => true,
+            ProjectEntry::File(_) => false
+        }
+    }
+
+    /// Returns the number of rows the entry takes: its own and the rows of the opened folder.
+    fn num_rows(&self) -> usize {
+        match *self {
+            ProjectEntry::Folder(ref folder) if folder.opened => 1 + folder.entries.num_rows(),
+            _ => 1
+        }
+    }
+}
+
+impl ProjectFolder {
+    pub fn is_opened(&self) -> bool {
+        self.opened
+    }
+
+    pub fn set_opened(&mut self, opened: bool) {
+        self.opened = opened;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.entries.is_empty()
+    }
 }
 
 impl ProjectEntries {
-    pub fn from_project(project: &Project) -> ProjectEntries {
+    pub fn from_project(project: &Project, vcs: &VcsStatus) -> ProjectEntries {
         let mut entries = Vec::new();
         for pf in &project.folders {
-            entries.push(ProjectEntry::folder(pf.name().into_owned(), &pf.folder));
+            let name = pf.name().into_owned();
+            entries.push(ProjectEntry::folder(name, pf.path.clone(), &pf.folder, vcs));
         }
         ProjectEntries {
             entries: entries
         }
     }
 
-    pub fn from_folder(folder: &Folder) -> ProjectEntries {
+    pub fn from_folder(path: &Path, folder: &Folder, vcs: &VcsStatus) -> ProjectEntries {
         let mut entries = Vec::new();
         for (name, folder) in &folder.folders {
-            entries.push(ProjectEntry::folder(name.to_owned(), &folder))
+            entries.push(ProjectEntry::folder(name.to_owned(), path.join(name), &folder, vcs))
         }
         for name in &folder.files {
-            entries.push(ProjectEntry::file(name.to_owned()))
+            entries.push(ProjectEntry::file(name.to_owned(), path.join(name), vcs))
         }
         ProjectEntries {
             entries: entries
         }
     }
 
+    /// Rebuilds the entries after the project was walked again or git status was changed,
+    /// closed folders stay closed.
+    pub fn update(&mut self, project: &Project, vcs: &VcsStatus) {
+        let mut closed = Vec::new();
+        self.closed_folders(&mut closed);
+        *self = ProjectEntries::from_project(project, vcs);
+        self.close_folders(&closed);
+    }
+
+    /// Collects paths of the closed folders.
+    pub fn closed_folders(&self, paths: &mut Vec<PathBuf>) {
+        for entry in &self.entries {
+            if let ProjectEntry::Folder(ref folder) = *entry {
+                if !folder.opened {
+                    paths.push(folder.path.clone());
+                }
+                folder.entries.closed_folders(paths);
+            }
+        }
+    }
+
+    /// Closes the folders with the given paths, others are opened.
+    pub fn close_folders(&mut self, paths: &[PathBuf]) {
+        for entry in &mut self.entries {
+            if let ProjectEntry::Folder(ref mut folder) = *entry {
+                folder.opened = !paths.contains(&folder.path);
+                folder.entries.close_folders(paths);
+            }
+        }
+    }
+
     pub fn iter(&self) -> SliceIter<ProjectEntry> {
         self.entries.iter()
     }
 
+    /// Returns the number of visible rows.
+    pub fn num_rows(&self) -> usize {
+        self.entries.iter().map(ProjectEntry::num_rows).sum()
+    }
+
+    /// Returns the entry displayed in the visible row.
+    pub fn entry_mut(&mut self, row: usize) -> Option<&mut ProjectEntry> {
+        let mut row = row;
+        for entry in &mut self.entries {
+            let num_rows = entry.num_rows();
+            if row >= num_rows {
+                row -= num_rows;
+                continue
+            }
+            if row == 0 {
+                return Some(entry)
+            }
+            return match *entry {
+                ProjectEntry::Folder(ref mut folder) => folder.entries.entry_mut(row - 1),
+                ProjectEntry::File(_) => None
+            }
+        }
+        None
+    }
+
+    /// Returns the row of the folder that contains the entry displayed in the row.
+    pub fn parent_row(&self, row: usize) -> Option<usize> {
+        let levels: Vec<u8> = self.views().map(|view| view.level).collect();
+        levels.get(row).and_then(|&level| {
+            (0..row).rev().find(|&parent| levels[parent] < level)
+        })
+    }
+
+    /// Opens folders that contain the path. Returns the row of the path, if it's found.
+    pub fn reveal(&mut self, path: &Path) -> Option<usize> {
+        let mut row = 0;
+        for entry in &mut self.entries {
+            if entry.path() == path {
+                return Some(row)
+            }
+            if let ProjectEntry::Folder(ref mut folder) = *entry {
+                if path.starts_with(&folder.path) {
+                    folder.opened = true;
+                    if let Some(found) = folder.entries.reveal(path) {
+                        return Some(row + 1 + found)
+                    }
+                }
+            }
+            row += entry.num_rows();
+        }
+        None
+    }
+
     pub fn views(&self) -> ProjectEntriesIterator {
         ProjectEntriesIterator {
             path: vec![self.iter()]
@@ -120,18 +256,24 @@ impl<'a> Iterator for ProjectEntriesIterator<'a> {
                 let r = match *entry {
                     ProjectEntry::Folder(ref folder) => ProjectEntryView {
                         level: level,
-                        name: &folder.name,
+                        name: Cow::Borrowed(&folder.name),
                         entry_type: if folder.opened {
                             self.path.push(folder.entries.iter());
                             ProjectEntryType::OpenedFolder
                         } else {
                             ProjectEntryType::ClosedFolder
-                        }
+                        },
+                        status: folder.status,
+                        is_selected: false,
+                        dim_ignored: false
                     },
                     ProjectEntry::File(ref file) => ProjectEntryView {
                         level: level,
-                        name: &file.name,
-                        entry_type: ProjectEntryType::File
+                        name: Cow::Borrowed(&file.name),
+                        entry_type: ProjectEntryType::File,
+                        status: file.status,
+                        is_selected: false,
+                        dim_ignored: false
                     },
                 };
                 Some(r)
@@ -176,14 +318,59 @@ impl<'a> View for ProjectEntriesView<'a> {
 }
 
 impl<'a> ProjectEntryView<'a> {
+    /// Creates view of the file that isn't in the tree, like an open file.
+    pub fn file(name: Cow<'a, str>, status: Option<FileStatus>) -> ProjectEntryView<'a> {
+        ProjectEntryView {
+            level: 0,
+            name: name,
+            entry_type: ProjectEntryType::File,
+            status: status,
+            is_selected: false,
+            dim_ignored: false
+        }
+    }
+
     fn style(&self) -> Style {
-        match self.entry_type {
-            ProjectEntryType::File => SIDEBAR_LOW_STYLE,
-            ProjectEntryType::OpenedFolder |
-            ProjectEntryType::ClosedFolder => SIDEBAR_STYLE,
+        if self.is_selected { SIDEBAR_SELECTED_STYLE } else { SIDEBAR_STYLE }
+    }
+
+    /// Returns the style of the name, it's colored by the git status.
+    fn name_style(&self) -> Style {
+        match self.status {
+            Some(FileStatus::Ignored) if !self.dim_ignored => self.style(),
+            Some(status) => self.status_style(status),
+            None => self.style()
+        }
+    }
+
+    fn status_style(&self, status: FileStatus) -> Style {
+        match (status, self.is_selected) {
+            (FileStatus::Modified, false) => SIDEBAR_MODIFIED_STYLE,
+            (FileStatus::Modified, true) => SIDEBAR_MODIFIED_SELECTED_STYLE,
+            (FileStatus::Added, false) => SIDEBAR_ADDED_STYLE,
+            (FileStatus::Added, true) => SIDEBAR_ADDED_SELECTED_STYLE,
+            (FileStatus::Untracked, false) => SIDEBAR_UNTRACKED_STYLE,
+            (FileStatus::Untracked, true) => SIDEBAR_UNTRACKED_SELECTED_STYLE,
+            (FileStatus::Ignored, false) => SIDEBAR_IGNORED_STYLE,
+            (FileStatus::Ignored, true) => SIDEBAR_IGNORED_SELECTED_STYLE
+        }
+    }
+
+    /// Returns the badge shown at the end of the row, dimmed entries don't have it.
+    fn badge(&self) -> Option<&'static str> {
+        match self.status {
+            Some(FileStatus::Modified) => Some(MODIFIED_BADGE),
+            Some(FileStatus::Added) => Some(ADDED_BADGE),
+            Some(FileStatus::Untracked) => Some(UNTRACKED_BADGE),
+            Some(FileStatus::Ignored) if !self.dim_ignored => Some(IGNORED_BADGE),
+            _ => None
         }
     }
 
+    fn low_style(&self) -> Style {
+        if self.is_selected { SIDEBAR_LOW_SELECTED_STYLE } else { SIDEBAR_LOW_STYLE }
+    }
+
     fn marker(&self) -> &'static str {
         match self.entry_type {
             ProjectEntryType::File => "  ",
@@ -195,7 +382,8 @@ impl<'a> ProjectEntryView<'a> {
 
 impl<'a> View for ProjectEntryView<'a> {
     fn width(&self) -> usize {
-        self.name.width() + self.level as usize * 2
+        let badge = self.badge().map_or(0, |badge| badge.width() + 1);
+        self.name.width() + self.level as usize * 2 + badge
     }
 
     fn height(&self) -> usize {
@@ -203,11 +391,18 @@ impl<'a> View for ProjectEntryView<'a> {
     }
 
     fn render(&self, mut canvas: Canvas) {
-        canvas.style(SIDEBAR_LOW_STYLE);
+        canvas.style(self.low_style());
         canvas.cut_left(2 * self.level as usize + 1).fill();
         canvas.cut_left(2).text(self.marker(), 0, 0);
-        canvas.style(SIDEBAR_STYLE);
-        canvas.cut_left(self.name.width()).text(self.name, 0, 0);
+        if let (Some(badge), Some(status)) = (self.badge(), self.status) {
+            let badge_canvas = canvas.cut_right(badge.width() + 1);
+            badge_canvas.style(self.status_style(status));
+            badge_canvas.fill();
+            badge_canvas.text(badge, 0, 0);
+        }
+        canvas.style(self.name_style());
+        canvas.cut_left(self.name.width()).text(&self.name, 0, 0);
+        canvas.style(self.style());
         canvas.fill();
     }
 }
@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthStr;
+
+use toolkit::*;
+use core::group::Group;
+use view::theme::*;
+
+#[derive(Debug)]
+pub struct TabsView<'a> {
+    tabs: Vec<TabView<'a>>
+}
+
+#[derive(Debug)]
+pub struct TabView<'a> {
+    name: Cow<'a, str>,
+    is_selected: bool,
+    is_focused: bool
+}
+
+impl<'a> TabsView<'a> {
+    pub fn new(group: &'a Group, is_focused: bool) -> TabsView<'a> {
+        let active = group.active_index();
+        let tabs = group.views.iter().enumerate().map(|(i, view)| TabView {
+            name: view.name(),
+            is_selected: Some(i) == active,
+            is_focused: is_focused
+        }).collect();
+        TabsView { tabs: tabs }
+    }
+
+    /// Returns index of the tab displayed at the given column.
+    pub fn tab_at(&self, x: usize) -> Option<usize> {
+        let mut left = 0;
+        for (index, tab) in self.tabs.iter().enumerate() {
+            left += tab.width() + 1;
+            if x < left {
+                return Some(index)
+            }
+        }
+        None
+    }
+}
+
+impl<'a> TabView<'a> {
+    fn style(&self) -> Style {
+        match (self.is_selected, self.is_focused) {
+            (true, true) => TABS_SELECTED_STYLE,
+            (true, false) => TABS_LOW_SELECTED_STYLE,
+            (false, true) => TABS_STYLE,
+            (false, false) => TABS_LOW_STYLE
+        }
+    }
+}
+
+impl<'a> View for TabView<'a> {
+    fn width(&self) -> usize {
+        self.name.width() + 2
+    }
+
+    fn height(&self) -> usize {
+        1
+    }
+
+    fn render(&self, canvas: Canvas) {
+        canvas.style(self.style());
+        canvas.char(' ', 0, 0);
+        canvas.text(&self.name, 0, 1);
+        canvas.char(' ', 0, self.name.width() + 1);
+    }
+}
+
+impl<'a> View for TabsView<'a> {
+    fn width(&self) -> usize {
+        self.tabs.iter().fold(0, |w, tab| w + tab.width() + 1)
+    }
+
+    fn height(&self) -> usize {
+        1
+    }
+
+    fn render(&self, mut canvas: Canvas) {
+        for tab in &self.tabs {
+            let w = tab.width();
+            if w + 1 > canvas.width() {
+                break
+            }
+            tab.render(canvas.cut_left(w));
+            canvas.style(TABS_LOW_STYLE);
+            canvas.cut_left(1).fill();
+        }
+        canvas.style(TABS_LOW_STYLE);
+        canvas.fill();
+    }
+}
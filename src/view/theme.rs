@@ -1,9 +1,13 @@
-use toolkit::{Color, ColorPair, Style, NORMAL};
+use toolkit::{Color, ColorPair, Style, NORMAL, UNDERLINE};
 
 const BRIDGEST: i8 = 1;
 const CONTRAST: i8 = 17;
 const ACCENT: i8 = 4;
 
+const YELLOW: u8 = 179;
+const GREEN: u8 = 114;
+const BLUE: u8 = 74;
+
 // editor
 pub const EDITOR_STYLE: Style = Style {
     colors: ColorPair(0),
@@ -32,6 +36,45 @@ pub const SIDEBAR_LOW_SELECTED_STYLE: Style = Style {
     attrs: NORMAL
 };
 
+// sidebar git status
+pub const SIDEBAR_MODIFIED_STYLE: Style = Style {
+    colors: ColorPair(19),
+    attrs: NORMAL
+};
+pub const SIDEBAR_MODIFIED_SELECTED_STYLE: Style = Style {
+    colors: ColorPair(20),
+    attrs: NORMAL
+};
+pub const SIDEBAR_ADDED_STYLE: Style = Style {
+    colors: ColorPair(21),
+    attrs: NORMAL
+};
+pub const SIDEBAR_ADDED_SELECTED_STYLE: Style = Style {
+    colors: ColorPair(22),
+    attrs: NORMAL
+};
+pub const SIDEBAR_UNTRACKED_STYLE: Style = Style {
+    colors: ColorPair(23),
+    attrs: NORMAL
+};
+pub const SIDEBAR_UNTRACKED_SELECTED_STYLE: Style = Style {
+    colors: ColorPair(24),
+    attrs: NORMAL
+};
+pub const SIDEBAR_IGNORED_STYLE: Style = Style {
+    colors: ColorPair(25),
+    attrs: NORMAL
+};
+pub const SIDEBAR_IGNORED_SELECTED_STYLE: Style = Style {
+    colors: ColorPair(26),
+    attrs: NORMAL
+};
+
+pub const MODIFIED_BADGE: &'static str = "M";
+pub const ADDED_BADGE: &'static str = "A";
+pub const UNTRACKED_BADGE: &'static str = "U";
+pub const IGNORED_BADGE: &'static str = "I";
+
 // tabs
 pub const TABS_STYLE: Style = Style {
     colors: ColorPair(6),
@@ -91,6 +134,14 @@ pub const MODAL_DISABLED_LOW_STYLE: Style = Style {
     colors: ColorPair(18),
     attrs: NORMAL
 };
+pub const MODAL_MATCH_STYLE: Style = Style {
+    colors: ColorPair(13),
+    attrs: UNDERLINE
+};
+pub const MODAL_SELECTED_MATCH_STYLE: Style = Style {
+    colors: ColorPair(14),
+    attrs: UNDERLINE
+};
 
 macro_rules! create_color_pair {
     ($base:expr, $accent:expr) => ((
@@ -99,7 +150,14 @@ macro_rules! create_color_pair {
     ))
 }
 
-pub const PALETTE: [(Color, Color); 19] = [// editor
+macro_rules! create_status_pair {
+    ($base:expr, $color:expr) => ((
+        Color($color),
+        Color(232 + (BRIDGEST * $base) as u8)
+    ))
+}
+
+pub const PALETTE: [(Color, Color); 27] = [// editor
                                            create_color_pair!(0, 0), // EDITOR
                                            create_color_pair!(1, 0), // EDITOR_SELECTED
 
@@ -128,4 +186,14 @@ pub const PALETTE: [(Color, Color); 19] = [// editor
                                            create_color_pair!(4, -2), // MODAL_LOW
                                            create_color_pair!(5, -2), // MODAL_LOW_SELECTED
                                            create_color_pair!(4, -2), // MODAL_DISABLED
-                                           create_color_pair!(4, -4) /* MODAL_DISABLED_LOW */];
+                                           create_color_pair!(4, -4), // MODAL_DISABLED_LOW
+
+                                           // sidebar git status
+                                           create_status_pair!(1, YELLOW), // MODIFIED
+                                           create_status_pair!(2, YELLOW), // MODIFIED_SELECTED
+                                           create_status_pair!(1, GREEN), // ADDED
+                                           create_status_pair!(2, GREEN), // ADDED_SELECTED
+                                           create_status_pair!(1, BLUE), // UNTRACKED
+                                           create_status_pair!(2, BLUE), // UNTRACKED_SELECTED
+                                           create_color_pair!(1, -2), // IGNORED
+                                           create_color_pair!(2, -2) /* IGNORED_SELECTED */];
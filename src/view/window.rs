@@ -1,11 +1,48 @@
 use toolkit::*;
 use core::Core;
 
-use core::bindings::Key;
+use std::cmp::{min, max};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command as Process, Stdio};
+
+use core::bindings::{Hotkey, Key, MouseButton};
+use core::command::Command;
+use core::layout::Layout;
+use core::region::Region;
+use core::view::SelectBy;
+use core::settings::{Settings, SettingsObject, ParseSettings};
+use core::search::{self, Search, SearchOptions};
+use core::find_in_files::Where;
+use core::fuzzy;
+use core::workspace::{Project, BuildSystem};
+use toolkit::clipboard::set_clipboard;
 
 use view::menubar::Menubar;
 use view::editor::Editor;
 use view::sidebar::Sidebar;
+use view::tabs::TabsView;
+use view::overlay::Overlay;
+use view::command_palette::CommandPalette;
+use view::goto_anything::GotoAnything;
+use view::definition_list::{self, DefinitionList};
+use view::project_list::ProjectList;
+use view::find_panel::FindPanel;
+use view::input_panel::InputPanel;
+use view::theme::*;
+
+const SIDEBAR_WIDTH: usize = 30;
+const OVERLAY_WIDTH: usize = 60;
+/// Name of the panel that shows `input_panel`
+const INPUT_PANEL: &'static str = "input";
+/// Project file suggested when the project wasn't saved yet
+const UNTITLED_PROJECT: &'static str = "untitled.sublime-project";
+
+/// Program that shows folders in the file manager of the system.
+#[cfg(target_os = "macos")]
+const FILE_MANAGER: &'static str = "open";
+#[cfg(not(target_os = "macos"))]
+const FILE_MANAGER: &'static str = "xdg-open";
 
 #[derive(Debug)]
 pub struct Window {
@@ -13,29 +50,1131 @@ pub struct Window {
     menubar: Menubar,
     editor: Editor,
     sidebar: Sidebar,
+    overlay: Option<Overlay>,
+    /// Panel shown when `core.panel` is set
+    find_panel: FindPanel,
+    /// Panel that asks for a name or a confirmation of the file operation
+    input_panel: Option<InputPanel>,
+    /// Mouse selection in progress
+    drag: Option<Drag>,
+    /// Command of the mouse binding that is performed when the button is released
+    release_command: Option<Command>,
+    is_closed: bool
+}
+
+#[derive(Debug)]
+struct Drag {
+    group: usize,
+    by: SelectBy,
+    anchor: Region,
+    keep: usize
 }
 
 impl Window {
     pub fn new(core: Core) -> Window {
+        let mut core = core;
         let menubar = Menubar::new(&core);
-        let sidebar = Sidebar::new(&core);
+        let mut sidebar = Sidebar::new(&core);
         let editor = Editor::new(&core);
+        let mut find_panel = FindPanel::new();
+        if let Some(session) = core.take_session() {
+            find_panel.set_history(session.find_history);
+            sidebar.close_folders(&core, &session.closed_folders);
+        }
+        // the menu already shows the current history
+        core.take_history_change();
         Window {
             core: core,
             menubar: menubar,
             sidebar: sidebar,
-            editor: editor
+            editor: editor,
+            overlay: None,
+            find_panel: find_panel,
+            input_panel: None,
+            drag: None,
+            release_command: None,
+            is_closed: false
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    pub fn on_keypress(&mut self, mut canvas: Canvas, hotkey: Hotkey) {
+        let screen = canvas;
+        let mut processed = hotkey.modifiers().is_empty() &&
+            self.menubar.on_keypress(&self.core, canvas.cut_top(1), hotkey.key());
+        let mut changed = false;
+        if !processed && hotkey.modifiers().is_empty() {
+            if let Some(ref mut overlay) = self.overlay {
+                processed = overlay.on_keypress(&self.core, canvas, hotkey.key());
+                changed = processed;
+            }
+        }
+        if !processed && hotkey.modifiers().is_empty() && self.sidebar.is_focused() {
+            processed = self.sidebar.on_keypress(&self.core, canvas, hotkey.key());
+            changed = processed;
+        }
+        if !processed && hotkey.modifiers().is_empty() && self.shown_input_panel().is_some() {
+            if let Some(ref mut panel) = self.input_panel {
+                processed = panel.on_keypress(&self.core, canvas, hotkey.key());
+                changed = processed;
+            }
+        } else if !processed && hotkey.modifiers().is_empty() && self.core.panel.is_some() {
+            let input = self.find_panel.input().to_owned();
+            processed = self.find_panel.on_keypress((), canvas, hotkey.key());
+            changed = processed;
+            if self.find_panel.input() != input {
+                self.find_incremental();
+            }
+        }
+        if !processed {
+            if let Some(command) = self.core.hotkeys.perform_hotkey(&self.core, &hotkey) {
+                self.core.run_command(command);
+            } else if let (Key::Char(c), true) = (hotkey.key(), hotkey.modifiers().is_empty()) {
+                // unbinded characters are typed into the active view
+                let mut args = SettingsObject::new();
+                args.insert("characters".to_owned(), Settings::String(c.to_string()));
+                self.core.run_command(Command::new("insert", args));
+            }
+        }
+        if self.perform_commands() || changed {
+            self.render(screen);
+        }
+    }
+
+    /// Shows the results of the background work, while there is no input.
+    pub fn on_idle(&mut self, canvas: Canvas) {
+        let mut changed = self.core.update_find_results();
+        changed |= self.core.update_build();
+        changed |= self.core.update_diff_bases();
+        if self.core.update_watched() {
+            self.sidebar.refresh(&self.core);
+            changed = true;
+        }
+        if self.core.update_vcs() {
+            self.sidebar.refresh(&self.core);
+            changed = true;
+        }
+        // modified files changed on disk are asked about one at a time
+        if self.core.panel.is_none() {
+            if let Some(path) = self.core.take_dirty_change() {
+                self.ask_revert(path);
+                changed = true;
+            }
+        }
+        if changed {
+            self.render(canvas);
+        }
+    }
+
+    pub fn on_mouse(&mut self, mut canvas: Canvas, event: MouseEvent) {
+        let screen = canvas;
+        let menubar = canvas.cut_top(1);
+        canvas.cut_bottom(self.panel_height());
+        let sidebar = canvas.cut_left(self.sidebar_width());
+        // dragging selection captures the mouse
+        if self.drag.is_some() ||
+           (!self.menubar.on_mouse(&self.core, menubar, event) &&
+            !self.sidebar.on_mouse(&self.core, sidebar, event)) {
+            if event.action == MouseAction::Press {
+                self.sidebar.unfocus(&self.core);
+            }
+            self.on_groups_mouse(canvas, event);
+        }
+        self.perform_commands();
+        self.render(screen);
+    }
+
+    fn on_groups_mouse(&mut self, canvas: Canvas, event: MouseEvent) {
+        if let Some(drag) = self.drag.take() {
+            // selection is dragged even outside of the view
+            let (mut editor, _) = self.group_canvas(canvas, drag.group);
+            editor.cut_top(1);
+            let (x, y) = editor.relative(event.x, event.y);
+            if let Some(view) = self.core.groups[drag.group].active_view_mut() {
+                let point = view.point_at(x.saturating_sub(view.gutter_width()), y);
+                view.drag_to(drag.anchor, drag.keep, point, drag.by);
+                view.show(point);
+                if event.action == MouseAction::Release {
+                    view.normalize_selection();
+                }
+            }
+            if event.action == MouseAction::Drag {
+                self.drag = Some(drag);
+                return
+            }
+        }
+        if event.action == MouseAction::Release {
+            if let Some(command) = self.release_command.take() {
+                self.core.run_command(command);
+            }
+        }
+        if event.action != MouseAction::Press {
+            return
+        }
+        for index in 0..self.core.num_groups() {
+            let (mut editor, _) = self.group_canvas(canvas, index);
+            if !editor.contains(event.x, event.y) {
+                continue
+            }
+            let tabs = editor.cut_top(1);
+            if tabs.contains(event.x, event.y) {
+                if event.button == MouseButton::Button(1) {
+                    let (x, _) = tabs.relative(event.x, event.y);
+                    let tab = TabsView::new(&self.core.groups[index], true).tab_at(x);
+                    self.core.focus_group(index);
+                    if let Some(tab) = tab {
+                        self.core.group_mut().select(tab);
+                    }
+                }
+                return
+            }
+            let (x, y) = editor.relative(event.x, event.y);
+            let binding = self.core.mouse_binding(event.button, event.count, event.modifiers)
+                .map(|binding| (binding.press_command.clone(), binding.command.clone()));
+            match binding {
+                Some((press_command, command)) => {
+                    self.core.focus_group(index);
+                    if let Some(command) = press_command {
+                        self.core.run_command(with_event(command, x, y));
+                    }
+                    self.release_command = command.map(|command| with_event(command, x, y));
+                },
+                None => {
+                    let amount = match event.button {
+                        MouseButton::ScrollUp => 3,
+                        MouseButton::ScrollDown => -3,
+                        _ => return
+                    };
+                    if let Some(view) = self.core.groups[index].active_view_mut() {
+                        view.scroll_lines(amount);
+                    }
+                }
+            }
+            return
+        }
+    }
+
+    /// Performs commands scheduled by widgets, including commands scheduled while performing.
+    /// Returns false if there was nothing to perform.
+    fn perform_commands(&mut self) -> bool {
+        let mut commands = self.core.take_commands();
+        if commands.is_empty() {
+            return false
+        }
+        self.menubar.close(&self.core);
+        self.sidebar.close_menu(&self.core);
+        while !commands.is_empty() {
+            for command in commands {
+                self.perform_command(command);
+            }
+            commands = self.core.take_commands();
+        }
+        // recent items of the menu follow the history
+        if self.core.take_history_change() {
+            self.menubar = Menubar::new(&self.core);
+        }
+        true
+    }
+
+    fn perform_command(&mut self, command: Command) {
+        let Command { name, args } = command;
+        match &name[..] {
+            "exit" => {
+                self.save_session();
+                self.is_closed = true;
+            },
+            "set_layout" => match Layout::parse_settings(Settings::Object(args)) {
+                Ok(layout) => self.core.set_layout(layout),
+                Err(err) => error!("Incorrect layout: {:?}", err)
+            },
+            "focus_group" => {
+                if let Some(group) = args.get("group").and_then(Settings::as_u64) {
+                    self.core.focus_group(group as usize);
+                }
+            },
+            "move_to_group" => {
+                if let Some(group) = args.get("group").and_then(Settings::as_u64) {
+                    self.core.move_to_group(group as usize);
+                }
+            },
+            "focus_neighboring_group" => {
+                let forward = args.get("forward").and_then(Settings::as_boolean).unwrap_or(true);
+                self.core.focus_neighboring_group(forward);
+            },
+            "select_by_index" => {
+                if let Some(index) = args.get("index").and_then(Settings::as_u64) {
+                    self.core.group_mut().select(index as usize);
+                }
+            },
+            "next_view" => self.core.group_mut().next_view(),
+            "prev_view" => self.core.group_mut().prev_view(),
+            "clone_file" => self.core.clone_file(),
+            "close" | "close_file" => {
+                let confirmed = args.get("confirm").and_then(Settings::as_boolean) == Some(true);
+                let modified = self.core.active_view()
+                    .map(|view| (view.is_dirty(), view.name().into_owned()));
+                match modified {
+                    Some((true, file_name)) if !confirmed => {
+                        let question = format!("{} is modified, close it without saving? \
+                                                Enter: close, Escape: cancel", file_name);
+                        self.prompt(InputPanel::question(question, Command::new(&name, args)));
+                    },
+                    _ => self.core.close_view()
+                }
+            },
+            "reopen_last_file" => self.core.reopen_last_file(),
+            "open_recent_file" | "open_recent_folder" | "open_recent_project" => {
+                let command = Command::new(&name, args);
+                if let Some(path) = self.core.recent_path(&command).map(Path::to_path_buf) {
+                    self.open_recent(&name, path);
+                }
+            },
+            "clear_recent_files" => self.core.clear_recent_files(),
+            "clear_recent_projects" => self.core.clear_recent_projects(),
+            "prompt_select_project" => {
+                self.overlay = Some(Overlay::Projects(ProjectList::new(&self.core)));
+            },
+            "open_file" => {
+                let transient = args.get("transient").and_then(Settings::as_boolean);
+                if let Some(file) = args.get("file").and_then(Settings::as_string) {
+                    self.core.open_file(PathBuf::from(file), transient.unwrap_or(false));
+                    if let Some(line) = args.get("line").and_then(Settings::as_u64) {
+                        let column = args.get("column").and_then(Settings::as_u64).unwrap_or(1);
+                        self.core.goto_position(line as usize, column as usize);
+                    }
+                }
+            },
+            "drag_select" => self.drag_select(&args),
+            "show_overlay" => self.show_overlay(&args),
+            "hide_overlay" => {
+                self.overlay = None;
+                self.core.close_transient();
+            },
+            "goto_line" => {
+                if let (Some(view), Some(line)) = (self.core.active_view_mut(),
+                                                   args.get("line").and_then(Settings::as_u64)) {
+                    let point = view.buffer().clip_point((line as usize).saturating_sub(1), 0);
+                    view.select_region(Region::caret(point));
+                }
+            },
+            "goto_symbol" => {
+                // symbol is chosen by the index in the list or by the best match of the name
+                let region = self.core.active_view().and_then(|view| {
+                    let symbols = view.symbols(&self.core.symbol_list);
+                    let index = match (args.get("index").and_then(Settings::as_u64),
+                                       args.get("symbol").and_then(Settings::as_string)) {
+                        (Some(index), _) => index as usize,
+                        (None, Some(name)) => {
+                            let names = symbols.iter().map(|symbol| &symbol.name[..]);
+                            match fuzzy::filter(name, names).first() {
+                                Some(&(index, _)) => index,
+                                None => return None
+                            }
+                        },
+                        _ => return None
+                    };
+                    symbols.get(index).map(|symbol| symbol.region)
+                });
+                if let (Some(view), Some(region)) = (self.core.active_view_mut(), region) {
+                    view.select_region(region);
+                }
+            },
+            "goto_definition" => self.goto_definition(),
+            "goto_symbol_in_project" => {
+                let definitions = self.core.symbol_index.definitions();
+                let list = DefinitionList::new(&self.core, definitions);
+                self.overlay = Some(Overlay::Definitions(list));
+            },
+            "goto_text" => {
+                if let (Some(view), Some(text)) = (self.core.active_view_mut(),
+                                                   args.get("text").and_then(Settings::as_string)) {
+                    let region = view.buffer().find_text(text, false, false);
+                    if let Some(region) = region {
+                        view.select_region(region);
+                    }
+                }
+            },
+            "toggle_side_bar" => self.sidebar.toggle(),
+            "focus_side_bar" => self.sidebar.focus(&self.core),
+            "toggle_show_open_files" => self.sidebar.toggle_open_files(),
+            "reveal_in_side_bar" => {
+                let path = self.core.active_view()
+                    .and_then(|view| view.buffer().path().map(Path::to_path_buf));
+                if let Some(path) = path {
+                    self.sidebar.reveal(&self.core, &path);
+                }
+            },
+            "new_file_at" | "new_folder" | "rename_path" | "delete_file" | "delete_folder" => {
+                self.change_files(Command::new(&name, args));
+            },
+            "find_in_folder" => {
+                self.find_panel.clear_location();
+                for folder in path_args(&args, "dirs") {
+                    self.find_panel.add_location(&folder.to_string_lossy());
+                }
+                let mut args = SettingsObject::new();
+                args.insert("panel".to_owned(), Settings::String("find_in_files".to_owned()));
+                self.show_panel(&args);
+                self.sidebar.unfocus(&self.core);
+            },
+            "open_containing_folder" => {
+                for file in path_args(&args, "files") {
+                    match file.parent() {
+                        Some(folder) if folder != Path::new("") => open_folder(folder),
+                        _ => open_folder(Path::new("."))
+                    }
+                }
+            },
+            "copy_path" => {
+                let paths: Vec<String> = path_args(&args, "paths").into_iter().map(|path| {
+                    let path = env::current_dir().map(|dir| dir.join(&path)).unwrap_or(path);
+                    path.to_string_lossy().into_owned()
+                }).collect();
+                set_clipboard(&paths.join("\n"));
+            },
+            "revert" => {
+                let path = match args.get("file").and_then(Settings::as_string) {
+                    Some(file) => Some(PathBuf::from(file)),
+                    None => self.core.active_view()
+                        .and_then(|view| view.buffer().path().map(Path::to_path_buf))
+                };
+                if let Some(path) = path {
+                    self.core.revert(&path);
+                }
+            },
+            "refresh_folder_list" => {
+                self.core.refresh_folders();
+                self.sidebar.refresh(&self.core);
+            },
+            "remove_folder" => {
+                for folder in path_args(&args, "dirs") {
+                    self.core.remove_folder(&folder);
+                }
+                self.sidebar.refresh(&self.core);
+            },
+            "prompt_add_folder" => {
+                let command = Command::new("add_directory", SettingsObject::new());
+                self.prompt(InputPanel::input("Folder Path:", "", command));
+            },
+            "add_directory" => {
+                let mut folders = path_args(&args, "dirs");
+                match args.get("name").and_then(Settings::as_string) {
+                    Some(name) if !name.trim().is_empty() => {
+                        folders.push(PathBuf::from(name.trim()));
+                    },
+                    _ => {}
+                }
+                for folder in folders {
+                    self.core.add_folder(&folder);
+                }
+                self.sidebar.refresh(&self.core);
+            },
+            "save_project_as" => match args.get("name").and_then(Settings::as_string) {
+                Some(name) if !name.trim().is_empty() => {
+                    self.core.save_project_as(PathBuf::from(name.trim()));
+                },
+                Some(_) => {},
+                None => {
+                    let path = match self.core.project.path {
+                        Some(ref path) => path.to_string_lossy().into_owned(),
+                        None => UNTITLED_PROJECT.to_owned()
+                    };
+                    let command = Command::new("save_project_as", args);
+                    self.prompt(InputPanel::input("Project Path:", &path, command));
+                }
+            },
+            "show_panel" => self.show_panel(&args),
+            "hide_panel" => {
+                self.core.panel = None;
+                self.core.pending_replacements = None;
+                self.input_panel = None;
+            },
+            "find_next" => self.find(true),
+            "find_prev" => self.find(false),
+            "find_all" => {
+                if let Some(search) = self.find_panel.search() {
+                    self.find_panel.add_history();
+                    self.find_all(&search);
+                }
+                if args.get("close_panel").and_then(Settings::as_boolean) == Some(true) {
+                    self.core.panel = None;
+                }
+            },
+            "find_in_files" => self.find_in_files(),
+            "replace_in_files" => {
+                if args.get("confirm").and_then(Settings::as_boolean) == Some(true) {
+                    self.core.replace_in_files();
+                    self.core.panel = None;
+                } else {
+                    self.prepare_replace_in_files();
+                }
+            },
+            "build" => {
+                let variant = args.get("variant").and_then(Settings::as_string);
+                self.core.build(variant);
+            },
+            "exec" => {
+                if args.get("kill").and_then(Settings::as_boolean) == Some(true) {
+                    self.core.kill_build();
+                } else {
+                    match BuildSystem::parse_settings(Settings::Object(args)) {
+                        Ok(build_system) => self.core.exec(&build_system),
+                        Err(err) => error!("Incorrect build arguments: {:?}", err)
+                    }
+                }
+            },
+            "set_build_system" => {
+                let index = args.get("index").and_then(Settings::as_u64);
+                self.core.set_build_system(index.map(|index| index as usize));
+            },
+            "next_result" => self.core.next_result(true),
+            "prev_result" => self.core.next_result(false),
+            "clear_location" => self.find_panel.clear_location(),
+            "add_where_snippet" => {
+                if let Some(snippet) = args.get("snippet").and_then(Settings::as_string) {
+                    self.find_panel.add_location(&expand_snippet(snippet));
+                }
+            },
+            "find_under" => self.find_under(true),
+            "find_under_prev" => self.find_under(false),
+            "find_all_under" => {
+                if let Some(search) = self.search_under() {
+                    self.find_all(&search);
+                }
+            },
+            "replace_next" => {
+                if let Some(search) = self.find_panel.search() {
+                    self.find_panel.add_history();
+                    let format = self.find_panel.replacement();
+                    self.core.edit(|view| view.replace_next(&search, format));
+                }
+            },
+            "replace_all" => {
+                if let Some(search) = self.find_panel.search() {
+                    self.find_panel.add_history();
+                    let format = self.find_panel.replacement();
+                    self.core.edit(|view| view.replace_all(&search, format));
+                }
+                if args.get("close_panel").and_then(Settings::as_boolean) == Some(true) {
+                    self.core.panel = None;
+                }
+            },
+            "undo" => self.core.edit(|view| view.undo()),
+            "revert_hunk" => self.core.edit(|view| view.revert_hunks()),
+            "next_modification" | "prev_modification" => {
+                if let Some(view) = self.core.active_view_mut() {
+                    view.move_to_modification(name == "next_modification");
+                }
+            },
+            "redo" | "redo_or_repeat" => self.core.edit(|view| view.redo()),
+            "slurp_find_string" => {
+                if let Some((text, _)) = self.selected_text() {
+                    self.find_panel.set_input(&text);
+                }
+            },
+            "toggle_regex" | "toggle_case_sensitive" | "toggle_whole_word" |
+            "toggle_in_selection" | "toggle_preserve_case" => {
+                {
+                    let options = &mut self.find_panel.options;
+                    let option = match &name[..] {
+                        "toggle_regex" => &mut options.regex,
+                        "toggle_case_sensitive" => &mut options.case_sensitive,
+                        "toggle_whole_word" => &mut options.whole_word,
+                        "toggle_preserve_case" => &mut options.preserve_case,
+                        _ => &mut options.in_selection
+                    };
+                    *option = !*option;
+                }
+                self.find_incremental();
+            },
+            "toggle_setting" => {
+                if let (Some(view), Some(setting)) = (self.core.active_view_mut(),
+                                                      args.get("setting").and_then(Settings::as_string)) {
+                    if !view.preferences.toggle(setting) {
+                        warn!("Unsupported setting: {}", setting);
+                    }
+                }
+            },
+            "insert" => {
+                if let Some(text) = args.get("characters").and_then(Settings::as_string) {
+                    self.core.edit(|view| view.insert(text));
+                    self.show_caret();
+                }
+            },
+            "left_delete" => {
+                self.core.edit(|view| view.left_delete());
+                self.show_caret();
+            },
+            "right_delete" => {
+                self.core.edit(|view| view.right_delete());
+                self.show_caret();
+            },
+            "move" => {
+                let forward = args.get("forward").and_then(Settings::as_boolean).unwrap_or(true);
+                let extend = args.get("extend").and_then(Settings::as_boolean).unwrap_or(false);
+                if let Some(view) = self.core.active_view_mut() {
+                    match args.get("by").and_then(Settings::as_string) {
+                        Some("characters") => view.move_by_characters(forward, extend),
+                        Some("lines") => view.move_by_lines(forward, extend),
+                        Some("pages") => view.move_by_pages(forward, extend),
+                        by => warn!("Unsupported move: {:?}", by)
+                    }
+                    view.show_caret();
+                }
+            },
+            "move_to" => {
+                let extend = args.get("extend").and_then(Settings::as_boolean).unwrap_or(false);
+                if let (Some(view), Some(to)) = (self.core.active_view_mut(),
+                                                 args.get("to").and_then(Settings::as_string)) {
+                    view.move_to(to, extend);
+                    view.show_caret();
+                }
+            },
+            "scroll_lines" => {
+                if let (Some(view), Some(amount)) = (self.core.active_view_mut(),
+                                                     args.get("amount").and_then(Settings::as_f64)) {
+                    view.scroll_lines(amount as isize);
+                }
+            },
+            "show_at_center" => {
+                if let Some(view) = self.core.active_view_mut() {
+                    if let Some(region) = view.selection.last().cloned() {
+                        view.show_at_center(region.b);
+                    }
+                }
+            },
+            _ => warn!("Unknown command: {}", name)
+        }
+    }
+
+    /// Checks whether the command is supported and can be performed now.
+    fn is_enabled(&self, command: &Command) -> bool {
+        let has_view = self.core.active_view().is_some();
+        match &command.name[..] {
+            "exit" | "set_layout" | "focus_group" | "move_to_group" | "focus_neighboring_group" |
+            "open_file" | "show_overlay" | "hide_overlay" | "goto_symbol_in_project" |
+            "show_panel" | "hide_panel" | "toggle_regex" | "toggle_case_sensitive" |
+            "toggle_whole_word" | "toggle_in_selection" | "toggle_preserve_case" |
+            "find_in_files" | "replace_in_files" | "next_result" | "prev_result" |
+            "clear_location" | "add_where_snippet" | "toggle_side_bar" | "focus_side_bar" |
+            "toggle_show_open_files" => true,
+            "new_file_at" | "new_folder" | "delete_folder" | "find_in_folder" | "remove_folder" =>
+                !path_args(&command.args, "dirs").is_empty(),
+            "delete_file" | "open_containing_folder" =>
+                !path_args(&command.args, "files").is_empty(),
+            "rename_path" | "copy_path" => !path_args(&command.args, "paths").is_empty(),
+            "refresh_folder_list" | "prompt_add_folder" | "add_directory" |
+            "save_project_as" | "reopen_last_file" | "clear_recent_files" |
+            "clear_recent_projects" | "build" | "exec" => true,
+            "set_build_system" => {
+                !command.args.contains_key("index") ||
+                    self.core.indexed_build_system(command).is_some()
+            },
+            "open_recent_file" | "open_recent_folder" | "open_recent_project" => {
+                self.core.recent_path(command).is_some()
+            },
+            "prompt_select_project" => !self.core.history().projects().is_empty(),
+            "revert" => has_view || command.args.contains_key("file"),
+            "select_by_index" | "next_view" | "prev_view" | "clone_file" | "insert" |
+            "left_delete" | "right_delete" | "move" | "move_to" | "scroll_lines" |
+            "show_at_center" | "drag_select" | "goto_line" | "goto_symbol" |
+            "goto_text" | "goto_definition" | "find_next" | "find_prev" | "find_all" |
+            "find_under" | "find_under_prev" | "find_all_under" | "slurp_find_string" |
+            "replace_next" | "replace_all" | "undo" | "redo" | "redo_or_repeat" |
+            "revert_hunk" | "next_modification" | "prev_modification" |
+            "reveal_in_side_bar" | "close" | "close_file" => has_view,
+            "toggle_setting" => {
+                let setting = command.args.get("setting").and_then(Settings::as_string);
+                match (self.core.active_view(), setting) {
+                    (Some(view), Some(setting)) => view.preferences.get_bool(setting).is_some(),
+                    _ => false
+                }
+            },
+            _ => false
+        }
+    }
+
+    fn show_overlay(&mut self, args: &SettingsObject) {
+        match args.get("overlay").and_then(Settings::as_string) {
+            Some("command_palette") => {
+                let list = self.core.create_command_list().into_iter()
+                    .filter(|item| self.is_enabled(&item.command))
+                    .collect();
+                self.overlay = Some(Overlay::CommandPalette(CommandPalette::new(&self.core, list)));
+            },
+            Some("goto") => {
+                let show_files = args.get("show_files").and_then(Settings::as_boolean);
+                let files = if show_files == Some(true) {
+                    self.core.project.text_files()
+                } else {
+                    Vec::new()
+                };
+                let symbols = match self.core.active_view() {
+                    Some(view) => view.symbols(&self.core.symbol_list),
+                    None => Vec::new()
+                };
+                let text = args.get("text").and_then(Settings::as_string).unwrap_or("");
+                self.overlay = Some(Overlay::Goto(GotoAnything::new(files, symbols, text)));
+            },
+            overlay => warn!("Unsupported overlay: {:?}", overlay)
+        }
+    }
+
+    fn show_panel(&mut self, args: &SettingsObject) {
+        let panel = match args.get("panel").and_then(Settings::as_string) {
+            Some(panel @ "find") | Some(panel @ "incremental_find") |
+            Some(panel @ "replace") | Some(panel @ "find_in_files") => panel,
+            Some("output.exec") => {
+                self.core.show_output_view();
+                return
+            },
+            panel => {
+                warn!("Unsupported panel: {:?}", panel);
+                return
+            }
+        };
+        let toggle = args.get("toggle").and_then(Settings::as_boolean).unwrap_or(false);
+        if toggle && self.core.panel.as_ref().map_or(false, |shown| shown == panel) {
+            self.core.panel = None;
+            return
+        }
+        let reverse = args.get("reverse").and_then(Settings::as_boolean).unwrap_or(false);
+        self.find_panel.show(panel, self.core.active_view(), reverse);
+        self.core.panel = Some(panel.to_owned());
+    }
+
+    /// Returns the input panel, if it's shown instead of the find panel.
+    fn shown_input_panel(&self) -> Option<&InputPanel> {
+        match self.core.panel {
+            Some(ref panel) if panel == INPUT_PANEL => self.input_panel.as_ref(),
+            _ => None
+        }
+    }
+
+    fn panel_height(&self) -> usize {
+        match self.shown_input_panel() {
+            Some(panel) => panel.view(&self.core).height(),
+            None if self.core.panel.is_some() => self.find_panel.view(()).height(),
+            None => 0
+        }
+    }
+
+    /// Shows the input panel, the typed text goes to it instead of the sidebar.
+    fn prompt(&mut self, panel: InputPanel) {
+        self.input_panel = Some(panel);
+        self.core.panel = Some(INPUT_PANEL.to_owned());
+        self.sidebar.unfocus(&self.core);
+    }
+
+    /// Asks whether to reload the modified file that was changed on disk.
+    fn ask_revert(&mut self, path: PathBuf) {
+        let name = path.file_name().map_or(String::new(), |name| {
+            name.to_string_lossy().into_owned()
+        });
+        let mut args = SettingsObject::new();
+        args.insert("file".to_owned(), Settings::String(path.to_string_lossy().into_owned()));
+        let question = format!("{} has changed on disk, reload it? Enter: reload, Escape: keep",
+                               name);
+        self.prompt(InputPanel::question(question, Command::new("revert", args)));
+    }
+
+    /// Creates, renames or deletes the file or the folder given in the command arguments.
+    /// The name or the confirmation is asked in the input panel first, then the command
+    /// is performed again with the answer. Failure is shown in the input panel.
+    fn change_files(&mut self, command: Command) {
+        let kind = command.name.clone();
+        let paths = path_args(&command.args, match &kind[..] {
+            "new_file_at" | "new_folder" | "delete_folder" => "dirs",
+            "delete_file" => "files",
+            _ => "paths"
+        });
+        let path = match paths.first() {
+            Some(path) => path.clone(),
+            None => return
+        };
+        let file_name = path.file_name().map_or(String::new(), |name| {
+            name.to_string_lossy().into_owned()
+        });
+        let name = command.args.get("name").and_then(Settings::as_string).map(str::to_owned);
+        let confirmed = command.args.get("confirm").and_then(Settings::as_boolean) == Some(true);
+        let result = match (&kind[..], name) {
+            (_, Some(ref name)) if name.trim().is_empty() => return,
+            ("new_file_at", Some(name)) => self.core.create_file(&path, &name),
+            ("new_folder", Some(name)) => self.core.create_folder(&path, &name),
+            ("rename_path", Some(name)) => self.core.rename_path(&path, &path.with_file_name(name)),
+            ("delete_file", _) | ("delete_folder", _) if confirmed => {
+                paths.iter().map(|path| self.core.delete_path(path)).collect()
+            },
+            ("new_file_at", None) => {
+                return self.prompt(InputPanel::input("File Name:", "", command))
+            },
+            ("new_folder", None) => {
+                return self.prompt(InputPanel::input("Folder Name:", "", command))
+            },
+            ("rename_path", None) => {
+                return self.prompt(InputPanel::input("New Name:", &file_name, command))
+            },
+            _ => {
+                let question = match paths.len() {
+                    1 => format!("Delete {}? Enter: delete, Escape: cancel", file_name),
+                    count => format!("Delete {} items? Enter: delete, Escape: cancel", count)
+                };
+                return self.prompt(InputPanel::question(question, command))
+            }
+        };
+        self.sidebar.refresh(&self.core);
+        if let Err(err) = result {
+            let action = match &kind[..] {
+                "new_file_at" => "create file in",
+                "new_folder" => "create folder in",
+                "rename_path" => "rename",
+                _ => "delete"
+            };
+            let message = format!("Can't {} {}: {}. Enter: close", action, file_name, err);
+            self.prompt(InputPanel::message(message));
         }
     }
 
-    pub fn on_keypress(&mut self, mut canvas: Canvas, key: Key) {
-        self.menubar.on_keypress(&self.core, canvas.cut_top(1), key);
+    /// Opens the recent file, or switches the window to the recent folder or project.
+    fn open_recent(&mut self, kind: &str, path: PathBuf) {
+        let project = match kind {
+            "open_recent_file" => return self.core.open_file(path, false),
+            "open_recent_folder" => {
+                let mut project = Project::new();
+                project.add_folder(&path).map(|_| project)
+            },
+            _ => Project::open(path.clone())
+        };
+        match project {
+            Ok(project) => self.open_project(project),
+            Err(err) => error!("Can't open {:?}: {:?}", path, err)
+        }
+    }
+
+    /// Switches the window to another project, the open files stay open. Session of the
+    /// previous project is saved, the find history and the collapsed folders of the new
+    /// one are restored.
+    fn open_project(&mut self, project: Project) {
+        self.save_session();
+        self.core.open_project(project);
+        self.sidebar.refresh(&self.core);
+        if let Some(session) = self.core.take_session() {
+            self.find_panel.set_history(session.find_history);
+            self.sidebar.close_folders(&self.core, &session.closed_folders);
+        }
+    }
+
+    /// Saves the open files, the layout, the find history and the collapsed folders
+    /// of the sidebar, they're restored on the next start.
+    fn save_session(&self) {
+        let mut session = self.core.session();
+        session.find_history = self.find_panel.history().to_vec();
+        session.closed_folders = self.sidebar.closed_folders();
+        self.core.save_session(&session);
+    }
+
+    /// Selects the next match of the find panel search in the active view.
+    fn find(&mut self, forward: bool) {
+        let search = match self.find_panel.search() {
+            Some(search) => search,
+            None => return
+        };
+        self.find_panel.add_history();
+        if let Some(view) = self.core.active_view_mut() {
+            if !view.find(&search, forward) {
+                warn!("Unable to find: {:?}", self.find_panel.input());
+            }
+        }
+    }
+
+    /// Selects the first match after the point the find panel was shown at,
+    /// so the match is updated while the pattern is typed.
+    fn find_incremental(&mut self) {
+        match self.core.panel.as_ref().map(|panel| &panel[..]) {
+            Some("find") | Some("incremental_find") | Some("replace") => {},
+            _ => return
+        }
+        let search = match self.find_panel.search() {
+            Some(search) => search,
+            None => return
+        };
+        let (origin, forward) = self.find_panel.origin();
+        if let Some(view) = self.core.active_view_mut() {
+            view.find_from(&search, origin, forward);
+        }
+    }
+
+    fn find_all(&mut self, search: &Search) {
+        if let Some(view) = self.core.active_view_mut() {
+            if !view.find_all(search) {
+                warn!("Unable to find: {:?}", self.find_panel.input());
+            }
+        }
     }
 
+    /// Searches the files given in the find panel, the panel is hidden when the search starts.
+    fn find_in_files(&mut self) {
+        let location = match Where::parse(self.find_panel.location()) {
+            Ok(location) => location,
+            Err(err) => {
+                warn!("Incorrect location: {:?}", err);
+                return
+            }
+        };
+        let pattern = self.find_panel.input().to_owned();
+        if pattern.is_empty() {
+            return
+        }
+        self.find_panel.add_history();
+        match self.core.find_in_files(&pattern, self.find_panel.options, &location) {
+            Ok(()) => self.core.panel = None,
+            Err(err) => warn!("Incorrect pattern: {:?}", err)
+        }
+    }
+
+    /// Shows the preview of the replacements in the files given in the find panel,
+    /// they're performed when confirmed in the panel.
+    fn prepare_replace_in_files(&mut self) {
+        let location = match Where::parse(self.find_panel.location()) {
+            Ok(location) => location,
+            Err(err) => {
+                warn!("Incorrect location: {:?}", err);
+                return
+            }
+        };
+        let pattern = self.find_panel.input().to_owned();
+        if pattern.is_empty() {
+            return
+        }
+        self.find_panel.add_history();
+        let format = self.find_panel.replacement();
+        let options = self.find_panel.options;
+        match self.core.prepare_replace_in_files(&pattern, format, options, &location) {
+            Ok(0) => warn!("Unable to find: {:?}", pattern),
+            Ok(count) => {
+                let question = format!("Replace {} matches? Enter: replace, Escape: cancel", count);
+                self.find_panel.confirm(question);
+                self.core.panel = Some("replace_in_files".to_owned());
+            },
+            Err(err) => warn!("Incorrect pattern: {:?}", err)
+        }
+    }
+
+    /// Returns the text of the last selected region, or the word under the caret.
+    /// The flag tells whether it's the word.
+    fn selected_text(&self) -> Option<(String, bool)> {
+        let view = match self.core.active_view() {
+            Some(view) => view,
+            None => return None
+        };
+        view.selection.last().map(|region| {
+            let buffer = view.buffer();
+            if region.is_empty() {
+                let word = buffer.word_region(region.b);
+                (buffer.substr(word.begin(), word.end()), true)
+            } else {
+                (buffer.substr(region.begin(), region.end()), false)
+            }
+        })
+    }
+
+    /// Returns case sensitive search for the selected text, or the whole word under the caret.
+    /// The text becomes the pattern of the find panel.
+    fn search_under(&mut self) -> Option<Search> {
+        let (text, is_word) = match self.selected_text() {
+            Some(selected) => selected,
+            None => return None
+        };
+        if self.find_panel.options.regex {
+            self.find_panel.set_input(&search::escape(&text));
+        } else {
+            self.find_panel.set_input(&text);
+        }
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: is_word,
+            ..SearchOptions::default()
+        };
+        Search::new(&text, options, &[]).ok()
+    }
+
+    fn find_under(&mut self, forward: bool) {
+        let search = match self.search_under() {
+            Some(search) => search,
+            None => return
+        };
+        if let Some(view) = self.core.active_view_mut() {
+            view.find(&search, forward);
+        }
+    }
+
+    /// Opens definition of the word under the last caret. When there are several definitions,
+    /// they're listed in the overlay.
+    fn goto_definition(&mut self) {
+        let name = match self.selected_text() {
+            Some((name, _)) => name,
+            None => return
+        };
+        let definitions = self.core.symbol_index.find(&name);
+        match definitions.len() {
+            0 => warn!("Definition is not found: {:?}", name),
+            1 => self.core.run_command(definition_list::open_command(&definitions[0], false)),
+            _ => {
+                let list = DefinitionList::new(&self.core, definitions);
+                self.overlay = Some(Overlay::Definitions(list));
+            }
+        }
+    }
+
+    fn drag_select(&mut self, args: &SettingsObject) {
+        let flag = |name: &str| args.get(name).and_then(Settings::as_boolean).unwrap_or(false);
+        let by = match args.get("by").and_then(Settings::as_string) {
+            Some("words") => SelectBy::Words,
+            Some("lines") => SelectBy::Lines,
+            Some("columns") => SelectBy::Columns,
+            _ => SelectBy::Characters
+        };
+        let position = args.get("event").and_then(|event| {
+            match (event.find("x").and_then(Settings::as_u64),
+                   event.find("y").and_then(Settings::as_u64)) {
+                (Some(x), Some(y)) => Some((x as usize, y as usize)),
+                _ => None
+            }
+        });
+        let (x, y) = match position {
+            Some(position) => position,
+            None => {
+                warn!("drag_select requires mouse event");
+                return
+            }
+        };
+        let group = self.core.active_group();
+        if let Some(view) = self.core.active_view_mut() {
+            let point = view.point_at(x.saturating_sub(view.gutter_width()), y);
+            let drag = view.drag_select(point, by, flag("additive"), flag("subtractive"), flag("extend"));
+            self.drag = drag.map(|(anchor, keep)| Drag {
+                group: group,
+                by: by,
+                anchor: anchor,
+                keep: keep
+            });
+        }
+    }
+
+    fn show_caret(&mut self) {
+        if let Some(view) = self.core.active_view_mut() {
+            view.show_caret();
+        }
+    }
 
     pub fn render(&self, mut canvas: Canvas) {
+        let screen = canvas;
         self.menubar.view(&self.core).render(canvas.cut_top(1));
-        self.sidebar.view(&self.core).render(canvas.cut_left(30));
-        self.editor.view(&self.core).render(canvas);
+        let search = if let Some(panel) = self.shown_input_panel() {
+            let view = panel.view(&self.core);
+            view.render(canvas.cut_bottom(view.height()));
+            None
+        } else if self.core.panel.is_some() {
+            let panel = self.find_panel.view(());
+            panel.render(canvas.cut_bottom(panel.height()));
+            self.find_panel.search()
+        } else {
+            None
+        };
+        if self.sidebar.is_visible() {
+            self.sidebar.view(&self.core).render(canvas.cut_left(SIDEBAR_WIDTH));
+        }
+        self.render_groups(canvas, search.as_ref());
+        if let Some(ref overlay) = self.overlay {
+            let view = overlay.view(&self.core);
+            // overlay is centered horizontally at the top of the window
+            let width = min(max(view.width(), OVERLAY_WIDTH), screen.width());
+            let x = (screen.width() - width) / 2;
+            view.render(screen.region(x, 1, x + width, 1 + view.height()));
+        }
+    }
+
+    fn sidebar_width(&self) -> usize {
+        if self.sidebar.is_visible() { SIDEBAR_WIDTH } else { 0 }
+    }
+
+    /// Returns canvas of the group and canvas of the separator on the left side of it.
+    fn group_canvas(&self, canvas: Canvas, index: usize) -> (Canvas, Option<Canvas>) {
+        let (x1, y1, x2, y2) = self.core.layout.cell_rect(index, canvas.width(), canvas.height());
+        let mut canvas = canvas.region(x1, y1, x2, y2);
+        // separate groups that placed side by side
+        let separator = if x1 > 0 { Some(canvas.cut_left(1)) } else { None };
+        (canvas, separator)
     }
+
+    /// Draws the groups. Matches of the search are highlighted in the active view.
+    fn render_groups(&self, canvas: Canvas, search: Option<&Search>) {
+        for (index, group) in self.core.groups.iter().enumerate() {
+            let (mut canvas, separator) = self.group_canvas(canvas, index);
+            if let Some(separator) = separator {
+                separator.style(TABS_LOW_STYLE);
+                separator.fill_char('│');
+            }
+            let is_focused = index == self.core.active_group();
+            TabsView::new(group, is_focused).render(canvas.cut_top(1));
+            match group.active_view() {
+                Some(view) => {
+                    let search = if is_focused { search } else { None };
+                    self.editor.view((&self.core, view, search)).render(canvas)
+                },
+                None => {
+                    canvas.style(EDITOR_STYLE);
+                    canvas.fill();
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the snippet fields like `${0:txt}` by their default text.
+fn expand_snippet(snippet: &str) -> String {
+    let mut result = String::new();
+    let mut chars = snippet.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' && chars.peek() == Some(&'{') {
+            let field: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+            result.push_str(field.splitn(2, ':').nth(1).unwrap_or(""));
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Returns paths from the array argument of the sidebar command.
+fn path_args(args: &SettingsObject, name: &str) -> Vec<PathBuf> {
+    match args.get(name).and_then(Settings::as_array) {
+        Some(paths) => paths.iter().filter_map(Settings::as_string).map(PathBuf::from).collect(),
+        None => Vec::new()
+    }
+}
+
+/// Shows the folder in the file manager, its output is discarded to keep the screen intact.
+fn open_folder(path: &Path) {
+    let result = Process::new(FILE_MANAGER)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(err) = result {
+        error!("Can't open folder {:?}: {:?}", path, err);
+    }
+}
+
+/// Adds position of the mouse event to the command arguments.
+fn with_event(mut command: Command, x: usize, y: usize) -> Command {
+    let mut event = SettingsObject::new();
+    event.insert("x".to_owned(), Settings::U64(x as u64));
+    event.insert("y".to_owned(), Settings::U64(y as u64));
+    command.args.insert("event".to_owned(), Settings::Object(event));
+    command
 }